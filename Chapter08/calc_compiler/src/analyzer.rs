@@ -1,103 +1,629 @@
+use crate::diagnostics::Diagnostic;
 use crate::parser::{
-    ExprOperator, ParsedExpr, ParsedFactor, ParsedProgram, ParsedStatement, ParsedTerm,
-    TermOperator,
+    ComparisonOperator, ExprOperator, ParsedCondition, ParsedExpr, ParsedFactor, ParsedProgram,
+    ParsedStatement, ParsedTerm, TermOperator,
 };
 use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
 
 extern crate nom;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum AnalyzedFactor {
-    Literal(f64),
+    Literal(Value),
     Identifier(usize),
+    Element(usize, Box<AnalyzedExpr>),
     SubExpression(Box<AnalyzedExpr>),
+    FunctionCall(usize, Vec<AnalyzedExpr>),
+    BuiltinCall(BuiltinFunction, Vec<AnalyzedExpr>),
+    NativeCall(usize, Vec<AnalyzedExpr>),
+    Negation(Box<AnalyzedFactor>),
+    Power(Box<AnalyzedFactor>, Box<AnalyzedFactor>),
 }
 
-pub type AnalyzedTerm = (AnalyzedFactor, Vec<(TermOperator, AnalyzedFactor)>);
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BuiltinFunction {
+    Sqrt,
+    Sin,
+    Abs,
+    Min,
+    Max,
+}
+
+impl BuiltinFunction {
+    pub const ALL: [BuiltinFunction; 5] = [
+        BuiltinFunction::Sqrt,
+        BuiltinFunction::Sin,
+        BuiltinFunction::Abs,
+        BuiltinFunction::Min,
+        BuiltinFunction::Max,
+    ];
+    pub fn find(name: &str) -> Option<BuiltinFunction> {
+        BuiltinFunction::ALL
+            .iter()
+            .copied()
+            .find(|builtin| builtin.name() == name)
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinFunction::Sqrt => "sqrt",
+            BuiltinFunction::Sin => "sin",
+            BuiltinFunction::Abs => "abs",
+            BuiltinFunction::Min => "min",
+            BuiltinFunction::Max => "max",
+        }
+    }
+    pub fn parameter_count(self) -> usize {
+        match self {
+            BuiltinFunction::Sqrt | BuiltinFunction::Sin | BuiltinFunction::Abs => 1,
+            BuiltinFunction::Min | BuiltinFunction::Max => 2,
+        }
+    }
+    // The arguments are numbers, and only abs, min and max preserve integers.
+    pub fn result_type(self, argument_types: &[Type]) -> Type {
+        match self {
+            BuiltinFunction::Sqrt | BuiltinFunction::Sin => Type::Float,
+            BuiltinFunction::Abs => argument_types[0],
+            BuiltinFunction::Min | BuiltinFunction::Max => {
+                argument_types[0].numeric_result(argument_types[1])
+            }
+        }
+    }
+    pub fn apply(self, arguments: &[Value]) -> Result<Value, String> {
+        match (self, arguments) {
+            (BuiltinFunction::Abs, [Value::Int(value)]) => value
+                .checked_abs()
+                .map(Value::Int)
+                .ok_or_else(|| "Error: Integer overflow.".to_string()),
+            (BuiltinFunction::Min, [Value::Int(first), Value::Int(second)]) => {
+                Ok(Value::Int(*first.min(second)))
+            }
+            (BuiltinFunction::Max, [Value::Int(first), Value::Int(second)]) => {
+                Ok(Value::Int(*first.max(second)))
+            }
+            _ => {
+                let first = arguments[0].as_float();
+                Ok(Value::Float(match self {
+                    BuiltinFunction::Sqrt => first.sqrt(),
+                    BuiltinFunction::Sin => first.sin(),
+                    BuiltinFunction::Abs => first.abs(),
+                    BuiltinFunction::Min => first.min(arguments[1].as_float()),
+                    BuiltinFunction::Max => first.max(arguments[1].as_float()),
+                }))
+            }
+        }
+    }
+}
+
+// The last item of terms and expressions is their type.
+pub type AnalyzedTerm = (AnalyzedFactor, Vec<(TermOperator, AnalyzedFactor)>, Type);
+
+pub type AnalyzedExpr = (AnalyzedTerm, Vec<(ExprOperator, AnalyzedTerm)>, Type);
 
-pub type AnalyzedExpr = (AnalyzedTerm, Vec<(ExprOperator, AnalyzedTerm)>);
+pub type AnalyzedCondition = (AnalyzedExpr, ComparisonOperator, AnalyzedExpr);
 
 #[derive(Debug)]
 pub enum AnalyzedStatement {
     Declaration(usize),
     InputOperation(usize),
-    OutputOperation(AnalyzedExpr),
+    // The array, and the index of the element.
+    ElementInput(usize, AnalyzedExpr),
+    OutputOperation(Vec<AnalyzedExpr>),
     Assignment(usize, AnalyzedExpr),
+    // The array, the index of the element, and the assigned value.
+    ElementAssignment(usize, AnalyzedExpr, AnalyzedExpr),
+    IfElse(AnalyzedCondition, AnalyzedProgram, AnalyzedProgram),
+    WhileLoop(AnalyzedCondition, AnalyzedProgram),
+    FunctionDefinition(usize),
+    Return(usize, AnalyzedExpr),
 }
 
-pub type AnalyzedProgram = Vec<AnalyzedStatement>;
+// Every statement keeps the line where it begins in the source.
+pub type AnalyzedProgram = Vec<(AnalyzedStatement, usize)>;
 
-pub fn analyze_program(
+pub const MAX_ARRAY_SIZE: usize = 65536;
+
+pub fn factor_type(variables: &SymbolTable, analyzed_factor: &AnalyzedFactor) -> Type {
+    match analyzed_factor {
+        AnalyzedFactor::Literal(value) => value.get_type(),
+        AnalyzedFactor::Identifier(handle) | AnalyzedFactor::Element(handle, _) => {
+            variables.get_type(*handle)
+        }
+        AnalyzedFactor::SubExpression(expr) => expr.2,
+        AnalyzedFactor::FunctionCall(handle, _) => variables.get_function(*handle).return_type,
+        AnalyzedFactor::NativeCall(handle, _) => variables.get_native(*handle).return_type,
+        AnalyzedFactor::BuiltinCall(builtin, arguments) => builtin.result_type(
+            &arguments
+                .iter()
+                .map(|argument| argument.2)
+                .collect::<Vec<Type>>(),
+        ),
+        AnalyzedFactor::Negation(factor) => factor_type(variables, factor),
+        AnalyzedFactor::Power(base, exponent) => {
+            factor_type(variables, base).numeric_result(factor_type(variables, exponent))
+        }
+    }
+}
+
+pub fn term_operator_symbol(operator: TermOperator) -> &'static str {
+    match operator {
+        TermOperator::Multiply => "*",
+        TermOperator::Divide => "/",
+        TermOperator::Remainder => "%",
+    }
+}
+
+pub fn expr_operator_symbol(operator: ExprOperator) -> &'static str {
+    match operator {
+        ExprOperator::Add => "+",
+        ExprOperator::Subtract => "-",
+    }
+}
+
+pub fn comparison_operator_symbol(operator: ComparisonOperator) -> &'static str {
+    match operator {
+        ComparisonOperator::Equal => "==",
+        ComparisonOperator::NotEqual => "!=",
+        ComparisonOperator::Less => "<",
+        ComparisonOperator::LessOrEqual => "<=",
+        ComparisonOperator::Greater => ">",
+        ComparisonOperator::GreaterOrEqual => ">=",
+    }
+}
+
+// Analysis goes on after an invalid statement,
+// so that all the errors of the program are reported.
+pub fn analyze_program<'a>(
     variables: &mut SymbolTable,
-    parsed_program: &ParsedProgram,
-) -> Result<AnalyzedProgram, String> {
-    let mut analyzed_program = AnalyzedProgram::new();
-    for statement in parsed_program {
-        analyzed_program.push(analyze_statement(variables, statement)?);
+    parsed_program: &ParsedProgram<'a>,
+) -> Result<AnalyzedProgram, Vec<Diagnostic<'a>>> {
+    analyze_statements(variables, parsed_program, false)
+}
+
+fn analyze_block<'a>(
+    variables: &mut SymbolTable,
+    parsed_block: &ParsedProgram<'a>,
+) -> Result<AnalyzedProgram, Vec<Diagnostic<'a>>> {
+    variables.open_scope();
+    let analyzed_block = analyze_statements(variables, parsed_block, true);
+    variables.close_scope();
+    analyzed_block
+}
+
+fn analyze_statements<'a>(
+    variables: &mut SymbolTable,
+    parsed_block: &ParsedProgram<'a>,
+    inside_block: bool,
+) -> Result<AnalyzedProgram, Vec<Diagnostic<'a>>> {
+    let mut analyzed_block = AnalyzedProgram::new();
+    let mut diagnostics = Vec::<Diagnostic>::new();
+    for (statement, line) in parsed_block {
+        match analyze_statement(variables, statement, inside_block) {
+            Ok(analyzed_statement) => analyzed_block.push((analyzed_statement, *line)),
+            Err(mut errors) => diagnostics.append(&mut errors),
+        }
+    }
+    if diagnostics.is_empty() {
+        Ok(analyzed_block)
+    } else {
+        Err(diagnostics)
     }
-    Ok(analyzed_program)
 }
 
-fn analyze_factor(
+// Parameters without a type annotation are floats.
+fn analyze_function<'a>(
     variables: &mut SymbolTable,
-    parsed_factor: &ParsedFactor,
-) -> Result<AnalyzedFactor, String> {
+    handle: usize,
+    parameters: &[(&'a str, Option<Type>)],
+    parsed_body: &ParsedProgram<'a>,
+) -> Result<(), Vec<Diagnostic<'a>>> {
+    let previous_frame_start = variables.open_function_scope(handle);
+    let mut diagnostics = Vec::<Diagnostic>::new();
+    for (parameter, parameter_type) in parameters {
+        if let Err(err) = variables.insert_symbol(parameter, parameter_type.unwrap_or(Type::Float))
+        {
+            diagnostics.push(err);
+        }
+    }
+    let analyzed_body = analyze_statements(variables, parsed_body, true);
+    variables.close_function_scope(previous_frame_start);
+    match analyzed_body {
+        Ok(analyzed_body) if diagnostics.is_empty() => {
+            variables.set_function_body(handle, analyzed_body);
+            Ok(())
+        }
+        Ok(_) => Err(diagnostics),
+        Err(mut errors) => {
+            diagnostics.append(&mut errors);
+            Err(diagnostics)
+        }
+    }
+}
+
+// Replaces the escape sequences of a string literal.
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(escaped) => result.push(escaped),
+                None => {}
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    result
+}
+
+fn check_type(span: &str, expected: Type, found: Type) -> Result<(), Diagnostic<'_>> {
+    if expected.accepts(found) {
+        Ok(())
+    } else {
+        Err(Diagnostic::TypeMismatch(span, expected, found))
+    }
+}
+
+// Arrays can be used only through their elements.
+fn find_variable<'a>(variables: &SymbolTable, name: &'a str) -> Result<usize, Diagnostic<'a>> {
+    let handle = variables.find_symbol(name)?;
+    match variables.array_size(handle) {
+        Some(_) => Err(Diagnostic::MissingIndex(name)),
+        None => Ok(handle),
+    }
+}
+
+fn find_array<'a>(variables: &SymbolTable, name: &'a str) -> Result<usize, Diagnostic<'a>> {
+    let handle = variables.find_symbol(name)?;
+    match variables.array_size(handle) {
+        Some(_) => Ok(handle),
+        None => Err(Diagnostic::NotAnArray(name)),
+    }
+}
+
+fn analyze_index<'a>(
+    variables: &mut SymbolTable,
+    index: &ParsedExpr<'a>,
+) -> Result<AnalyzedExpr, Diagnostic<'a>> {
+    let analyzed_index = analyze_expr(variables, index)?;
+    check_type(index.2, Type::Int, analyzed_index.2)?;
+    Ok(analyzed_index)
+}
+
+// The span is the source code of the expression containing the factor.
+fn analyze_factor<'a>(
+    variables: &mut SymbolTable,
+    parsed_factor: &ParsedFactor<'a>,
+    span: &'a str,
+) -> Result<AnalyzedFactor, Diagnostic<'a>> {
     match parsed_factor {
-        ParsedFactor::Literal(value) => Ok(AnalyzedFactor::Literal(*value)),
+        ParsedFactor::Literal(value) => Ok(AnalyzedFactor::Literal(Value::Float(*value))),
+        ParsedFactor::IntegerLiteral(value) => Ok(AnalyzedFactor::Literal(Value::Int(*value))),
+        ParsedFactor::BooleanLiteral(value) => Ok(AnalyzedFactor::Literal(Value::Bool(*value))),
+        ParsedFactor::StringLiteral(text) => {
+            Ok(AnalyzedFactor::Literal(Value::Str(unescape(text))))
+        }
         ParsedFactor::Identifier(name) => {
-            Ok(AnalyzedFactor::Identifier(variables.find_symbol(name)?))
+            Ok(AnalyzedFactor::Identifier(find_variable(variables, name)?))
+        }
+        ParsedFactor::Element(name, index) => {
+            let handle = find_array(variables, name)?;
+            Ok(AnalyzedFactor::Element(
+                handle,
+                Box::new(analyze_index(variables, index)?),
+            ))
         }
         ParsedFactor::SubExpression(expr) => Ok(AnalyzedFactor::SubExpression(
             Box::<AnalyzedExpr>::new(analyze_expr(variables, expr)?),
         )),
+        ParsedFactor::FunctionCall(name, arguments) => {
+            if let Some(builtin) = BuiltinFunction::find(name) {
+                if builtin.parameter_count() != arguments.len() {
+                    return Err(Diagnostic::WrongArgumentCount(
+                        name,
+                        builtin.parameter_count(),
+                        arguments.len(),
+                    ));
+                }
+                let analyzed_arguments = analyze_arguments(variables, arguments)?;
+                for (argument, analyzed_argument) in arguments.iter().zip(&analyzed_arguments) {
+                    if !analyzed_argument.2.is_numeric() {
+                        return Err(Diagnostic::InvalidOperand(
+                            argument.2,
+                            builtin.name(),
+                            analyzed_argument.2,
+                        ));
+                    }
+                }
+                return Ok(AnalyzedFactor::BuiltinCall(builtin, analyzed_arguments));
+            }
+            if let Some(handle) = variables.find_native(name) {
+                let parameter_types = variables.get_native(handle).parameter_types.clone();
+                if parameter_types.len() != arguments.len() {
+                    return Err(Diagnostic::WrongArgumentCount(
+                        name,
+                        parameter_types.len(),
+                        arguments.len(),
+                    ));
+                }
+                let analyzed_arguments = analyze_arguments(variables, arguments)?;
+                for ((argument, analyzed_argument), parameter_type) in arguments
+                    .iter()
+                    .zip(&analyzed_arguments)
+                    .zip(parameter_types)
+                {
+                    check_type(argument.2, parameter_type, analyzed_argument.2)?;
+                }
+                return Ok(AnalyzedFactor::NativeCall(handle, analyzed_arguments));
+            }
+            let handle = variables.find_function(name, arguments.len())?;
+            let analyzed_arguments = analyze_arguments(variables, arguments)?;
+            let parameters = variables.get_function(handle).locals.start;
+            for (index, (argument, analyzed_argument)) in
+                arguments.iter().zip(&analyzed_arguments).enumerate()
+            {
+                check_type(
+                    argument.2,
+                    variables.get_type(parameters + index),
+                    analyzed_argument.2,
+                )?;
+            }
+            Ok(AnalyzedFactor::FunctionCall(handle, analyzed_arguments))
+        }
+        ParsedFactor::Negation(factor) => {
+            let analyzed_factor = analyze_factor(variables, factor, span)?;
+            let factor_type = factor_type(variables, &analyzed_factor);
+            if !factor_type.is_numeric() {
+                return Err(Diagnostic::InvalidOperand(span, "-", factor_type));
+            }
+            Ok(AnalyzedFactor::Negation(Box::new(analyzed_factor)))
+        }
+        ParsedFactor::Power(base, exponent) => {
+            let analyzed_base = analyze_factor(variables, base, span)?;
+            let analyzed_exponent = analyze_factor(variables, exponent, span)?;
+            let base_type = factor_type(variables, &analyzed_base);
+            let exponent_type = factor_type(variables, &analyzed_exponent);
+            if !base_type.is_numeric() || !exponent_type.is_numeric() {
+                return Err(Diagnostic::IncompatibleOperands(
+                    span,
+                    "^",
+                    base_type,
+                    exponent_type,
+                ));
+            }
+            Ok(AnalyzedFactor::Power(
+                Box::new(analyzed_base),
+                Box::new(analyzed_exponent),
+            ))
+        }
+    }
+}
+
+fn analyze_arguments<'a>(
+    variables: &mut SymbolTable,
+    arguments: &[ParsedExpr<'a>],
+) -> Result<Vec<AnalyzedExpr>, Diagnostic<'a>> {
+    let mut analyzed_arguments = Vec::<AnalyzedExpr>::new();
+    for argument in arguments {
+        analyzed_arguments.push(analyze_expr(variables, argument)?);
     }
+    Ok(analyzed_arguments)
 }
 
-fn analyze_term(
+fn analyze_term<'a>(
     variables: &mut SymbolTable,
-    parsed_term: &ParsedTerm,
-) -> Result<AnalyzedTerm, String> {
-    let first_factor = analyze_factor(variables, &parsed_term.0)?;
+    parsed_term: &ParsedTerm<'a>,
+    span: &'a str,
+) -> Result<AnalyzedTerm, Diagnostic<'a>> {
+    let first_factor = analyze_factor(variables, &parsed_term.0, span)?;
+    let mut term_type = factor_type(variables, &first_factor);
     let mut other_factors = Vec::<(TermOperator, AnalyzedFactor)>::new();
     for factor in &parsed_term.1 {
-        other_factors.push((factor.0, analyze_factor(variables, &factor.1)?));
+        let analyzed_factor = analyze_factor(variables, &factor.1, span)?;
+        let other_type = factor_type(variables, &analyzed_factor);
+        if !term_type.is_numeric() || !other_type.is_numeric() {
+            return Err(Diagnostic::IncompatibleOperands(
+                span,
+                term_operator_symbol(factor.0),
+                term_type,
+                other_type,
+            ));
+        }
+        term_type = term_type.term_result(factor.0, other_type);
+        other_factors.push((factor.0, analyzed_factor));
     }
-    Ok((first_factor, other_factors))
+    Ok((first_factor, other_factors, term_type))
 }
 
-fn analyze_expr(
+// Strings can be concatenated using the "+" operator.
+pub fn analyze_expr<'a>(
     variables: &mut SymbolTable,
-    parsed_expr: &ParsedExpr,
-) -> Result<AnalyzedExpr, String> {
-    let first_term = analyze_term(variables, &parsed_expr.0)?;
+    parsed_expr: &ParsedExpr<'a>,
+) -> Result<AnalyzedExpr, Diagnostic<'a>> {
+    let span = parsed_expr.2;
+    let first_term = analyze_term(variables, &parsed_expr.0, span)?;
+    let mut expr_type = first_term.2;
     let mut other_terms = Vec::<(ExprOperator, AnalyzedTerm)>::new();
     for term in &parsed_expr.1 {
-        other_terms.push((term.0, analyze_term(variables, &term.1)?));
+        let analyzed_term = analyze_term(variables, &term.1, span)?;
+        let other_type = analyzed_term.2;
+        expr_type = if expr_type.is_numeric() && other_type.is_numeric() {
+            expr_type.numeric_result(other_type)
+        } else if term.0 == ExprOperator::Add && expr_type == Type::Str && other_type == Type::Str {
+            Type::Str
+        } else {
+            return Err(Diagnostic::IncompatibleOperands(
+                span,
+                expr_operator_symbol(term.0),
+                expr_type,
+                other_type,
+            ));
+        };
+        other_terms.push((term.0, analyzed_term));
     }
-    Ok((first_term, other_terms))
+    Ok((first_term, other_terms, expr_type))
 }
 
-fn analyze_statement(
+// A boolean expression used as a condition is compared with true.
+fn analyze_condition<'a>(
     variables: &mut SymbolTable,
-    parsed_statement: &ParsedStatement,
-) -> Result<AnalyzedStatement, String> {
+    parsed_condition: &ParsedCondition<'a>,
+) -> Result<AnalyzedCondition, Diagnostic<'a>> {
+    let left = analyze_expr(variables, &parsed_condition.0)?;
+    match &parsed_condition.1 {
+        Some((operator, parsed_right)) => {
+            let right = analyze_expr(variables, parsed_right)?;
+            let comparable = (left.2.is_numeric() && right.2.is_numeric())
+                || (left.2 == Type::Str && right.2 == Type::Str)
+                || (left.2 == Type::Bool
+                    && right.2 == Type::Bool
+                    && (*operator == ComparisonOperator::Equal
+                        || *operator == ComparisonOperator::NotEqual));
+            if !comparable {
+                return Err(Diagnostic::IncompatibleOperands(
+                    parsed_condition.0 .2,
+                    comparison_operator_symbol(*operator),
+                    left.2,
+                    right.2,
+                ));
+            }
+            Ok((left, *operator, right))
+        }
+        None => {
+            check_type(parsed_condition.0 .2, Type::Bool, left.2)?;
+            let true_literal = (
+                (
+                    AnalyzedFactor::Literal(Value::Bool(true)),
+                    Vec::new(),
+                    Type::Bool,
+                ),
+                Vec::new(),
+                Type::Bool,
+            );
+            Ok((left, ComparisonOperator::Equal, true_literal))
+        }
+    }
+}
+
+fn analyze_statement<'a>(
+    variables: &mut SymbolTable,
+    parsed_statement: &ParsedStatement<'a>,
+    inside_block: bool,
+) -> Result<AnalyzedStatement, Vec<Diagnostic<'a>>> {
     match parsed_statement {
         ParsedStatement::Assignment(identifier, expr) => {
-            let handle = variables.find_symbol(identifier)?;
-            let analyzed_expr = analyze_expr(variables, expr)?;
+            let handle = find_variable(variables, identifier).map_err(|err| vec![err])?;
+            let analyzed_expr = analyze_expr(variables, expr).map_err(|err| vec![err])?;
+            check_type(expr.2, variables.get_type(handle), analyzed_expr.2)
+                .map_err(|err| vec![err])?;
             Ok(AnalyzedStatement::Assignment(handle, analyzed_expr))
         }
-        ParsedStatement::Declaration(identifier) => {
-            let handle = variables.insert_symbol(identifier)?;
+        ParsedStatement::ElementAssignment(identifier, index, expr) => {
+            let handle = find_array(variables, identifier).map_err(|err| vec![err])?;
+            let analyzed_index = analyze_index(variables, index).map_err(|err| vec![err])?;
+            let analyzed_expr = analyze_expr(variables, expr).map_err(|err| vec![err])?;
+            check_type(expr.2, variables.get_type(handle), analyzed_expr.2)
+                .map_err(|err| vec![err])?;
+            Ok(AnalyzedStatement::ElementAssignment(
+                handle,
+                analyzed_index,
+                analyzed_expr,
+            ))
+        }
+        ParsedStatement::Declaration(identifier, declared_type) => {
+            let handle = variables
+                .insert_symbol(identifier, declared_type.unwrap_or(Type::Float))
+                .map_err(|err| vec![err])?;
+            Ok(AnalyzedStatement::Declaration(handle))
+        }
+        ParsedStatement::ArrayDeclaration(identifier, size, declared_type) => {
+            let size = match size.parse::<usize>() {
+                Ok(value) if (1..=MAX_ARRAY_SIZE).contains(&value) => value,
+                _ => return Err(vec![Diagnostic::InvalidArraySize(size)]),
+            };
+            let handle = variables
+                .insert_array(identifier, declared_type.unwrap_or(Type::Float), size)
+                .map_err(|err| vec![err])?;
             Ok(AnalyzedStatement::Declaration(handle))
         }
         ParsedStatement::InputOperation(identifier) => {
-            let handle = variables.find_symbol(identifier)?;
+            let handle = find_variable(variables, identifier).map_err(|err| vec![err])?;
             Ok(AnalyzedStatement::InputOperation(handle))
         }
-        ParsedStatement::OutputOperation(expr) => {
-            let analyzed_expr = analyze_expr(variables, expr)?;
-            Ok(AnalyzedStatement::OutputOperation(analyzed_expr))
+        ParsedStatement::ElementInput(identifier, index) => {
+            let handle = find_array(variables, identifier).map_err(|err| vec![err])?;
+            let analyzed_index = analyze_index(variables, index).map_err(|err| vec![err])?;
+            Ok(AnalyzedStatement::ElementInput(handle, analyzed_index))
+        }
+        ParsedStatement::OutputOperation(exprs) => {
+            let analyzed_exprs = analyze_arguments(variables, exprs).map_err(|err| vec![err])?;
+            Ok(AnalyzedStatement::OutputOperation(analyzed_exprs))
+        }
+        ParsedStatement::IfElse(condition, then_block, else_block) => {
+            let analyzed_condition = analyze_condition(variables, condition);
+            let analyzed_then_block = analyze_block(variables, then_block);
+            let analyzed_else_block = analyze_block(variables, else_block);
+            match (analyzed_condition, analyzed_then_block, analyzed_else_block) {
+                (Ok(condition), Ok(then_block), Ok(else_block)) => {
+                    Ok(AnalyzedStatement::IfElse(condition, then_block, else_block))
+                }
+                (condition, then_block, else_block) => {
+                    let mut diagnostics = Vec::<Diagnostic>::new();
+                    diagnostics.extend(condition.err());
+                    diagnostics.extend(then_block.err().unwrap_or_default());
+                    diagnostics.extend(else_block.err().unwrap_or_default());
+                    Err(diagnostics)
+                }
+            }
+        }
+        ParsedStatement::WhileLoop(condition, body) => {
+            let analyzed_condition = analyze_condition(variables, condition);
+            let analyzed_body = analyze_block(variables, body);
+            match (analyzed_condition, analyzed_body) {
+                (Ok(condition), Ok(body)) => Ok(AnalyzedStatement::WhileLoop(condition, body)),
+                (condition, body) => {
+                    let mut diagnostics = Vec::<Diagnostic>::new();
+                    diagnostics.extend(condition.err());
+                    diagnostics.extend(body.err().unwrap_or_default());
+                    Err(diagnostics)
+                }
+            }
+        }
+        ParsedStatement::FunctionDefinition(identifier, parameters, return_type, body) => {
+            if inside_block {
+                return Err(vec![Diagnostic::FunctionInsideBlock(identifier)]);
+            }
+            if BuiltinFunction::find(identifier).is_some()
+                || variables.find_native(identifier).is_some()
+            {
+                return Err(vec![Diagnostic::BuiltinRedefinition(identifier)]);
+            }
+            let handle = variables
+                .insert_function(
+                    identifier,
+                    parameters.len(),
+                    return_type.unwrap_or(Type::Float),
+                )
+                .map_err(|err| vec![err])?;
+            analyze_function(variables, handle, parameters, body)?;
+            Ok(AnalyzedStatement::FunctionDefinition(handle))
+        }
+        ParsedStatement::Return(keyword, expr) => {
+            let handle = match variables.current_function() {
+                Some(handle) => handle,
+                None => return Err(vec![Diagnostic::ReturnOutsideFunction(keyword)]),
+            };
+            let analyzed_expr = analyze_expr(variables, expr).map_err(|err| vec![err])?;
+            check_type(
+                expr.2,
+                variables.get_function(handle).return_type,
+                analyzed_expr.2,
+            )
+            .map_err(|err| vec![err])?;
+            Ok(AnalyzedStatement::Return(handle, analyzed_expr))
         }
     }
 }
@@ -0,0 +1,901 @@
+use crate::analyzer::{
+    AnalyzedCondition, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm, BuiltinFunction,
+};
+use crate::parser::{ComparisonOperator, ExprOperator, TermOperator};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+use nom_byte_machine::instructions::Instruction;
+
+const INPUT_BUFFER_SIZE: u16 = 8;
+const DIGIT_BUFFER_SIZE: u16 = 6;
+const STACK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    MinusCharacter,
+    NewlineCharacter,
+    StackPointer,
+    ReturnValue,
+    Number,
+    Sign,
+    Pointer,
+    Count,
+    Dividend,
+    Divisor,
+    Quotient,
+    Base,
+    Exponent,
+    Power,
+    InputBuffer,
+    DigitBufferEnd,
+    StackStart,
+}
+
+// Operand addresses are symbolic until the layout of the image is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Address {
+    Immediate(u16),
+    Constant(u16),
+    Cell(Cell),
+    Variable(usize),
+    Temporary(usize, usize),
+    Text(usize),
+    Label(usize),
+    LabelOperand(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Label(usize),
+    ByteInstruction(Instruction),
+    WordInstruction(fn(u16) -> Instruction, Address),
+    SaveFrame(usize),
+    RestoreFrame(usize),
+}
+
+// Every routine is called by storing the return address
+// into the operand of the jump that ends it.
+#[derive(Clone, Copy)]
+struct Routine {
+    entry: usize,
+    exit: usize,
+}
+
+struct Generator<'a> {
+    variables: &'a SymbolTable,
+    items: Vec<Item>,
+    label_count: usize,
+    constants: Vec<u16>,
+    texts: Vec<String>,
+    context: usize,
+    temporary_depth: usize,
+    temporary_counts: Vec<usize>,
+    functions: Vec<Routine>,
+    read_number: Routine,
+    print_number: Routine,
+    divide: Routine,
+    power: Routine,
+}
+
+fn array_error() -> String {
+    "Error: The byte machine does not support arrays.".to_string()
+}
+
+fn float_error() -> String {
+    "Error: The byte machine does not support floats: \
+     declare the variables, the parameters and the results as int."
+        .to_string()
+}
+
+// Whether a variable or a function result has the given type.
+fn has_type(variables: &SymbolTable, value_type: Type) -> bool {
+    (0..variables.symbol_count()).any(|handle| variables.get_type(handle) == value_type)
+        || (0..variables.function_count())
+            .any(|handle| variables.get_function(handle).return_type == value_type)
+}
+
+// Translates the program to an image for the byte machine,
+// whose first word is the size of the process.
+// Numbers are 16-bit signed integers, and remainders have the sign of the dividend.
+// Floats are rejected, as they would be truncated silently:
+// they include untyped variables and divisions.
+// Booleans are 0 or 1, and strings can only be printed as literals.
+pub fn translate_to_byte_machine(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Result<Vec<u8>, String> {
+    if has_type(variables, Type::Str) {
+        return Err("Error: The byte machine does not support string variables.".to_string());
+    }
+    if has_type(variables, Type::Float) {
+        return Err(float_error());
+    }
+    if (0..variables.symbol_count()).any(|handle| variables.array_size(handle).is_some()) {
+        return Err(array_error());
+    }
+    let mut g = Generator {
+        variables,
+        items: Vec::<Item>::new(),
+        label_count: 0,
+        constants: Vec::<u16>::new(),
+        texts: Vec::<String>::new(),
+        context: 0,
+        temporary_depth: 0,
+        temporary_counts: vec![0; variables.function_count() + 1],
+        functions: Vec::<Routine>::new(),
+        read_number: Routine { entry: 0, exit: 0 },
+        print_number: Routine { entry: 0, exit: 0 },
+        divide: Routine { entry: 0, exit: 0 },
+        power: Routine { entry: 0, exit: 0 },
+    };
+    for _ in 0..variables.function_count() {
+        let routine = g.new_routine();
+        g.functions.push(routine);
+    }
+    g.read_number = g.new_routine();
+    g.print_number = g.new_routine();
+    g.divide = g.new_routine();
+    g.power = g.new_routine();
+
+    g.emit(Instruction::Set, Address::Cell(Cell::StackStart));
+    g.emit(Instruction::Store, Address::Cell(Cell::StackPointer));
+    g.generate_block(analyzed_program)?;
+    g.items
+        .push(Item::ByteInstruction(Instruction::Terminate(0)));
+    for handle in 0..variables.function_count() {
+        g.generate_function(handle)?;
+    }
+    g.generate_read_number();
+    g.generate_print_number();
+    g.generate_divide();
+    g.generate_power();
+    g.assemble()
+}
+
+impl<'a> Generator<'a> {
+    fn new_label(&mut self) -> usize {
+        self.label_count += 1;
+        self.label_count - 1
+    }
+    fn new_routine(&mut self) -> Routine {
+        Routine {
+            entry: self.new_label(),
+            exit: self.new_label(),
+        }
+    }
+    fn place_label(&mut self, label: usize) {
+        self.items.push(Item::Label(label));
+    }
+    fn emit(&mut self, instruction: fn(u16) -> Instruction, address: Address) {
+        self.items.push(Item::WordInstruction(instruction, address));
+    }
+    fn constant(&mut self, value: u16) -> Address {
+        if !self.constants.contains(&value) {
+            self.constants.push(value);
+        }
+        Address::Constant(value)
+    }
+    fn text(&mut self, text: &str) -> Address {
+        let index = match self.texts.iter().position(|item| item == text) {
+            Some(index) => index,
+            None => {
+                self.texts.push(text.to_string());
+                self.texts.len() - 1
+            }
+        };
+        Address::Text(index)
+    }
+    // The output instruction prints at most 255 bytes.
+    fn output_text(&mut self, text: &str) {
+        let address = self.text(text);
+        let mut offset = 0;
+        for chunk in text.as_bytes().chunks(255) {
+            self.emit(Instruction::Set, address);
+            if offset > 0 {
+                let offset_constant = self.constant(offset);
+                self.emit(Instruction::Add, offset_constant);
+            }
+            self.items.push(Item::ByteInstruction(
+                Instruction::Output(chunk.len() as u8),
+            ));
+            offset += chunk.len() as u16;
+        }
+    }
+    fn allocate_temporary(&mut self) -> Address {
+        let temporary = Address::Temporary(self.context, self.temporary_depth);
+        self.temporary_depth += 1;
+        let count = &mut self.temporary_counts[self.context];
+        *count = (*count).max(self.temporary_depth);
+        temporary
+    }
+    fn free_temporaries(&mut self, count: usize) {
+        self.temporary_depth -= count;
+    }
+    fn call(&mut self, routine: Routine) {
+        let return_label = self.new_label();
+        self.emit(Instruction::Set, Address::Label(return_label));
+        self.emit(Instruction::Store, Address::LabelOperand(routine.exit));
+        self.emit(Instruction::Jump, Address::Label(routine.entry));
+        self.place_label(return_label);
+    }
+    fn begin_routine(&mut self, routine: Routine) {
+        self.place_label(routine.entry);
+    }
+    fn end_routine(&mut self, routine: Routine) {
+        self.place_label(routine.exit);
+        self.emit(Instruction::Jump, Address::Immediate(0));
+    }
+
+    fn generate_factor(&mut self, analyzed_factor: &AnalyzedFactor) -> Result<(), String> {
+        match analyzed_factor {
+            AnalyzedFactor::Literal(value) => {
+                let number = match value {
+                    Value::Int(value) => *value,
+                    Value::Bool(value) => *value as i64,
+                    Value::Float(_) => return Err(float_error()),
+                    Value::Str(_) => {
+                        return Err("Error: The byte machine supports strings \
+                                    only as output items."
+                            .to_string())
+                    }
+                    Value::Array(_) => return Err(array_error()),
+                };
+                if !(-32768..=32767).contains(&number) {
+                    return Err(format!(
+                        "Error: The byte machine supports only integers \
+                         from -32768 to 32767, not {}.",
+                        value
+                    ));
+                }
+                self.emit(Instruction::Set, Address::Immediate(number as i16 as u16));
+            }
+            AnalyzedFactor::Identifier(handle) => {
+                self.emit(Instruction::Load, Address::Variable(*handle))
+            }
+            AnalyzedFactor::Element(..) => return Err(array_error()),
+            AnalyzedFactor::SubExpression(expr) => self.generate_expr(expr)?,
+            AnalyzedFactor::FunctionCall(handle, arguments) => {
+                self.generate_function_call(*handle, arguments)?
+            }
+            AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+                self.generate_builtin_call(*builtin, arguments)?
+            }
+            AnalyzedFactor::NativeCall(..) => {
+                return Err("Error: The byte machine does not support native functions.".to_string())
+            }
+            AnalyzedFactor::Negation(factor) => {
+                self.generate_factor(factor)?;
+                let value = self.allocate_temporary();
+                self.emit(Instruction::Store, value);
+                self.emit(Instruction::Set, Address::Immediate(0));
+                self.emit(Instruction::Subtract, value);
+                self.free_temporaries(1);
+            }
+            AnalyzedFactor::Power(base, exponent) => {
+                self.generate_factor(base)?;
+                let base_value = self.allocate_temporary();
+                self.emit(Instruction::Store, base_value);
+                self.generate_factor(exponent)?;
+                self.emit(Instruction::Store, Address::Cell(Cell::Exponent));
+                self.emit(Instruction::Load, base_value);
+                self.emit(Instruction::Store, Address::Cell(Cell::Base));
+                self.call(self.power);
+                self.free_temporaries(1);
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_builtin_call(
+        &mut self,
+        builtin: BuiltinFunction,
+        arguments: &[AnalyzedExpr],
+    ) -> Result<(), String> {
+        let end_label = self.new_label();
+        match builtin {
+            BuiltinFunction::Abs => {
+                self.generate_expr(&arguments[0])?;
+                self.emit(Instruction::JumpIfNonNegative, Address::Label(end_label));
+                let value = self.allocate_temporary();
+                self.emit(Instruction::Store, value);
+                self.emit(Instruction::Set, Address::Immediate(0));
+                self.emit(Instruction::Subtract, value);
+                self.free_temporaries(1);
+            }
+            BuiltinFunction::Min | BuiltinFunction::Max => {
+                self.generate_expr(&arguments[0])?;
+                let first = self.allocate_temporary();
+                self.emit(Instruction::Store, first);
+                self.generate_expr(&arguments[1])?;
+                let second = self.allocate_temporary();
+                self.emit(Instruction::Store, second);
+                let take_second_label = self.new_label();
+                self.emit(Instruction::Load, first);
+                self.emit(Instruction::Subtract, second);
+                self.emit(
+                    if builtin == BuiltinFunction::Min {
+                        Instruction::JumpIfPositive
+                    } else {
+                        Instruction::JumpIfNegative
+                    },
+                    Address::Label(take_second_label),
+                );
+                self.emit(Instruction::Load, first);
+                self.emit(Instruction::Jump, Address::Label(end_label));
+                self.place_label(take_second_label);
+                self.emit(Instruction::Load, second);
+                self.free_temporaries(2);
+            }
+            BuiltinFunction::Sqrt | BuiltinFunction::Sin => {
+                return Err(format!(
+                    "Error: The function '{}' is not supported by the byte machine.",
+                    builtin.name()
+                ));
+            }
+        }
+        self.place_label(end_label);
+        Ok(())
+    }
+
+    // The values of the parameters, of the local variables, of the temporaries
+    // and of the return address of the called function are saved on the stack,
+    // so that recursive calls do not clobber them.
+    fn generate_function_call(
+        &mut self,
+        handle: usize,
+        arguments: &[AnalyzedExpr],
+    ) -> Result<(), String> {
+        let mut argument_temporaries = Vec::<Address>::new();
+        for argument in arguments {
+            self.generate_expr(argument)?;
+            let temporary = self.allocate_temporary();
+            self.emit(Instruction::Store, temporary);
+            argument_temporaries.push(temporary);
+        }
+        self.items.push(Item::SaveFrame(handle));
+        let parameters = self.variables.get_function(handle).locals.start;
+        for (index, &temporary) in argument_temporaries.iter().enumerate() {
+            self.emit(Instruction::Load, temporary);
+            self.emit(Instruction::Store, Address::Variable(parameters + index));
+        }
+        self.call(self.functions[handle]);
+        self.emit(Instruction::Store, Address::Cell(Cell::ReturnValue));
+        self.items.push(Item::RestoreFrame(handle));
+        self.emit(Instruction::Load, Address::Cell(Cell::ReturnValue));
+        self.free_temporaries(argument_temporaries.len());
+        Ok(())
+    }
+
+    fn generate_term(&mut self, analyzed_term: &AnalyzedTerm) -> Result<(), String> {
+        self.generate_factor(&analyzed_term.0)?;
+        for factor in &analyzed_term.1 {
+            let left = self.allocate_temporary();
+            self.emit(Instruction::Store, left);
+            self.generate_factor(&factor.1)?;
+            match factor.0 {
+                TermOperator::Multiply => {
+                    self.emit(Instruction::Multiply, left);
+                }
+                // The divisions give floats.
+                TermOperator::Divide => return Err(float_error()),
+                // The remainder has the sign of the dividend,
+                // as it is computed from the truncated quotient.
+                TermOperator::Remainder => {
+                    let right = self.allocate_temporary();
+                    self.emit(Instruction::Store, right);
+                    self.emit(Instruction::Store, Address::Cell(Cell::Divisor));
+                    self.emit(Instruction::Load, left);
+                    self.emit(Instruction::Store, Address::Cell(Cell::Dividend));
+                    self.call(self.divide);
+                    self.emit(Instruction::Multiply, right);
+                    self.emit(Instruction::Store, right);
+                    self.emit(Instruction::Load, left);
+                    self.emit(Instruction::Subtract, right);
+                    self.free_temporaries(1);
+                }
+            }
+            self.free_temporaries(1);
+        }
+        Ok(())
+    }
+
+    // The expressions are checked too, as any float operand makes them floats.
+    fn generate_expr(&mut self, analyzed_expr: &AnalyzedExpr) -> Result<(), String> {
+        if analyzed_expr.2 == Type::Float {
+            return Err(float_error());
+        }
+        self.generate_term(&analyzed_expr.0)?;
+        for term in &analyzed_expr.1 {
+            let left = self.allocate_temporary();
+            self.emit(Instruction::Store, left);
+            self.generate_term(&term.1)?;
+            match term.0 {
+                ExprOperator::Add => {
+                    self.emit(Instruction::Add, left);
+                }
+                ExprOperator::Subtract => {
+                    let right = self.allocate_temporary();
+                    self.emit(Instruction::Store, right);
+                    self.emit(Instruction::Load, left);
+                    self.emit(Instruction::Subtract, right);
+                    self.free_temporaries(1);
+                }
+            }
+            self.free_temporaries(1);
+        }
+        Ok(())
+    }
+
+    // Jumps to the given label if the condition is false.
+    fn generate_condition(
+        &mut self,
+        analyzed_condition: &AnalyzedCondition,
+        false_label: usize,
+    ) -> Result<(), String> {
+        self.generate_expr(&analyzed_condition.0)?;
+        let left = self.allocate_temporary();
+        self.emit(Instruction::Store, left);
+        self.generate_expr(&analyzed_condition.2)?;
+        let right = self.allocate_temporary();
+        self.emit(Instruction::Store, right);
+        self.emit(Instruction::Load, left);
+        self.emit(Instruction::Subtract, right);
+        self.free_temporaries(2);
+        let jump: fn(u16) -> Instruction = match analyzed_condition.1 {
+            ComparisonOperator::Equal => Instruction::JumpIfNonZero,
+            ComparisonOperator::NotEqual => Instruction::JumpIfZero,
+            ComparisonOperator::Less => Instruction::JumpIfNonNegative,
+            ComparisonOperator::LessOrEqual => Instruction::JumpIfPositive,
+            ComparisonOperator::Greater => Instruction::JumpIfNonPositive,
+            ComparisonOperator::GreaterOrEqual => Instruction::JumpIfNegative,
+        };
+        self.emit(jump, Address::Label(false_label));
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, analyzed_statement: &AnalyzedStatement) -> Result<(), String> {
+        match analyzed_statement {
+            AnalyzedStatement::Assignment(handle, expr) => {
+                self.generate_expr(expr)?;
+                self.emit(Instruction::Store, Address::Variable(*handle));
+            }
+            AnalyzedStatement::Declaration(handle) => {
+                self.emit(Instruction::Set, Address::Immediate(0));
+                self.emit(Instruction::Store, Address::Variable(*handle));
+            }
+            AnalyzedStatement::InputOperation(handle) => {
+                if !self.variables.get_type(*handle).is_numeric() {
+                    return Err(format!(
+                        "Error: The byte machine can read only numbers, not values of type {}.",
+                        self.variables.get_type(*handle)
+                    ));
+                }
+                self.call(self.read_number);
+                self.emit(Instruction::Store, Address::Variable(*handle));
+            }
+            AnalyzedStatement::ElementAssignment(..) | AnalyzedStatement::ElementInput(..) => {
+                return Err(array_error())
+            }
+            AnalyzedStatement::OutputOperation(exprs) => {
+                for expr in exprs {
+                    self.generate_output_item(expr)?;
+                }
+                self.emit(Instruction::Set, Address::Cell(Cell::NewlineCharacter));
+                self.items
+                    .push(Item::ByteInstruction(Instruction::Output(1)));
+            }
+            AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                self.generate_condition(condition, else_label)?;
+                self.generate_block(then_block)?;
+                self.emit(Instruction::Jump, Address::Label(end_label));
+                self.place_label(else_label);
+                self.generate_block(else_block)?;
+                self.place_label(end_label);
+            }
+            AnalyzedStatement::WhileLoop(condition, body) => {
+                let start_label = self.new_label();
+                let end_label = self.new_label();
+                self.place_label(start_label);
+                self.generate_condition(condition, end_label)?;
+                self.generate_block(body)?;
+                self.emit(Instruction::Jump, Address::Label(start_label));
+                self.place_label(end_label);
+            }
+            AnalyzedStatement::FunctionDefinition(_) => {}
+            AnalyzedStatement::Return(_, expr) => {
+                self.generate_expr(expr)?;
+                self.emit(
+                    Instruction::Jump,
+                    Address::Label(self.functions[self.context - 1].exit),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_output_item(&mut self, expr: &AnalyzedExpr) -> Result<(), String> {
+        if let ((AnalyzedFactor::Literal(Value::Str(text)), factors, _), terms, _) = expr {
+            if factors.is_empty() && terms.is_empty() {
+                self.output_text(text);
+                return Ok(());
+            }
+        }
+        self.generate_expr(expr)?;
+        if expr.2 == Type::Bool {
+            let false_label = self.new_label();
+            let end_label = self.new_label();
+            self.emit(Instruction::JumpIfZero, Address::Label(false_label));
+            self.output_text("true");
+            self.emit(Instruction::Jump, Address::Label(end_label));
+            self.place_label(false_label);
+            self.output_text("false");
+            self.place_label(end_label);
+        } else {
+            self.emit(Instruction::Store, Address::Cell(Cell::Number));
+            self.call(self.print_number);
+        }
+        Ok(())
+    }
+
+    fn generate_block(&mut self, analyzed_block: &AnalyzedProgram) -> Result<(), String> {
+        for (statement, _) in analyzed_block {
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_function(&mut self, handle: usize) -> Result<(), String> {
+        let routine = self.functions[handle];
+        let body = self.variables.get_function(handle).body.clone();
+        self.context = handle + 1;
+        self.begin_routine(routine);
+        self.generate_block(&body)?;
+        // Functions that end without a return statement return zero.
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.end_routine(routine);
+        self.context = 0;
+        Ok(())
+    }
+
+    // Reads a line and converts its leading optional minus sign and digits to a number.
+    fn generate_read_number(&mut self) {
+        let routine = self.read_number;
+        let digits_label = self.new_label();
+        let end_label = self.new_label();
+        let positive_label = self.new_label();
+        let minus = self.constant(b'-' as u16);
+        let zero = self.constant(b'0' as u16);
+        let one = self.constant(1);
+        let ten = self.constant(10);
+        self.begin_routine(routine);
+        self.emit(Instruction::Set, Address::Cell(Cell::InputBuffer));
+        self.items.push(Item::ByteInstruction(Instruction::Input(
+            INPUT_BUFFER_SIZE as u8,
+        )));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Store, Address::Cell(Cell::Number));
+        self.emit(Instruction::Store, Address::Cell(Cell::Sign));
+        self.emit(Instruction::Set, Address::Cell(Cell::InputBuffer));
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::IndirectLoadByte, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Subtract, minus);
+        self.emit(Instruction::JumpIfNonZero, Address::Label(digits_label));
+        self.emit(Instruction::Set, Address::Immediate(1));
+        self.emit(Instruction::Store, Address::Cell(Cell::Sign));
+        self.emit(Instruction::Load, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Add, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.place_label(digits_label);
+        self.emit(Instruction::IndirectLoadByte, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Subtract, zero);
+        self.emit(Instruction::JumpIfNegative, Address::Label(end_label));
+        self.emit(Instruction::Subtract, ten);
+        self.emit(Instruction::JumpIfNonNegative, Address::Label(end_label));
+        self.emit(Instruction::Load, Address::Cell(Cell::Number));
+        self.emit(Instruction::Multiply, ten);
+        self.emit(Instruction::Store, Address::Cell(Cell::Number));
+        self.emit(Instruction::IndirectLoadByte, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Subtract, zero);
+        self.emit(Instruction::Add, Address::Cell(Cell::Number));
+        self.emit(Instruction::Store, Address::Cell(Cell::Number));
+        self.emit(Instruction::Load, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Add, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Jump, Address::Label(digits_label));
+        self.place_label(end_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Sign));
+        self.emit(Instruction::JumpIfZero, Address::Label(positive_label));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Number));
+        self.emit(Instruction::Jump, Address::Label(routine.exit));
+        self.place_label(positive_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Number));
+        self.end_routine(routine);
+    }
+
+    // Prints the number, without a newline.
+    fn generate_print_number(&mut self) {
+        let routine = self.print_number;
+        let nonnegative_label = self.new_label();
+        let digits_label = self.new_label();
+        let print_label = self.new_label();
+        let zero = self.constant(b'0' as u16);
+        let one = self.constant(1);
+        let ten = self.constant(10);
+        self.begin_routine(routine);
+        self.emit(Instruction::Load, Address::Cell(Cell::Number));
+        self.emit(
+            Instruction::JumpIfNonNegative,
+            Address::Label(nonnegative_label),
+        );
+        self.emit(Instruction::Set, Address::Cell(Cell::MinusCharacter));
+        self.items
+            .push(Item::ByteInstruction(Instruction::Output(1)));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Number));
+        self.emit(Instruction::Store, Address::Cell(Cell::Number));
+        self.place_label(nonnegative_label);
+        self.emit(Instruction::Set, Address::Cell(Cell::DigitBufferEnd));
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Store, Address::Cell(Cell::Count));
+        self.place_label(digits_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Subtract, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Load, Address::Cell(Cell::Count));
+        self.emit(Instruction::Add, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Count));
+        self.emit(Instruction::Load, Address::Cell(Cell::Number));
+        self.emit(Instruction::Remainder, ten);
+        self.emit(Instruction::Add, zero);
+        self.emit(Instruction::IndirectStoreByte, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Load, Address::Cell(Cell::Number));
+        self.emit(Instruction::Divide, ten);
+        self.emit(Instruction::Store, Address::Cell(Cell::Number));
+        self.emit(Instruction::JumpIfNonZero, Address::Label(digits_label));
+        self.place_label(print_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Pointer));
+        self.items
+            .push(Item::ByteInstruction(Instruction::Output(1)));
+        self.emit(Instruction::Load, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Add, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Pointer));
+        self.emit(Instruction::Load, Address::Cell(Cell::Count));
+        self.emit(Instruction::Subtract, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Count));
+        self.emit(Instruction::JumpIfNonZero, Address::Label(print_label));
+        self.end_routine(routine);
+    }
+
+    // Divides the dividend by the divisor as signed numbers,
+    // as the division instruction of the machine is unsigned.
+    fn generate_divide(&mut self) {
+        let routine = self.divide;
+        let dividend_label = self.new_label();
+        let divisor_label = self.new_label();
+        let positive_label = self.new_label();
+        self.begin_routine(routine);
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Store, Address::Cell(Cell::Sign));
+        self.emit(Instruction::Load, Address::Cell(Cell::Dividend));
+        self.emit(
+            Instruction::JumpIfNonNegative,
+            Address::Label(dividend_label),
+        );
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Dividend));
+        self.emit(Instruction::Store, Address::Cell(Cell::Dividend));
+        self.emit(Instruction::Set, Address::Immediate(1));
+        self.emit(Instruction::Store, Address::Cell(Cell::Sign));
+        self.place_label(dividend_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Divisor));
+        self.emit(
+            Instruction::JumpIfNonNegative,
+            Address::Label(divisor_label),
+        );
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Divisor));
+        self.emit(Instruction::Store, Address::Cell(Cell::Divisor));
+        self.emit(Instruction::Set, Address::Immediate(1));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Sign));
+        self.emit(Instruction::Store, Address::Cell(Cell::Sign));
+        self.place_label(divisor_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Dividend));
+        self.emit(Instruction::Divide, Address::Cell(Cell::Divisor));
+        self.emit(Instruction::Store, Address::Cell(Cell::Quotient));
+        self.emit(Instruction::Load, Address::Cell(Cell::Sign));
+        self.emit(Instruction::JumpIfZero, Address::Label(positive_label));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Quotient));
+        self.emit(Instruction::Jump, Address::Label(routine.exit));
+        self.place_label(positive_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Quotient));
+        self.end_routine(routine);
+    }
+
+    // Raises the base to the exponent. Negative exponents give the truncated
+    // result, which is zero unless the base is 1 or -1.
+    fn generate_power(&mut self) {
+        let routine = self.power;
+        let loop_label = self.new_label();
+        let zero_label = self.new_label();
+        let done_label = self.new_label();
+        let one = self.constant(1);
+        self.begin_routine(routine);
+        self.emit(Instruction::Set, Address::Immediate(1));
+        self.emit(Instruction::Store, Address::Cell(Cell::Power));
+        self.emit(Instruction::Load, Address::Cell(Cell::Exponent));
+        self.emit(Instruction::JumpIfNonNegative, Address::Label(loop_label));
+        self.emit(Instruction::Load, Address::Cell(Cell::Base));
+        self.emit(Instruction::Multiply, Address::Cell(Cell::Base));
+        self.emit(Instruction::Subtract, one);
+        self.emit(Instruction::JumpIfNonZero, Address::Label(zero_label));
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Subtract, Address::Cell(Cell::Exponent));
+        self.emit(Instruction::Store, Address::Cell(Cell::Exponent));
+        self.place_label(loop_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Exponent));
+        self.emit(Instruction::JumpIfZero, Address::Label(done_label));
+        self.emit(Instruction::Subtract, one);
+        self.emit(Instruction::Store, Address::Cell(Cell::Exponent));
+        self.emit(Instruction::Load, Address::Cell(Cell::Power));
+        self.emit(Instruction::Multiply, Address::Cell(Cell::Base));
+        self.emit(Instruction::Store, Address::Cell(Cell::Power));
+        self.emit(Instruction::Jump, Address::Label(loop_label));
+        self.place_label(zero_label);
+        self.emit(Instruction::Set, Address::Immediate(0));
+        self.emit(Instruction::Jump, Address::Label(routine.exit));
+        self.place_label(done_label);
+        self.emit(Instruction::Load, Address::Cell(Cell::Power));
+        self.end_routine(routine);
+    }
+
+    fn frame_words(&self, handle: usize) -> Vec<Address> {
+        let function = self.variables.get_function(handle);
+        let mut words: Vec<Address> = function.locals.clone().map(Address::Variable).collect();
+        for index in 0..self.temporary_counts[handle + 1] {
+            words.push(Address::Temporary(handle + 1, index));
+        }
+        words.push(Address::LabelOperand(self.functions[handle].exit));
+        words
+    }
+
+    // Replaces the frame saving and restoring items with instructions,
+    // now that the number of temporaries of every function is known.
+    fn expand_frames(&mut self) {
+        let two = self.constant(2);
+        let items = std::mem::take(&mut self.items);
+        for item in items {
+            match item {
+                Item::SaveFrame(handle) => {
+                    for word in self.frame_words(handle) {
+                        self.emit(Instruction::Load, word);
+                        self.emit(
+                            Instruction::IndirectStore,
+                            Address::Cell(Cell::StackPointer),
+                        );
+                        self.emit(Instruction::Load, Address::Cell(Cell::StackPointer));
+                        self.emit(Instruction::Add, two);
+                        self.emit(Instruction::Store, Address::Cell(Cell::StackPointer));
+                    }
+                }
+                Item::RestoreFrame(handle) => {
+                    for word in self.frame_words(handle).into_iter().rev() {
+                        self.emit(Instruction::Load, Address::Cell(Cell::StackPointer));
+                        self.emit(Instruction::Subtract, two);
+                        self.emit(Instruction::Store, Address::Cell(Cell::StackPointer));
+                        self.emit(Instruction::IndirectLoad, Address::Cell(Cell::StackPointer));
+                        self.emit(Instruction::Store, word);
+                    }
+                }
+                item => self.items.push(item),
+            }
+        }
+    }
+
+    fn assemble(mut self) -> Result<Vec<u8>, String> {
+        self.expand_frames();
+
+        let mut label_addresses = vec![0usize; self.label_count];
+        let mut address = 2;
+        for item in &self.items {
+            match item {
+                Item::Label(label) => label_addresses[*label] = address,
+                Item::ByteInstruction(instruction) => address += instruction.len(),
+                Item::WordInstruction(instruction, _) => address += instruction(0).len(),
+                Item::SaveFrame(_) | Item::RestoreFrame(_) => {}
+            }
+        }
+
+        // The data that needs an initial value is stored in the image,
+        // while the remaining data is zeroed by the machine.
+        let constants_start = address;
+        let minus_character = constants_start + 2 * self.constants.len();
+        let newline_character = minus_character + 2;
+        let mut texts_starts = Vec::<usize>::new();
+        let mut texts_end = newline_character + 2;
+        for text in &self.texts {
+            texts_starts.push(texts_end);
+            texts_end += text.len();
+        }
+        // The cells are word-aligned after the texts.
+        let image_size = texts_end + texts_end % 2;
+        let cells = [
+            Cell::StackPointer,
+            Cell::ReturnValue,
+            Cell::Number,
+            Cell::Sign,
+            Cell::Pointer,
+            Cell::Count,
+            Cell::Dividend,
+            Cell::Divisor,
+            Cell::Quotient,
+            Cell::Base,
+            Cell::Exponent,
+            Cell::Power,
+        ];
+        let input_buffer = image_size + 2 * cells.len();
+        // The byte after the input buffer stays zero, to end the parsing of the digits.
+        let digit_buffer = input_buffer + INPUT_BUFFER_SIZE as usize + 2;
+        let variables_start = digit_buffer + DIGIT_BUFFER_SIZE as usize;
+        let mut temporaries_starts = Vec::<usize>::new();
+        let mut next_address = variables_start + 2 * self.variables.symbol_count();
+        for &count in &self.temporary_counts {
+            temporaries_starts.push(next_address);
+            next_address += 2 * count;
+        }
+        let stack_start = next_address;
+        let process_size = stack_start + STACK_SIZE;
+        if process_size > 0xFFFF {
+            return Err("Error: The program is too large for the byte machine.".to_string());
+        }
+
+        let resolve = |address: Address| -> u16 {
+            (match address {
+                Address::Immediate(value) => return value,
+                Address::Constant(value) => {
+                    constants_start + 2 * self.constants.iter().position(|&c| c == value).unwrap()
+                }
+                Address::Cell(Cell::MinusCharacter) => minus_character,
+                Address::Cell(Cell::NewlineCharacter) => newline_character,
+                Address::Cell(Cell::InputBuffer) => input_buffer,
+                Address::Cell(Cell::DigitBufferEnd) => digit_buffer + DIGIT_BUFFER_SIZE as usize,
+                Address::Cell(Cell::StackStart) => stack_start,
+                Address::Cell(cell) => {
+                    image_size + 2 * cells.iter().position(|&c| c == cell).unwrap()
+                }
+                Address::Variable(handle) => variables_start + 2 * handle,
+                Address::Temporary(context, index) => temporaries_starts[context] + 2 * index,
+                Address::Text(index) => texts_starts[index],
+                Address::Label(label) => label_addresses[label],
+                Address::LabelOperand(label) => label_addresses[label] + 1,
+            }) as u16
+        };
+
+        let mut image = Vec::<u8>::new();
+        image.extend_from_slice(&(process_size as u16).to_le_bytes());
+        for item in &self.items {
+            match *item {
+                Item::ByteInstruction(instruction) => instruction.encode(&mut image),
+                Item::WordInstruction(instruction, address) => {
+                    instruction(resolve(address)).encode(&mut image)
+                }
+                Item::Label(_) | Item::SaveFrame(_) | Item::RestoreFrame(_) => {}
+            }
+        }
+        for &constant in &self.constants {
+            image.extend_from_slice(&constant.to_le_bytes());
+        }
+        image.extend_from_slice(&(b'-' as u16).to_le_bytes());
+        image.extend_from_slice(&(b'\n' as u16).to_le_bytes());
+        for text in &self.texts {
+            image.extend_from_slice(text.as_bytes());
+        }
+        image.resize(image_size, 0);
+        Ok(image)
+    }
+}
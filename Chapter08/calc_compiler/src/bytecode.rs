@@ -0,0 +1,622 @@
+use crate::analyzer::{
+    AnalyzedCondition, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm, BuiltinFunction, MAX_ARRAY_SIZE,
+};
+use crate::parser::{ComparisonOperator, ExprOperator, TermOperator};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+const MAGIC: &[u8; 4] = b"CALC";
+const FORMAT_VERSION: u8 = 4;
+
+// Jump targets and function addresses are indexes into the code,
+// variables are indexes into the memory of the virtual machine,
+// and string literals are indexes into the string table of the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Halt,
+    Push(f64),
+    Load(u32),
+    Store(u32),
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Jump(u32),
+    JumpIfFalse(u32),
+    Input(u32, Type),
+    Output(u32),
+    Call(u32),
+    Return,
+    Remainder,
+    Power,
+    Negate,
+    CallBuiltin(BuiltinFunction),
+    PushInt(i64),
+    PushBool(bool),
+    PushString(u32),
+    ToFloat,
+    // Pushes an array of the given size, whose elements have the default value of the given type.
+    NewArray(u32, Type),
+    // Pops the index, and pushes the element of the array stored in the variable.
+    LoadElement(u32),
+    // Pops the index and then the value, and stores the value into the element.
+    StoreElement(u32),
+    // Reads a value of the given type, and pushes it.
+    PushInput(Type),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BytecodeFunction {
+    pub address: u32,
+    pub parameter_count: u32,
+    pub locals_start: u32,
+    pub locals_end: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BytecodeProgram {
+    pub variable_count: u32,
+    pub functions: Vec<BytecodeFunction>,
+    pub strings: Vec<String>,
+    pub code: Vec<Instruction>,
+}
+
+struct Compiler<'a> {
+    variables: &'a SymbolTable,
+    code: Vec<Instruction>,
+    strings: Vec<String>,
+}
+
+pub fn compile_program(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Result<BytecodeProgram, String> {
+    let mut c = Compiler {
+        variables,
+        code: Vec::<Instruction>::new(),
+        strings: Vec::<String>::new(),
+    };
+    c.compile_block(analyzed_program)?;
+    c.code.push(Instruction::Halt);
+    let mut functions = Vec::<BytecodeFunction>::new();
+    for handle in 0..variables.function_count() {
+        let function = variables.get_function(handle);
+        functions.push(BytecodeFunction {
+            address: c.code.len() as u32,
+            parameter_count: function.parameter_count as u32,
+            locals_start: function.locals.start as u32,
+            locals_end: function.locals.end as u32,
+        });
+        c.compile_block(&function.body)?;
+        // Functions that end without a return statement return the default value of their type.
+        c.push_value(&function.return_type.default_value());
+        c.code.push(Instruction::Return);
+    }
+    Ok(BytecodeProgram {
+        variable_count: variables.symbol_count() as u32,
+        functions,
+        strings: c.strings,
+        code: c.code,
+    })
+}
+
+impl<'a> Compiler<'a> {
+    fn push_value(&mut self, value: &Value) {
+        let instruction = match value {
+            Value::Int(value) => Instruction::PushInt(*value),
+            Value::Float(value) => Instruction::Push(*value),
+            Value::Bool(value) => Instruction::PushBool(*value),
+            Value::Str(value) => {
+                let index = match self.strings.iter().position(|string| string == value) {
+                    Some(index) => index,
+                    None => {
+                        self.strings.push(value.clone());
+                        self.strings.len() - 1
+                    }
+                };
+                Instruction::PushString(index as u32)
+            }
+            // The only array values are the initial values of array variables.
+            Value::Array(items) => Instruction::NewArray(items.len() as u32, items[0].get_type()),
+        };
+        self.code.push(instruction);
+    }
+
+    // Integers are converted explicitly where a float is expected,
+    // as the virtual machine keeps the type of every value.
+    fn promote(&mut self, from: Type, to: Type) {
+        if from == Type::Int && to == Type::Float {
+            self.code.push(Instruction::ToFloat);
+        }
+    }
+
+    fn compile_factor(&mut self, analyzed_factor: &AnalyzedFactor) -> Result<(), String> {
+        match analyzed_factor {
+            AnalyzedFactor::Literal(value) => self.push_value(value),
+            AnalyzedFactor::Identifier(handle) => self.code.push(Instruction::Load(*handle as u32)),
+            AnalyzedFactor::Element(handle, index) => {
+                self.compile_expr(index)?;
+                self.code.push(Instruction::LoadElement(*handle as u32));
+            }
+            AnalyzedFactor::SubExpression(expr) => self.compile_expr(expr)?,
+            AnalyzedFactor::FunctionCall(handle, arguments) => {
+                let parameters = self.variables.get_function(*handle).locals.start;
+                for (index, argument) in arguments.iter().enumerate() {
+                    self.compile_expr(argument)?;
+                    self.promote(argument.2, self.variables.get_type(parameters + index));
+                }
+                self.code.push(Instruction::Call(*handle as u32));
+            }
+            AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+                for argument in arguments {
+                    self.compile_expr(argument)?;
+                }
+                self.code.push(Instruction::CallBuiltin(*builtin));
+            }
+            // Native functions are Rust closures of the engine, which exist only in the interpreter.
+            AnalyzedFactor::NativeCall(handle, _) => {
+                return Err(format!(
+                    "Error: The native function '{}' cannot be compiled to bytecode.",
+                    self.variables.get_native(*handle).name
+                ))
+            }
+            AnalyzedFactor::Negation(factor) => {
+                self.compile_factor(factor)?;
+                self.code.push(Instruction::Negate);
+            }
+            AnalyzedFactor::Power(base, exponent) => {
+                self.compile_factor(base)?;
+                self.compile_factor(exponent)?;
+                self.code.push(Instruction::Power);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_term(&mut self, analyzed_term: &AnalyzedTerm) -> Result<(), String> {
+        self.compile_factor(&analyzed_term.0)?;
+        for factor in &analyzed_term.1 {
+            self.compile_factor(&factor.1)?;
+            self.code.push(match factor.0 {
+                TermOperator::Multiply => Instruction::Multiply,
+                TermOperator::Divide => Instruction::Divide,
+                TermOperator::Remainder => Instruction::Remainder,
+            });
+        }
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, analyzed_expr: &AnalyzedExpr) -> Result<(), String> {
+        self.compile_term(&analyzed_expr.0)?;
+        for term in &analyzed_expr.1 {
+            self.compile_term(&term.1)?;
+            self.code.push(match term.0 {
+                ExprOperator::Add => Instruction::Add,
+                ExprOperator::Subtract => Instruction::Subtract,
+            });
+        }
+        Ok(())
+    }
+
+    fn compile_condition(&mut self, analyzed_condition: &AnalyzedCondition) -> Result<(), String> {
+        self.compile_expr(&analyzed_condition.0)?;
+        self.compile_expr(&analyzed_condition.2)?;
+        self.code.push(match analyzed_condition.1 {
+            ComparisonOperator::Equal => Instruction::Equal,
+            ComparisonOperator::NotEqual => Instruction::NotEqual,
+            ComparisonOperator::Less => Instruction::Less,
+            ComparisonOperator::LessOrEqual => Instruction::LessOrEqual,
+            ComparisonOperator::Greater => Instruction::Greater,
+            ComparisonOperator::GreaterOrEqual => Instruction::GreaterOrEqual,
+        });
+        Ok(())
+    }
+
+    // Emits a forward jump whose target is set later by `patch_jump`,
+    // when the end of the code is reached.
+    fn emit_jump(&mut self, instruction: fn(u32) -> Instruction) -> usize {
+        self.code.push(instruction(0));
+        self.code.len() - 1
+    }
+
+    fn patch_jump(&mut self, position: usize) {
+        let target = self.code.len() as u32;
+        self.code[position] = match self.code[position] {
+            Instruction::Jump(_) => Instruction::Jump(target),
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
+            instruction => instruction,
+        };
+    }
+
+    fn compile_statement(&mut self, analyzed_statement: &AnalyzedStatement) -> Result<(), String> {
+        match analyzed_statement {
+            AnalyzedStatement::Assignment(handle, expr) => {
+                self.compile_expr(expr)?;
+                self.promote(expr.2, self.variables.get_type(*handle));
+                self.code.push(Instruction::Store(*handle as u32));
+            }
+            // The value is evaluated before the index, like in the interpreter.
+            AnalyzedStatement::ElementAssignment(handle, index, expr) => {
+                self.compile_expr(expr)?;
+                self.promote(expr.2, self.variables.get_type(*handle));
+                self.compile_expr(index)?;
+                self.code.push(Instruction::StoreElement(*handle as u32));
+            }
+            AnalyzedStatement::Declaration(handle) => {
+                self.push_value(&self.variables.default_value(*handle));
+                self.code.push(Instruction::Store(*handle as u32));
+            }
+            AnalyzedStatement::InputOperation(handle) => {
+                self.code.push(Instruction::Input(
+                    *handle as u32,
+                    self.variables.get_type(*handle),
+                ));
+            }
+            AnalyzedStatement::ElementInput(handle, index) => {
+                self.code
+                    .push(Instruction::PushInput(self.variables.get_type(*handle)));
+                self.compile_expr(index)?;
+                self.code.push(Instruction::StoreElement(*handle as u32));
+            }
+            AnalyzedStatement::OutputOperation(exprs) => {
+                for expr in exprs {
+                    self.compile_expr(expr)?;
+                }
+                self.code.push(Instruction::Output(exprs.len() as u32));
+            }
+            AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+                self.compile_condition(condition)?;
+                let jump_to_else = self.emit_jump(Instruction::JumpIfFalse);
+                self.compile_block(then_block)?;
+                let jump_to_end = self.emit_jump(Instruction::Jump);
+                self.patch_jump(jump_to_else);
+                self.compile_block(else_block)?;
+                self.patch_jump(jump_to_end);
+            }
+            AnalyzedStatement::WhileLoop(condition, body) => {
+                let loop_start = self.code.len();
+                self.compile_condition(condition)?;
+                let jump_to_end = self.emit_jump(Instruction::JumpIfFalse);
+                self.compile_block(body)?;
+                self.code.push(Instruction::Jump(loop_start as u32));
+                self.patch_jump(jump_to_end);
+            }
+            AnalyzedStatement::FunctionDefinition(_) => {}
+            AnalyzedStatement::Return(handle, expr) => {
+                self.compile_expr(expr)?;
+                self.promote(expr.2, self.variables.get_function(*handle).return_type);
+                self.code.push(Instruction::Return);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_block(&mut self, analyzed_block: &AnalyzedProgram) -> Result<(), String> {
+        for (statement, _) in analyzed_block {
+            self.compile_statement(statement)?;
+        }
+        Ok(())
+    }
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+const TYPES: [Type; 4] = [Type::Int, Type::Float, Type::Bool, Type::Str];
+
+fn encode_type(value_type: Type) -> u8 {
+    TYPES.iter().position(|&item| item == value_type).unwrap() as u8
+}
+
+fn encode_instruction(bytes: &mut Vec<u8>, instruction: Instruction) {
+    use Instruction::*;
+    match instruction {
+        Halt => bytes.push(0),
+        Push(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Load(operand) => {
+            bytes.push(2);
+            push_u32(bytes, operand);
+        }
+        Store(operand) => {
+            bytes.push(3);
+            push_u32(bytes, operand);
+        }
+        Add => bytes.push(4),
+        Subtract => bytes.push(5),
+        Multiply => bytes.push(6),
+        Divide => bytes.push(7),
+        Equal => bytes.push(8),
+        NotEqual => bytes.push(9),
+        Less => bytes.push(10),
+        LessOrEqual => bytes.push(11),
+        Greater => bytes.push(12),
+        GreaterOrEqual => bytes.push(13),
+        Jump(operand) => {
+            bytes.push(14);
+            push_u32(bytes, operand);
+        }
+        JumpIfFalse(operand) => {
+            bytes.push(15);
+            push_u32(bytes, operand);
+        }
+        Input(operand, value_type) => {
+            bytes.push(16);
+            push_u32(bytes, operand);
+            bytes.push(encode_type(value_type));
+        }
+        Output(count) => {
+            bytes.push(17);
+            push_u32(bytes, count);
+        }
+        Call(operand) => {
+            bytes.push(18);
+            push_u32(bytes, operand);
+        }
+        Return => bytes.push(19),
+        Remainder => bytes.push(20),
+        Power => bytes.push(21),
+        Negate => bytes.push(22),
+        CallBuiltin(builtin) => {
+            bytes.push(23);
+            bytes.push(
+                BuiltinFunction::ALL
+                    .iter()
+                    .position(|&item| item == builtin)
+                    .unwrap() as u8,
+            );
+        }
+        PushInt(value) => {
+            bytes.push(24);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        PushBool(value) => {
+            bytes.push(25);
+            bytes.push(value as u8);
+        }
+        PushString(operand) => {
+            bytes.push(26);
+            push_u32(bytes, operand);
+        }
+        ToFloat => bytes.push(27),
+        NewArray(size, value_type) => {
+            bytes.push(28);
+            push_u32(bytes, size);
+            bytes.push(encode_type(value_type));
+        }
+        LoadElement(operand) => {
+            bytes.push(29);
+            push_u32(bytes, operand);
+        }
+        StoreElement(operand) => {
+            bytes.push(30);
+            push_u32(bytes, operand);
+        }
+        PushInput(value_type) => {
+            bytes.push(31);
+            bytes.push(encode_type(value_type));
+        }
+    }
+}
+
+// Checks that the variables used by the code and the function locals
+// are within the memory of the virtual machine.
+pub fn check_variables(program: &BytecodeProgram) -> Result<(), String> {
+    use Instruction::*;
+    let highest_code_variable = program
+        .code
+        .iter()
+        .filter_map(|&instruction| match instruction {
+            Load(address)
+            | Store(address)
+            | Input(address, _)
+            | LoadElement(address)
+            | StoreElement(address) => Some(address as u64 + 1),
+            _ => None,
+        });
+    let highest_local = program
+        .functions
+        .iter()
+        .map(|function| u64::from(function.locals_end));
+    let needed_count = highest_code_variable
+        .chain(highest_local)
+        .max()
+        .unwrap_or(0);
+    if needed_count > u64::from(program.variable_count) {
+        return Err(format!(
+            "Error: The bytecode uses {} variables, but declares only {}.",
+            needed_count, program.variable_count
+        ));
+    }
+    Ok(())
+}
+
+// Checks that the arrays have the sizes allowed in the declarations,
+// so that a damaged file cannot make the virtual machine allocate too much memory.
+fn check_array_sizes(program: &BytecodeProgram) -> Result<(), String> {
+    for (address, instruction) in program.code.iter().enumerate() {
+        if let Instruction::NewArray(size, _) = instruction {
+            if !(1..=MAX_ARRAY_SIZE).contains(&(*size as usize)) {
+                return Err(format!(
+                    "Error: Invalid array size {} at {}.",
+                    size, address
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The format is: the magic bytes "CALC", the format version,
+// the number of variables, the function table, the string table, and the code,
+// with every number stored in little-endian order
+// and every string stored as its length followed by its UTF-8 bytes.
+pub fn serialize_program(program: &BytecodeProgram) -> Vec<u8> {
+    let mut bytes = Vec::<u8>::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    push_u32(&mut bytes, program.variable_count);
+    push_u32(&mut bytes, program.functions.len() as u32);
+    for function in &program.functions {
+        push_u32(&mut bytes, function.address);
+        push_u32(&mut bytes, function.parameter_count);
+        push_u32(&mut bytes, function.locals_start);
+        push_u32(&mut bytes, function.locals_end);
+    }
+    push_u32(&mut bytes, program.strings.len() as u32);
+    for string in &program.strings {
+        push_u32(&mut bytes, string.len() as u32);
+        bytes.extend_from_slice(string.as_bytes());
+    }
+    push_u32(&mut bytes, program.code.len() as u32);
+    for &instruction in &program.code {
+        encode_instruction(&mut bytes, instruction);
+    }
+    bytes
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.position + count > self.bytes.len() {
+            return Err("Error: Unexpected end of bytecode.".to_string());
+        }
+        let slice = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(slice)
+    }
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let mut buffer = [0u8; 4];
+        buffer.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buffer))
+    }
+    fn read_f64(&mut self) -> Result<f64, String> {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(self.take(8)?);
+        Ok(f64::from_le_bytes(buffer))
+    }
+    fn read_i64(&mut self) -> Result<i64, String> {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(self.take(8)?);
+        Ok(i64::from_le_bytes(buffer))
+    }
+    fn read_type(&mut self) -> Result<Type, String> {
+        let index = self.read_u8()?;
+        TYPES
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| format!("Error: Invalid type {}.", index))
+    }
+    fn read_string(&mut self) -> Result<String, String> {
+        let length = self.read_u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "Error: Invalid string in bytecode.".to_string())
+    }
+}
+
+fn decode_instruction(reader: &mut ByteReader) -> Result<Instruction, String> {
+    use Instruction::*;
+    Ok(match reader.read_u8()? {
+        0 => Halt,
+        1 => Push(reader.read_f64()?),
+        2 => Load(reader.read_u32()?),
+        3 => Store(reader.read_u32()?),
+        4 => Add,
+        5 => Subtract,
+        6 => Multiply,
+        7 => Divide,
+        8 => Equal,
+        9 => NotEqual,
+        10 => Less,
+        11 => LessOrEqual,
+        12 => Greater,
+        13 => GreaterOrEqual,
+        14 => Jump(reader.read_u32()?),
+        15 => JumpIfFalse(reader.read_u32()?),
+        16 => Input(reader.read_u32()?, reader.read_type()?),
+        17 => Output(reader.read_u32()?),
+        18 => Call(reader.read_u32()?),
+        19 => Return,
+        20 => Remainder,
+        21 => Power,
+        22 => Negate,
+        23 => {
+            let index = reader.read_u8()?;
+            CallBuiltin(
+                *BuiltinFunction::ALL
+                    .get(index as usize)
+                    .ok_or_else(|| format!("Error: Invalid built-in function {}.", index))?,
+            )
+        }
+        24 => PushInt(reader.read_i64()?),
+        25 => PushBool(reader.read_u8()? != 0),
+        26 => PushString(reader.read_u32()?),
+        27 => ToFloat,
+        28 => NewArray(reader.read_u32()?, reader.read_type()?),
+        29 => LoadElement(reader.read_u32()?),
+        30 => StoreElement(reader.read_u32()?),
+        31 => PushInput(reader.read_type()?),
+        opcode => return Err(format!("Error: Invalid opcode {}.", opcode)),
+    })
+}
+
+pub fn deserialize_program(bytes: &[u8]) -> Result<BytecodeProgram, String> {
+    let mut reader = ByteReader { bytes, position: 0 };
+    if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err("Error: Not a calc bytecode file.".to_string());
+    }
+    let version = reader.read_u8()?;
+    if version != FORMAT_VERSION {
+        return Err(format!("Error: Unsupported bytecode version {}.", version));
+    }
+    let variable_count = reader.read_u32()?;
+    let function_count = reader.read_u32()?;
+    let mut functions = Vec::<BytecodeFunction>::new();
+    for _ in 0..function_count {
+        functions.push(BytecodeFunction {
+            address: reader.read_u32()?,
+            parameter_count: reader.read_u32()?,
+            locals_start: reader.read_u32()?,
+            locals_end: reader.read_u32()?,
+        });
+    }
+    let string_count = reader.read_u32()?;
+    let mut strings = Vec::<String>::new();
+    for _ in 0..string_count {
+        strings.push(reader.read_string()?);
+    }
+    let instruction_count = reader.read_u32()?;
+    let mut code = Vec::<Instruction>::new();
+    for _ in 0..instruction_count {
+        code.push(decode_instruction(&mut reader)?);
+    }
+    if reader.position != bytes.len() {
+        return Err("Error: Unexpected data after the bytecode.".to_string());
+    }
+    let program = BytecodeProgram {
+        variable_count,
+        functions,
+        strings,
+        code,
+    };
+    check_variables(&program)?;
+    check_array_sizes(&program)?;
+    Ok(program)
+}
@@ -0,0 +1,636 @@
+use crate::analyzer::{
+    comparison_operator_symbol, AnalyzedProgram, AnalyzedStatement, BuiltinFunction,
+};
+use crate::compiler::{
+    lower_condition, lower_expr, lower_expr_to, parenthesize, precedence, render_infix,
+    ArithmeticOperator, Backend, LoweredCondition, LoweredExpr, Operation, ATOM_PRECEDENCE,
+    NEGATION_PRECEDENCE,
+};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+pub struct CBackend;
+
+impl Backend for CBackend {
+    fn file_suffix(&self) -> &'static str {
+        ".c"
+    }
+    fn translate_program(
+        &self,
+        variables: &SymbolTable,
+        analyzed_program: &AnalyzedProgram,
+    ) -> Result<String, String> {
+        translate_to_c_program(variables, analyzed_program)
+    }
+}
+
+// The headers of the generated code.
+const HEADERS: &str = r#"#include <ctype.h>
+#include <errno.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+"#;
+
+// The functions used by the generated code, each one after the functions it calls.
+// Only the functions called by a program are emitted, so that it compiles
+// without warnings about unused functions.
+// Strings are never freed, as the programs are short-lived.
+const RUNTIME: [(&str, &str); 15] = [
+    (
+        "calc_input_line",
+        r#"static char *calc_input_line(void) {
+    char buffer[4096];
+    char *start = buffer;
+    char *end;
+    char *result;
+    fputs("? ", stderr);
+    fflush(stderr);
+    if (!fgets(buffer, sizeof buffer, stdin)) {
+        buffer[0] = '\0';
+    }
+    while (isspace((unsigned char)*start)) {
+        start++;
+    }
+    end = start + strlen(start);
+    while (end > start && isspace((unsigned char)end[-1])) {
+        end--;
+    }
+    *end = '\0';
+    result = malloc(strlen(start) + 1);
+    strcpy(result, start);
+    return result;
+}
+"#,
+    ),
+    (
+        "calc_input_int",
+        r#"static long long calc_input_int(void) {
+    char *text = calc_input_line();
+    char *end;
+    long long value;
+    errno = 0;
+    value = strtoll(text, &end, 10);
+    if (*text == '\0' || *end != '\0' || errno != 0) {
+        value = 0;
+    }
+    free(text);
+    return value;
+}
+"#,
+    ),
+    (
+        "calc_input_float",
+        r#"static double calc_input_float(void) {
+    char *text = calc_input_line();
+    char *end;
+    double value = strtod(text, &end);
+    if (*text == '\0' || *end != '\0') {
+        value = 0.0;
+    }
+    free(text);
+    return value;
+}
+"#,
+    ),
+    (
+        "calc_input_bool",
+        r#"static int calc_input_bool(void) {
+    char *text = calc_input_line();
+    int value = strcmp(text, "true") == 0;
+    free(text);
+    return value;
+}
+"#,
+    ),
+    (
+        "calc_input_string",
+        r#"static char *calc_input_string(void) {
+    return calc_input_line();
+}
+"#,
+    ),
+    (
+        "calc_concat",
+        r#"static char *calc_concat(int count, ...) {
+    va_list strings;
+    size_t length = 0;
+    char *result;
+    int i;
+    va_start(strings, count);
+    for (i = 0; i < count; i++) {
+        length += strlen(va_arg(strings, const char *));
+    }
+    va_end(strings);
+    result = malloc(length + 1);
+    result[0] = '\0';
+    va_start(strings, count);
+    for (i = 0; i < count; i++) {
+        strcat(result, va_arg(strings, const char *));
+    }
+    va_end(strings);
+    return result;
+}
+"#,
+    ),
+    (
+        "calc_pow_int",
+        r#"static long long calc_pow_int(long long base, long long exponent) {
+    long long result = 1;
+    for (; exponent > 0; exponent--) {
+        result *= base;
+    }
+    return result;
+}
+"#,
+    ),
+    (
+        "calc_min_int",
+        r#"static long long calc_min_int(long long first, long long second) {
+    return first < second ? first : second;
+}
+"#,
+    ),
+    (
+        "calc_max_int",
+        r#"static long long calc_max_int(long long first, long long second) {
+    return first > second ? first : second;
+}
+"#,
+    ),
+    (
+        "calc_index",
+        r#"static long long calc_index(long long index, long long size) {
+    if (index < 0 || index >= size) {
+        fprintf(stderr, "Error: Index %lld is out of bounds for an array of size %lld.\n",
+                index, size);
+        exit(1);
+    }
+    return index;
+}
+"#,
+    ),
+    (
+        "calc_fill_strings",
+        r#"static void calc_fill_strings(char **strings, long long size) {
+    long long i;
+    for (i = 0; i < size; i++) {
+        strings[i] = "";
+    }
+}
+"#,
+    ),
+    (
+        "calc_print_int",
+        r#"static void calc_print_int(long long value) {
+    printf("%lld", value);
+}
+"#,
+    ),
+    (
+        "calc_print_float",
+        r#"/* Prints a float like Rust does: with the fewest digits that read back
+   as the same value, and never with an exponent. */
+static void calc_print_float(double value) {
+    char text[32];
+    char digits[20];
+    char *cursor = text;
+    int precision, exponent, digit_count = 0, i;
+    if (isnan(value)) {
+        fputs("NaN", stdout);
+        return;
+    }
+    if (isinf(value)) {
+        fputs(value < 0 ? "-inf" : "inf", stdout);
+        return;
+    }
+    for (precision = 0; precision < 17; precision++) {
+        snprintf(text, sizeof text, "%.*e", precision, value);
+        if (strtod(text, NULL) == value) {
+            break;
+        }
+    }
+    if (*cursor == '-') {
+        putchar('-');
+        cursor++;
+    }
+    for (; *cursor != 'e'; cursor++) {
+        if (*cursor != '.') {
+            digits[digit_count++] = *cursor;
+        }
+    }
+    exponent = atoi(cursor + 1);
+    if (exponent < 0) {
+        fputs("0.", stdout);
+        for (i = -1; i > exponent; i--) {
+            putchar('0');
+        }
+        fwrite(digits, 1, digit_count, stdout);
+    } else if (exponent + 1 >= digit_count) {
+        fwrite(digits, 1, digit_count, stdout);
+        for (i = digit_count; i <= exponent; i++) {
+            putchar('0');
+        }
+    } else {
+        fwrite(digits, 1, exponent + 1, stdout);
+        putchar('.');
+        fwrite(digits + exponent + 1, 1, digit_count - exponent - 1, stdout);
+    }
+}
+"#,
+    ),
+    (
+        "calc_print_bool",
+        r#"static void calc_print_bool(int value) {
+    fputs(value ? "true" : "false", stdout);
+}
+"#,
+    ),
+    (
+        "calc_print_string",
+        r#"static void calc_print_string(const char *value) {
+    fputs(value, stdout);
+}
+"#,
+    ),
+];
+
+fn c_type(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "long long",
+        Type::Float => "double",
+        Type::Bool => "int",
+        Type::Str => "char *",
+    }
+}
+
+fn c_declaration(value_type: Type, identifier: &str) -> String {
+    let c_type = c_type(value_type);
+    if c_type.ends_with('*') {
+        format!("{}{}", c_type, identifier)
+    } else {
+        format!("{} {}", c_type, identifier)
+    }
+}
+
+// The name of the runtime function handling values of the given type.
+fn runtime_suffix(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "int",
+        Type::Float => "float",
+        Type::Bool => "bool",
+        Type::Str => "string",
+    }
+}
+
+// Non-printable characters are written as octal escape sequences,
+// which never take the following characters with them.
+fn translate_to_c_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => result += "\\\"",
+            b'\\' => result += "\\\\",
+            b'\n' => result += "\\n",
+            b'\t' => result += "\\t",
+            b' '..=b'~' => result.push(byte as char),
+            _ => result += &format!("\\{:03o}", byte),
+        }
+    }
+    result + "\""
+}
+
+fn translate_to_c_value(value: &Value) -> String {
+    match value {
+        // The smallest integer cannot be written as a negated literal.
+        Value::Int(i64::MIN) => "(-9223372036854775807LL - 1)".to_string(),
+        Value::Int(value) => value.to_string() + "LL",
+        Value::Float(value) if value.is_nan() => "NAN".to_string(),
+        Value::Float(value) if value.is_infinite() && *value < 0. => "-INFINITY".to_string(),
+        Value::Float(value) if value.is_infinite() => "INFINITY".to_string(),
+        Value::Float(value) => format!("{:?}", value),
+        Value::Bool(value) => (*value as i32).to_string(),
+        Value::Str(value) => translate_to_c_string(value),
+        // Usable only as the initializer of an array.
+        Value::Array(items) => format!(
+            "{{{}}}",
+            items
+                .iter()
+                .map(translate_to_c_value)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn translate_to_c_list(variables: &SymbolTable, lowered_exprs: &[LoweredExpr]) -> String {
+    lowered_exprs
+        .iter()
+        .map(|lowered_expr| translate_to_c_expr(variables, lowered_expr))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn translate_to_c_element(variables: &SymbolTable, handle: usize, index: &LoweredExpr) -> String {
+    format!(
+        "v_{}[calc_index({}, {})]",
+        variables.get_name(handle),
+        translate_to_c_expr(variables, index),
+        variables.array_size(handle).unwrap_or(0)
+    )
+}
+
+fn translate_to_c_expr(variables: &SymbolTable, lowered_expr: &LoweredExpr) -> String {
+    match &lowered_expr.0 {
+        Operation::Literal(value) => translate_to_c_value(value),
+        Operation::Variable(handle) => "v_".to_string() + &variables.get_name(*handle),
+        Operation::Element(handle, index) => translate_to_c_element(variables, *handle, index),
+        Operation::ToFloat(operand) => format!(
+            "((double){})",
+            parenthesize(
+                translate_to_c_expr(variables, operand),
+                precedence(operand) < ATOM_PRECEDENCE
+            )
+        ),
+        Operation::Negation(operand) => {
+            "-".to_string()
+                + &parenthesize(
+                    translate_to_c_expr(variables, operand),
+                    precedence(operand) <= NEGATION_PRECEDENCE,
+                )
+        }
+        Operation::Arithmetic(ArithmeticOperator::Remainder, left, right)
+            if lowered_expr.1 == Type::Float =>
+        {
+            format!(
+                "fmod({}, {})",
+                translate_to_c_expr(variables, left),
+                translate_to_c_expr(variables, right)
+            )
+        }
+        Operation::Arithmetic(..) => render_infix(lowered_expr, &|operand| {
+            translate_to_c_expr(variables, operand)
+        }),
+        Operation::Power(base, exponent) => format!(
+            "{}({}, {})",
+            if lowered_expr.1 == Type::Int {
+                "calc_pow_int"
+            } else {
+                "pow"
+            },
+            translate_to_c_expr(variables, base),
+            translate_to_c_expr(variables, exponent)
+        ),
+        Operation::Concatenation(operands) => format!(
+            "calc_concat({}, {})",
+            operands.len(),
+            translate_to_c_list(variables, operands)
+        ),
+        Operation::FunctionCall(handle, arguments) => format!(
+            "fn_{}({})",
+            variables.get_function(*handle).name,
+            translate_to_c_list(variables, arguments)
+        ),
+        Operation::BuiltinCall(builtin, arguments) => {
+            let function = match (builtin, lowered_expr.1) {
+                (BuiltinFunction::Abs, Type::Int) => "llabs",
+                (BuiltinFunction::Abs, _) => "fabs",
+                (BuiltinFunction::Min, Type::Int) => "calc_min_int",
+                (BuiltinFunction::Min, _) => "fmin",
+                (BuiltinFunction::Max, Type::Int) => "calc_max_int",
+                (BuiltinFunction::Max, _) => "fmax",
+                (builtin, _) => builtin.name(),
+            };
+            format!(
+                "{}({})",
+                function,
+                translate_to_c_list(variables, arguments)
+            )
+        }
+    }
+}
+
+// Strings are compared by their content.
+fn translate_to_c_condition(variables: &SymbolTable, condition: &LoweredCondition) -> String {
+    let (left, operator, right) = condition;
+    let operator = comparison_operator_symbol(*operator);
+    if left.1 == Type::Str {
+        format!(
+            "strcmp({}, {}) {} 0",
+            translate_to_c_expr(variables, left),
+            translate_to_c_expr(variables, right),
+            operator
+        )
+    } else {
+        format!(
+            "{} {} {}",
+            translate_to_c_expr(variables, left),
+            operator,
+            translate_to_c_expr(variables, right)
+        )
+    }
+}
+
+fn translate_to_c_statement(
+    variables: &SymbolTable,
+    analyzed_statement: &AnalyzedStatement,
+    indentation: usize,
+) -> Result<String, String> {
+    Ok(match analyzed_statement {
+        AnalyzedStatement::Assignment(handle, expr) => format!(
+            "v_{} = {};",
+            variables.get_name(*handle),
+            translate_to_c_expr(
+                variables,
+                &lower_expr_to(variables, expr, variables.get_type(*handle))?
+            )
+        ),
+        AnalyzedStatement::ElementAssignment(handle, index, expr) => format!(
+            "{} = {};",
+            translate_to_c_element(variables, *handle, &lower_expr(variables, index)?),
+            translate_to_c_expr(
+                variables,
+                &lower_expr_to(variables, expr, variables.get_type(*handle))?
+            )
+        ),
+        // The elements of arrays are zeros, except strings, which are filled with empty strings.
+        AnalyzedStatement::Declaration(handle) => {
+            let variable_type = variables.get_type(*handle);
+            let name = "v_".to_string() + &variables.get_name(*handle);
+            match variables.array_size(*handle) {
+                Some(size) if variable_type == Type::Str => format!(
+                    "{}[{}];\n{}calc_fill_strings({}, {});",
+                    c_declaration(variable_type, &name),
+                    size,
+                    "    ".repeat(indentation),
+                    name,
+                    size
+                ),
+                Some(size) => format!("{}[{}] = {{0}};", c_declaration(variable_type, &name), size),
+                None => format!(
+                    "{} = {};",
+                    c_declaration(variable_type, &name),
+                    translate_to_c_value(&variable_type.default_value())
+                ),
+            }
+        }
+        AnalyzedStatement::InputOperation(handle) => format!(
+            "v_{} = calc_input_{}();",
+            variables.get_name(*handle),
+            runtime_suffix(variables.get_type(*handle))
+        ),
+        AnalyzedStatement::ElementInput(handle, index) => format!(
+            "{} = calc_input_{}();",
+            translate_to_c_element(variables, *handle, &lower_expr(variables, index)?),
+            runtime_suffix(variables.get_type(*handle))
+        ),
+        // Every item is printed by its own call.
+        AnalyzedStatement::OutputOperation(exprs) => {
+            let mut result = String::new();
+            for expr in exprs {
+                let lowered_expr = lower_expr(variables, expr)?;
+                result += &format!(
+                    "calc_print_{}({});\n",
+                    runtime_suffix(lowered_expr.1),
+                    translate_to_c_expr(variables, &lowered_expr)
+                );
+                result += &"    ".repeat(indentation);
+            }
+            result + "putchar('\\n');"
+        }
+        AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+            let mut result = format!(
+                "if ({}) {{\n{}",
+                translate_to_c_condition(variables, &lower_condition(variables, condition)?),
+                translate_to_c_block(variables, then_block, indentation + 1)?
+            );
+            if !else_block.is_empty() {
+                result += &"    ".repeat(indentation);
+                result += "} else {\n";
+                result += &translate_to_c_block(variables, else_block, indentation + 1)?;
+            }
+            result += &"    ".repeat(indentation);
+            result += "}";
+            result
+        }
+        AnalyzedStatement::WhileLoop(condition, body) => format!(
+            "while ({}) {{\n{}{}}}",
+            translate_to_c_condition(variables, &lower_condition(variables, condition)?),
+            translate_to_c_block(variables, body, indentation + 1)?,
+            "    ".repeat(indentation)
+        ),
+        AnalyzedStatement::FunctionDefinition(_) => String::new(),
+        AnalyzedStatement::Return(handle, expr) => {
+            let return_type = variables.get_function(*handle).return_type;
+            format!(
+                "return {};",
+                translate_to_c_expr(variables, &lower_expr_to(variables, expr, return_type)?)
+            )
+        }
+    })
+}
+
+fn translate_to_c_signature(variables: &SymbolTable, handle: usize) -> String {
+    let function = variables.get_function(handle);
+    let parameters = function
+        .locals
+        .clone()
+        .take(function.parameter_count)
+        .map(|local| {
+            c_declaration(
+                variables.get_type(local),
+                &("v_".to_string() + &variables.get_name(local)),
+            )
+        })
+        .collect::<Vec<String>>();
+    format!(
+        "static {}({})",
+        c_declaration(function.return_type, &("fn_".to_string() + &function.name)),
+        if parameters.is_empty() {
+            "void".to_string()
+        } else {
+            parameters.join(", ")
+        }
+    )
+}
+
+// Functions that end without a return statement return the default value of their type.
+fn translate_to_c_function(variables: &SymbolTable, handle: usize) -> Result<String, String> {
+    let function = variables.get_function(handle);
+    let mut result = translate_to_c_signature(variables, handle);
+    result += " {\n";
+    result += &translate_to_c_block(variables, &function.body, 1)?;
+    result += &format!(
+        "    return {};\n",
+        translate_to_c_value(&function.return_type.default_value())
+    );
+    result += "}\n";
+    Ok(result)
+}
+
+fn translate_to_c_block(
+    variables: &SymbolTable,
+    analyzed_block: &AnalyzedProgram,
+    indentation: usize,
+) -> Result<String, String> {
+    let mut result = String::new();
+    for (statement, _) in analyzed_block {
+        result += &"    ".repeat(indentation);
+        result += &translate_to_c_statement(variables, statement, indentation)?;
+        result += "\n";
+    }
+    Ok(result)
+}
+
+// The runtime functions called by the given code,
+// directly or through other runtime functions.
+fn translate_to_c_runtime(code: &str) -> String {
+    let mut calls = code.to_string();
+    let mut used_functions = Vec::<&str>::new();
+    for (name, definition) in RUNTIME.iter().rev() {
+        if calls.contains(&format!("{}(", name)) {
+            calls += definition;
+            used_functions.insert(0, definition);
+        }
+    }
+    used_functions
+        .iter()
+        .map(|definition| format!("\n{}", definition))
+        .collect()
+}
+
+// The functions are declared before being defined, so that they can call each other.
+pub fn translate_to_c_program(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Result<String, String> {
+    let mut declarations = String::new();
+    let mut definitions = String::new();
+    let mut main_body = String::new();
+    for (statement, _) in analyzed_program {
+        if let AnalyzedStatement::FunctionDefinition(handle) = statement {
+            declarations += &translate_to_c_signature(variables, *handle);
+            declarations += ";\n";
+            definitions += "\n";
+            definitions += &translate_to_c_function(variables, *handle)?;
+        } else {
+            main_body += "    ";
+            main_body += &translate_to_c_statement(variables, statement, 1)?;
+            main_body += "\n";
+        }
+    }
+    let mut c_program = String::from(HEADERS);
+    c_program += &translate_to_c_runtime(&(definitions.clone() + &main_body));
+    if !declarations.is_empty() {
+        c_program += "\n";
+        c_program += &declarations;
+    }
+    c_program += &definitions;
+    c_program += "\nint main(void) {\n";
+    c_program += &main_body;
+    c_program += "    return 0;\n";
+    c_program += "}\n";
+    Ok(c_program)
+}
@@ -1,101 +1,278 @@
 use crate::analyzer::{
-    AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement, AnalyzedTerm,
+    AnalyzedCondition, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedTerm, BuiltinFunction,
 };
-use crate::parser::{ExprOperator, TermOperator};
+use crate::c_backend::CBackend;
+use crate::parser::{ComparisonOperator, ExprOperator, TermOperator};
+use crate::rust_backend::RustBackend;
 use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+use crate::wat_backend::WatBackend;
 
-fn translate_to_rust_factor(variables: &SymbolTable, analyzed_factor: &AnalyzedFactor) -> String {
-    match analyzed_factor {
-        AnalyzedFactor::Literal(value) => value.to_string() + "f64",
-        AnalyzedFactor::Identifier(handle) => "_".to_string() + &variables.get_name(*handle),
-        AnalyzedFactor::SubExpression(expr) => {
-            "(".to_string() + &translate_to_rust_expr(variables, expr) + ")"
-        }
+// A code generator, which translates an analyzed program into source code of another language.
+pub trait Backend {
+    // The suffix of the generated files.
+    fn file_suffix(&self) -> &'static str;
+    fn translate_program(
+        &self,
+        variables: &SymbolTable,
+        analyzed_program: &AnalyzedProgram,
+    ) -> Result<String, String>;
+}
+
+pub const TARGETS: [&str; 3] = ["rust", "c", "wat"];
+
+pub fn find_backend(target: &str) -> Option<Box<dyn Backend>> {
+    match target {
+        "rust" => Some(Box::new(RustBackend)),
+        "c" => Some(Box::new(CBackend)),
+        "wat" => Some(Box::new(WatBackend)),
+        _ => None,
     }
 }
 
-fn translate_to_rust_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm) -> String {
-    let mut result = translate_to_rust_factor(variables, &analyzed_term.0);
-    for factor in &analyzed_term.1 {
-        match factor.0 {
-            TermOperator::Multiply => {
-                result += " * ";
-                result += &translate_to_rust_factor(variables, &factor.1);
-            }
-            TermOperator::Divide => {
-                result += " / ";
-                result += &translate_to_rust_factor(variables, &factor.1);
-            }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl ArithmeticOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ArithmeticOperator::Add => "+",
+            ArithmeticOperator::Subtract => "-",
+            ArithmeticOperator::Multiply => "*",
+            ArithmeticOperator::Divide => "/",
+            ArithmeticOperator::Remainder => "%",
         }
     }
-    result
 }
 
-fn translate_to_rust_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr) -> String {
-    let mut result = translate_to_rust_term(variables, &analyzed_expr.0);
-    for term in &analyzed_expr.1 {
-        match term.0 {
-            ExprOperator::Add => {
-                result += " + ";
-                result += &translate_to_rust_term(variables, &term.1);
-            }
-            ExprOperator::Subtract => {
-                result += " - ";
-                result += &translate_to_rust_term(variables, &term.1);
-            }
-        }
+// The operands of every operation already have the type required by the operation,
+// so the backends never have to apply the promotion rules of the language.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Literal(Value),
+    Variable(usize),
+    // An element of an array variable, and its index.
+    Element(usize, Box<LoweredExpr>),
+    // Converts an integer into a float.
+    ToFloat(Box<LoweredExpr>),
+    Negation(Box<LoweredExpr>),
+    Arithmetic(ArithmeticOperator, Box<LoweredExpr>, Box<LoweredExpr>),
+    Power(Box<LoweredExpr>, Box<LoweredExpr>),
+    Concatenation(Vec<LoweredExpr>),
+    FunctionCall(usize, Vec<LoweredExpr>),
+    BuiltinCall(BuiltinFunction, Vec<LoweredExpr>),
+}
+
+// The last item of a lowered expression is its type.
+pub type LoweredExpr = (Operation, Type);
+
+// Both operands have the same type.
+pub type LoweredCondition = (LoweredExpr, ComparisonOperator, LoweredExpr);
+
+pub fn promote(lowered_expr: LoweredExpr, target: Type) -> LoweredExpr {
+    if lowered_expr.1 == Type::Int && target == Type::Float {
+        (Operation::ToFloat(Box::new(lowered_expr)), Type::Float)
+    } else {
+        lowered_expr
     }
-    result
 }
 
-fn translate_to_rust_statement(
+fn lower_factor(
     variables: &SymbolTable,
-    analyzed_statement: &AnalyzedStatement,
-) -> String {
-    match analyzed_statement {
-        AnalyzedStatement::Assignment(handle, expr) => format!(
-            "_{} = {}",
-            variables.get_name(*handle),
-            translate_to_rust_expr(&variables, expr)
+    analyzed_factor: &AnalyzedFactor,
+) -> Result<LoweredExpr, String> {
+    Ok(match analyzed_factor {
+        AnalyzedFactor::Literal(value) => (Operation::Literal(value.clone()), value.get_type()),
+        AnalyzedFactor::Identifier(handle) => {
+            (Operation::Variable(*handle), variables.get_type(*handle))
+        }
+        AnalyzedFactor::Element(handle, index) => (
+            Operation::Element(*handle, Box::new(lower_expr(variables, index)?)),
+            variables.get_type(*handle),
         ),
-        AnalyzedStatement::Declaration(handle) => {
-            format!("let mut _{} = 0.0", variables.get_name(*handle))
+        AnalyzedFactor::SubExpression(expr) => lower_expr(variables, expr)?,
+        AnalyzedFactor::FunctionCall(handle, arguments) => {
+            let function = variables.get_function(*handle);
+            let arguments = arguments
+                .iter()
+                .zip(function.locals.clone())
+                .map(|(argument, parameter)| {
+                    lower_expr_to(variables, argument, variables.get_type(parameter))
+                })
+                .collect::<Result<_, _>>()?;
+            (
+                Operation::FunctionCall(*handle, arguments),
+                function.return_type,
+            )
+        }
+        AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+            let argument_types: Vec<Type> = arguments.iter().map(|argument| argument.2).collect();
+            let result_type = builtin.result_type(&argument_types);
+            let argument_type = match builtin {
+                BuiltinFunction::Sqrt | BuiltinFunction::Sin => Type::Float,
+                _ => result_type,
+            };
+            let arguments = arguments
+                .iter()
+                .map(|argument| lower_expr_to(variables, argument, argument_type))
+                .collect::<Result<_, _>>()?;
+            (Operation::BuiltinCall(*builtin, arguments), result_type)
+        }
+        // Native functions are Rust closures of the engine, which exist only in the interpreter.
+        AnalyzedFactor::NativeCall(handle, _) => {
+            return Err(format!(
+                "Error: The native function '{}' cannot be translated to other languages.",
+                variables.get_native(*handle).name
+            ))
+        }
+        AnalyzedFactor::Negation(factor) => {
+            let operand = lower_factor(variables, factor)?;
+            let result_type = operand.1;
+            (Operation::Negation(Box::new(operand)), result_type)
         }
-        AnalyzedStatement::InputOperation(handle) => {
-            format!("_{} = input()", variables.get_name(*handle))
+        AnalyzedFactor::Power(base, exponent) => {
+            let base = lower_factor(variables, base)?;
+            let exponent = lower_factor(variables, exponent)?;
+            let result_type = base.1.numeric_result(exponent.1);
+            (
+                Operation::Power(
+                    Box::new(promote(base, result_type)),
+                    Box::new(promote(exponent, result_type)),
+                ),
+                result_type,
+            )
         }
-        AnalyzedStatement::OutputOperation(expr) => format!(
-            "println!(\"{}\", {})",
-            "{}",
-            translate_to_rust_expr(&variables, expr)
+    })
+}
+
+// Divisions give floats even between integers.
+fn arithmetic(operator: ArithmeticOperator, left: LoweredExpr, right: LoweredExpr) -> LoweredExpr {
+    let result_type = if operator == ArithmeticOperator::Divide {
+        Type::Float
+    } else {
+        left.1.numeric_result(right.1)
+    };
+    (
+        Operation::Arithmetic(
+            operator,
+            Box::new(promote(left, result_type)),
+            Box::new(promote(right, result_type)),
         ),
+        result_type,
+    )
+}
+
+// Operands are combined from left to right, and the partial result
+// is promoted to float only when a float operand is reached, like the interpreter does.
+fn lower_term(
+    variables: &SymbolTable,
+    analyzed_term: &AnalyzedTerm,
+) -> Result<LoweredExpr, String> {
+    let mut result = lower_factor(variables, &analyzed_term.0)?;
+    for (operator, factor) in &analyzed_term.1 {
+        let operator = match operator {
+            TermOperator::Multiply => ArithmeticOperator::Multiply,
+            TermOperator::Divide => ArithmeticOperator::Divide,
+            TermOperator::Remainder => ArithmeticOperator::Remainder,
+        };
+        result = arithmetic(operator, result, lower_factor(variables, factor)?);
+    }
+    Ok(result)
+}
+
+// Fails only on calls to native functions, which cannot be translated.
+pub fn lower_expr(
+    variables: &SymbolTable,
+    analyzed_expr: &AnalyzedExpr,
+) -> Result<LoweredExpr, String> {
+    if analyzed_expr.2 == Type::Str && !analyzed_expr.1.is_empty() {
+        let mut terms = vec![lower_term(variables, &analyzed_expr.0)?];
+        for term in &analyzed_expr.1 {
+            terms.push(lower_term(variables, &term.1)?);
+        }
+        return Ok((Operation::Concatenation(terms), Type::Str));
+    }
+    let mut result = lower_term(variables, &analyzed_expr.0)?;
+    for (operator, term) in &analyzed_expr.1 {
+        let operator = match operator {
+            ExprOperator::Add => ArithmeticOperator::Add,
+            ExprOperator::Subtract => ArithmeticOperator::Subtract,
+        };
+        result = arithmetic(operator, result, lower_term(variables, term)?);
     }
+    Ok(result)
+}
+
+// Lowers an expression whose value is assigned, passed or returned
+// where a value of the given type is expected.
+pub fn lower_expr_to(
+    variables: &SymbolTable,
+    analyzed_expr: &AnalyzedExpr,
+    target: Type,
+) -> Result<LoweredExpr, String> {
+    Ok(promote(lower_expr(variables, analyzed_expr)?, target))
 }
 
-pub fn translate_to_rust_program(
+pub fn lower_condition(
     variables: &SymbolTable,
-    analyzed_program: &AnalyzedProgram,
-) -> String {
-    let mut rust_program = String::new();
-    rust_program += "use std::io::Write;\n";
-    rust_program += "\n";
-    rust_program += "#[allow(dead_code)]\n";
-    rust_program += "fn input() -> f64 {\n";
-    rust_program += "    let mut text = String::new();\n";
-    rust_program += "    eprint!(\"? \");\n";
-    rust_program += "    std::io::stderr().flush().unwrap();\n";
-    rust_program += "    std::io::stdin()\n";
-    rust_program += "        .read_line(&mut text)\n";
-    rust_program += "        .expect(\"Cannot read line.\");\n";
-    rust_program += "    text.trim().parse::<f64>().unwrap_or(0.)\n";
-    rust_program += "}\n";
-    rust_program += "\n";
-    rust_program += "fn main() {\n";
-    for statement in analyzed_program {
-        rust_program += "    ";
-        rust_program += &translate_to_rust_statement(&variables, statement);
-        rust_program += ";\n";
+    analyzed_condition: &AnalyzedCondition,
+) -> Result<LoweredCondition, String> {
+    let (left, operator, right) = analyzed_condition;
+    let operand_type = if left.2.is_numeric() {
+        left.2.numeric_result(right.2)
+    } else {
+        left.2
+    };
+    Ok((
+        lower_expr_to(variables, left, operand_type)?,
+        *operator,
+        lower_expr_to(variables, right, operand_type)?,
+    ))
+}
+
+// The precedence of an expression written with infix operators.
+// Calls and parenthesized operations have the highest one.
+pub const NEGATION_PRECEDENCE: u8 = 3;
+pub const ATOM_PRECEDENCE: u8 = 4;
+
+pub fn precedence(lowered_expr: &LoweredExpr) -> u8 {
+    match &lowered_expr.0 {
+        Operation::Arithmetic(ArithmeticOperator::Add, _, _)
+        | Operation::Arithmetic(ArithmeticOperator::Subtract, _, _) => 1,
+        Operation::Arithmetic(..) => 2,
+        Operation::Negation(_) => NEGATION_PRECEDENCE,
+        Operation::Literal(Value::Int(value)) if *value < 0 => NEGATION_PRECEDENCE,
+        Operation::Literal(Value::Float(value)) if value.is_sign_negative() => NEGATION_PRECEDENCE,
+        _ => ATOM_PRECEDENCE,
+    }
+}
+
+pub fn parenthesize(code: String, needed: bool) -> String {
+    if needed {
+        format!("({})", code)
+    } else {
+        code
+    }
+}
+
+// Renders an infix operation, adding only the parentheses needed by its operands.
+// All the operators are left-associative.
+pub fn render_infix(lowered_expr: &LoweredExpr, render: &dyn Fn(&LoweredExpr) -> String) -> String {
+    if let Operation::Arithmetic(operator, left, right) = &lowered_expr.0 {
+        let operator_precedence = precedence(lowered_expr);
+        format!(
+            "{} {} {}",
+            parenthesize(render(left), precedence(left) < operator_precedence),
+            operator.symbol(),
+            parenthesize(render(right), precedence(right) <= operator_precedence)
+        )
+    } else {
+        render(lowered_expr)
     }
-    rust_program += "}\n";
-    rust_program
 }
@@ -0,0 +1,282 @@
+use crate::analyzer::{AnalyzedProgram, AnalyzedStatement};
+use crate::executor::ExecutionObserver;
+use crate::program_io::ProgramIo;
+use crate::repl::{describe_variable, LineReader, ReadResult};
+use crate::symbol_table::SymbolTable;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const HELP: &str = "\
+Commands:
+  step, s          Execute the current statement (also an empty line)
+  continue, c      Run until the next breakpoint
+  break <line>     Stop before the statements beginning at a line
+  delete <line>    Remove a breakpoint
+  print <var>      Show the value of a variable
+  watch <var>      Show a variable at every stop
+  unwatch <var>    Stop showing a variable
+  vars             Show the variables of the current function and the global variables
+  where            Show the current statement
+  help             Show this help
+  quit, q          Stop the program";
+
+type SharedReader<'a> = Rc<RefCell<&'a mut dyn LineReader>>;
+
+// Runs a program stopping before its first statement, at the breakpoints,
+// and after every step, to read commands from the same lines as the inputs.
+// At the end of the lines, the program runs to completion.
+pub struct Debugger<'a> {
+    source_lines: Vec<&'a str>,
+    program: &'a AnalyzedProgram,
+    reader: SharedReader<'a>,
+    breakpoints: Vec<usize>,
+    watched: Vec<String>,
+    stepping: bool,
+}
+
+// The inputs of the debugged program, read from the lines of the debugger.
+pub struct DebuggerIo<'a> {
+    reader: SharedReader<'a>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(
+        source_code: &'a str,
+        program: &'a AnalyzedProgram,
+        reader: &'a mut dyn LineReader,
+    ) -> Debugger<'a> {
+        Debugger {
+            source_lines: source_code.lines().collect(),
+            program,
+            reader: Rc::new(RefCell::new(reader)),
+            breakpoints: Vec::new(),
+            watched: Vec::new(),
+            stepping: true,
+        }
+    }
+
+    // The devices of the debugged program.
+    pub fn io(&self) -> DebuggerIo<'a> {
+        DebuggerIo {
+            reader: Rc::clone(&self.reader),
+        }
+    }
+
+    fn show_statement(&self, line: usize) {
+        let text = self.source_lines.get(line - 1).unwrap_or(&"");
+        eprintln!("{:>4} | {}", line, text.trim());
+    }
+
+    // As in the analyzer, the variables declared before the current statement
+    // in the blocks enclosing it hide the parameters of the current function, if any.
+    // The global variables can be shown from anywhere.
+    fn find_variable(
+        &self,
+        variables: &SymbolTable,
+        function: Option<usize>,
+        line: usize,
+        name: &str,
+    ) -> Option<Value> {
+        let mut visible = Vec::<usize>::new();
+        let block = match function {
+            Some(function) => {
+                let function = variables.get_function(function);
+                visible.extend(
+                    function.locals.start..function.locals.start + function.parameter_count,
+                );
+                &function.body
+            }
+            None => self.program,
+        };
+        find_scope(block, line, &mut visible);
+        visible
+            .into_iter()
+            .rev()
+            .find(|&handle| variables.get_name(handle) == name)
+            .map(|handle| variables.get_value(handle))
+            .or_else(|| self.find_global(variables, name))
+    }
+
+    fn find_global(&self, variables: &SymbolTable, name: &str) -> Option<Value> {
+        variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn show_variable(
+        &self,
+        variables: &SymbolTable,
+        function: Option<usize>,
+        line: usize,
+        name: &str,
+    ) {
+        match self.find_variable(variables, function, line, name) {
+            Some(value) => eprintln!("{}", describe_variable(name, &value)),
+            None => eprintln!("No variable named '{}'.", name),
+        }
+    }
+
+    fn show_watched(&self, variables: &SymbolTable, function: Option<usize>, line: usize) {
+        for name in &self.watched {
+            self.show_variable(variables, function, line, name);
+        }
+    }
+
+    fn parse_line(&self, argument: &str) -> Option<usize> {
+        match argument.parse::<usize>() {
+            Ok(line) if line > 0 => Some(line),
+            _ => {
+                eprintln!("Invalid line number '{}'.", argument);
+                None
+            }
+        }
+    }
+
+    // Returns whether the program must be resumed.
+    fn run_command(
+        &mut self,
+        variables: &SymbolTable,
+        function: Option<usize>,
+        line: usize,
+        command: &str,
+    ) -> bool {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(position) => (&command[..position], command[position..].trim()),
+            None => (command, ""),
+        };
+        match name {
+            "" | "step" | "s" => {
+                self.stepping = true;
+                return true;
+            }
+            "continue" | "c" => {
+                self.stepping = false;
+                return true;
+            }
+            "break" | "b" => {
+                if let Some(line) = self.parse_line(argument) {
+                    if !self.breakpoints.contains(&line) {
+                        self.breakpoints.push(line);
+                    }
+                    eprintln!("Breakpoint at line {}.", line);
+                }
+            }
+            "delete" | "d" => {
+                if let Some(line) = self.parse_line(argument) {
+                    match self.breakpoints.iter().position(|&other| other == line) {
+                        Some(position) => {
+                            self.breakpoints.remove(position);
+                            eprintln!("Removed the breakpoint at line {}.", line);
+                        }
+                        None => eprintln!("No breakpoint at line {}.", line),
+                    }
+                }
+            }
+            "print" | "p" => self.show_variable(variables, function, line, argument),
+            "watch" | "w" => {
+                if !self.watched.iter().any(|name| name == argument) {
+                    self.watched.push(argument.to_string());
+                }
+                self.show_variable(variables, function, line, argument);
+            }
+            "unwatch" => self.watched.retain(|name| name != argument),
+            "vars" => {
+                if let Some(function) = function {
+                    for local in variables.get_function(function).locals.clone() {
+                        let value = variables.get_value(local);
+                        eprintln!("{}", describe_variable(&variables.get_name(local), &value));
+                    }
+                }
+                for (name, value) in variables.iter() {
+                    eprintln!("{}", describe_variable(name, value));
+                }
+            }
+            "where" => self.show_statement(line),
+            "help" | "h" => eprintln!("{}", HELP),
+            _ => eprintln!("Unknown command '{}'. Type 'help' for the commands.", name),
+        }
+        false
+    }
+}
+
+// Adds to the given handles the variables declared in the block before the statements
+// beginning at the given line, and in the blocks enclosing them,
+// returning whether the line is in the block.
+fn find_scope(block: &AnalyzedProgram, line: usize, visible: &mut Vec<usize>) -> bool {
+    for (statement, statement_line) in block {
+        if *statement_line > line {
+            break;
+        }
+        let inner_blocks = match statement {
+            AnalyzedStatement::Declaration(handle) => {
+                visible.push(*handle);
+                vec![]
+            }
+            AnalyzedStatement::IfElse(_, then_block, else_block) => vec![then_block, else_block],
+            AnalyzedStatement::WhileLoop(_, body) => vec![body],
+            _ => vec![],
+        };
+        for inner_block in inner_blocks {
+            let outer_count = visible.len();
+            if find_scope(inner_block, line, visible) {
+                return true;
+            }
+            visible.truncate(outer_count);
+        }
+        if *statement_line == line {
+            return true;
+        }
+    }
+    false
+}
+
+impl<'a> ProgramIo for DebuggerIo<'a> {
+    // The end of the input, or an interrupted line, reads as an empty line.
+    fn input_line(&mut self) -> Result<String, String> {
+        match self.reader.borrow_mut().read_line("? ") {
+            ReadResult::Line(line) => Ok(line),
+            ReadResult::Interrupted | ReadResult::End => Ok(String::new()),
+        }
+    }
+    fn output_line(&mut self, text: &str) -> Result<(), String> {
+        println!("{}", text);
+        Ok(())
+    }
+}
+
+impl<'a> ExecutionObserver for Debugger<'a> {
+    fn before_statement(
+        &mut self,
+        variables: &SymbolTable,
+        function: Option<usize>,
+        line: usize,
+    ) -> Result<(), String> {
+        if !self.stepping && !self.breakpoints.contains(&line) {
+            return Ok(());
+        }
+        self.show_statement(line);
+        self.show_watched(variables, function, line);
+        loop {
+            let command = self.reader.borrow_mut().read_line("(debug) ");
+            let command = match command {
+                ReadResult::Line(command) => command,
+                // Ctrl-C discards the command being typed.
+                ReadResult::Interrupted => continue,
+                ReadResult::End => {
+                    self.stepping = false;
+                    self.breakpoints.clear();
+                    return Ok(());
+                }
+            };
+            let command = command.trim();
+            if command == "quit" || command == "q" {
+                return Err("Program stopped by the debugger.".to_string());
+            }
+            if self.run_command(variables, function, line, command) {
+                return Ok(());
+            }
+        }
+    }
+}
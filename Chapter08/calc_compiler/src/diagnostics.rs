@@ -0,0 +1,160 @@
+use crate::analyzer::MAX_ARRAY_SIZE;
+use crate::value::Type;
+use nom::error::ErrorKind;
+use nom::Offset;
+
+// Every diagnostic carries its span, that is the slice of the source code
+// it refers to, from which the line and the column are computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic<'a> {
+    InvalidStatement(&'a str),
+    UnclosedBlock(&'a str),
+    DuplicateDeclaration(&'a str),
+    UndeclaredIdentifier(&'a str),
+    DuplicateFunction(&'a str),
+    BuiltinRedefinition(&'a str),
+    UndefinedFunction(&'a str),
+    WrongArgumentCount(&'a str, usize, usize),
+    FunctionInsideBlock(&'a str),
+    ReturnOutsideFunction(&'a str),
+    TypeMismatch(&'a str, Type, Type),
+    InvalidOperand(&'a str, &'static str, Type),
+    IncompatibleOperands(&'a str, &'static str, Type, Type),
+    InvalidArraySize(&'a str),
+    NotAnArray(&'a str),
+    MissingIndex(&'a str),
+}
+
+impl<'a> Diagnostic<'a> {
+    // The diagnostic of a parse error of the given source,
+    // at the input where the innermost parser failed.
+    // The code ending before the closing brace of a block leaves no input.
+    pub fn from_parse_error(
+        source: &'a str,
+        err: nom::Err<(&'a str, ErrorKind)>,
+    ) -> Diagnostic<'a> {
+        match err {
+            nom::Err::Error((rest, _)) | nom::Err::Failure((rest, _))
+                if !rest.trim().is_empty() =>
+            {
+                Diagnostic::InvalidStatement(rest.trim())
+            }
+            _ => Diagnostic::UnclosedBlock(&source[source.trim_end().len()..]),
+        }
+    }
+
+    pub fn span(&self) -> &'a str {
+        use Diagnostic::*;
+        match self {
+            InvalidStatement(span)
+            | UnclosedBlock(span)
+            | DuplicateDeclaration(span)
+            | UndeclaredIdentifier(span)
+            | DuplicateFunction(span)
+            | BuiltinRedefinition(span)
+            | UndefinedFunction(span)
+            | WrongArgumentCount(span, _, _)
+            | FunctionInsideBlock(span)
+            | ReturnOutsideFunction(span)
+            | TypeMismatch(span, _, _)
+            | InvalidOperand(span, _, _)
+            | IncompatibleOperands(span, _, _, _)
+            | InvalidArraySize(span)
+            | NotAnArray(span)
+            | MissingIndex(span) => span,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Diagnostic::*;
+        match self {
+            InvalidStatement(_) => write!(f, "Invalid statement."),
+            UnclosedBlock(_) => write!(f, "Missing '}}' at the end of the code."),
+            DuplicateDeclaration(name) => {
+                write!(f, "Identifier '{}' declared several times.", name)
+            }
+            UndeclaredIdentifier(name) => {
+                write!(f, "Identifier '{}' used before having been declared.", name)
+            }
+            DuplicateFunction(name) => write!(f, "Function '{}' defined several times.", name),
+            BuiltinRedefinition(name) => {
+                write!(f, "Function '{}' is a built-in function.", name)
+            }
+            UndefinedFunction(name) => {
+                write!(f, "Function '{}' called before having been defined.", name)
+            }
+            WrongArgumentCount(name, expected, found) => write!(
+                f,
+                "Function '{}' takes {} arguments but {} were supplied.",
+                name, expected, found
+            ),
+            FunctionInsideBlock(name) => write!(f, "Function '{}' defined inside a block.", name),
+            ReturnOutsideFunction(_) => write!(f, "Return statement outside of a function."),
+            TypeMismatch(_, expected, found) => write!(
+                f,
+                "Mismatched types: expected {}, found {}.",
+                expected, found
+            ),
+            InvalidOperand(_, operator, found) => write!(
+                f,
+                "Cannot apply '{}' to a value of type {}.",
+                operator, found
+            ),
+            IncompatibleOperands(_, operator, left, right) => write!(
+                f,
+                "Cannot apply '{}' to values of types {} and {}.",
+                operator, left, right
+            ),
+            InvalidArraySize(size) => write!(
+                f,
+                "Invalid array size {}: it must be between 1 and {}.",
+                size, MAX_ARRAY_SIZE
+            ),
+            NotAnArray(name) => write!(f, "Identifier '{}' is not an array.", name),
+            MissingIndex(name) => write!(f, "Array '{}' used without an index.", name),
+        }
+    }
+}
+
+// Returns the 1-based line and column where the span begins,
+// or None if the span does not belong to the source.
+pub fn locate(source: &str, span: &str) -> Option<(usize, usize)> {
+    let source_start = source.as_ptr() as usize;
+    let span_start = span.as_ptr() as usize;
+    if span_start < source_start || span_start > source_start + source.len() {
+        return None;
+    }
+    let offset = source.offset(span);
+    let line_start = source[..offset].rfind('\n').map_or(0, |pos| pos + 1);
+    let line = source[..offset].matches('\n').count() + 1;
+    let column = source[line_start..offset].chars().count() + 1;
+    Some((line, column))
+}
+
+// Renders the diagnostic with the offending source line and a caret under the span.
+pub fn render(source: &str, source_path: &str, diagnostic: &Diagnostic) -> String {
+    let mut result = format!("error: {}\n", diagnostic);
+    let (line, column) = match locate(source, diagnostic.span()) {
+        Some(location) => location,
+        None => {
+            result += &format!(" --> {}\n", source_path);
+            return result;
+        }
+    };
+    let source_line = source.lines().nth(line - 1).unwrap_or("");
+    let span_line = diagnostic.span().lines().next().unwrap_or("");
+    let caret_count = span_line.chars().count().max(1);
+    let margin = " ".repeat(line.to_string().len());
+    result += &format!("{} --> {}:{}:{}\n", margin, source_path, line, column);
+    result += &format!("{} |\n", margin);
+    result += &format!("{} | {}\n", line, source_line);
+    let indentation: String = source_line
+        .chars()
+        .take(column - 1)
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+    result += &format!("{} | {}{}\n", margin, indentation, "^".repeat(caret_count));
+    result
+}
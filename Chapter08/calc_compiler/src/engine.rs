@@ -0,0 +1,226 @@
+use crate::analyzer::{self, AnalyzedExpr, AnalyzedProgram, BuiltinFunction};
+use crate::diagnostics::{self, Diagnostic};
+use crate::executor;
+use crate::parser;
+use crate::program_io::{ConsoleIo, ProgramIo};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// The name of the sources in the error messages.
+const SOURCE_NAME: &str = "<formula>";
+
+// The identifier of the next engine, so that every engine has its own.
+static NEXT_ENGINE_ID: AtomicUsize = AtomicUsize::new(0);
+
+// A compiled source, made of statements that may be followed by an expression,
+// whose value is the result of the evaluation.
+// It can be evaluated only by the engine that compiled it,
+// as it refers to the variables of that engine by their handles.
+#[derive(Debug)]
+pub struct Formula {
+    engine_id: usize,
+    statements: AnalyzedProgram,
+    result: Option<AnalyzedExpr>,
+}
+
+// Compiles calc sources once, to evaluate them many times.
+// The sources share the global variables, including the ones defined by the host,
+// and they can call the native functions registered by the host.
+// The sources are not optimized, as the host may read any variable they assign.
+#[derive(Debug)]
+pub struct Engine {
+    id: usize,
+    variables: SymbolTable,
+}
+
+// A clone gets its own identifier, as its variables may then diverge from the original ones.
+impl Clone for Engine {
+    fn clone(&self) -> Engine {
+        Engine {
+            id: NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed),
+            variables: self.variables.clone(),
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine {
+            id: NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed),
+            variables: SymbolTable::new(),
+        }
+    }
+
+    // The type of the variable is the type of its initial value,
+    // and arrays must have at least an element, all of the same type.
+    pub fn define_variable(&mut self, name: &str, value: Value) -> Result<(), String> {
+        if let Value::Array(items) = &value {
+            let element_type = items
+                .first()
+                .ok_or_else(|| format!("Error: Array '{}' is empty.", name))?
+                .get_type();
+            if items
+                .iter()
+                .any(|item| matches!(item, Value::Array(_)) || item.get_type() != element_type)
+            {
+                return Err(format!(
+                    "Error: The elements of array '{}' must have the same type.",
+                    name
+                ));
+            }
+        }
+        self.variables
+            .insert_value(name, value)
+            .map(|_| ())
+            .map_err(|err| format!("Error: {}", err))
+    }
+
+    fn find_global(&self, name: &str) -> Result<usize, String> {
+        self.variables
+            .find_symbol(name)
+            .map_err(|err| format!("Error: {}", err))
+    }
+
+    // Integers are converted when assigned to float variables,
+    // and arrays must keep their size.
+    pub fn set_variable(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let handle = self.find_global(name)?;
+        let current_value = self.variables.get_value(handle);
+        let variable_type = current_value.get_type();
+        let accepted = match (&current_value, &value) {
+            (Value::Array(current_items), Value::Array(items)) => {
+                current_items.len() == items.len()
+                    && items.iter().all(|item| {
+                        !matches!(item, Value::Array(_)) && variable_type.accepts(item.get_type())
+                    })
+            }
+            (Value::Array(_), _) | (_, Value::Array(_)) => false,
+            _ => variable_type.accepts(value.get_type()),
+        };
+        if !accepted {
+            return Err(format!(
+                "Error: Cannot assign the value {} to variable '{}'.",
+                value, name
+            ));
+        }
+        let value = match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| item.convert_to(variable_type))
+                    .collect(),
+            ),
+            value => value,
+        };
+        self.variables.set_value(handle, value);
+        Ok(())
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        self.variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    // The arguments are converted to the types of the parameters before the call.
+    pub fn register_function<F>(
+        &mut self,
+        name: &str,
+        parameter_types: &[Type],
+        return_type: Type,
+        function: F,
+    ) -> Result<(), String>
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + 'static,
+    {
+        let is_defined = (0..self.variables.function_count())
+            .any(|handle| self.variables.get_function(handle).name == name);
+        let result = if BuiltinFunction::find(name).is_some() {
+            Err(Diagnostic::BuiltinRedefinition(name))
+        } else if is_defined {
+            Err(Diagnostic::DuplicateFunction(name))
+        } else {
+            self.variables.insert_native(
+                name,
+                parameter_types.to_vec(),
+                return_type,
+                Rc::new(function),
+            )
+        };
+        result.map(|_| ()).map_err(|err| format!("Error: {}", err))
+    }
+
+    // A source containing errors leaves no declarations behind.
+    pub fn compile(&mut self, source: &str) -> Result<Formula, String> {
+        let (rest, parsed_program) = parser::parse_program(source)
+            .map_err(|err| render(source, &[Diagnostic::from_parse_error(source, err)]))?;
+        let rest = rest.trim();
+        let parsed_result = if rest.is_empty() {
+            None
+        } else {
+            match parser::parse_expr(rest) {
+                Ok((after, parsed_expr)) if after.trim().is_empty() => Some(parsed_expr),
+                _ => return Err(render(source, &[Diagnostic::InvalidStatement(rest)])),
+            }
+        };
+        let previous_variables = self.variables.clone();
+        let formula = analyzer::analyze_program(&mut self.variables, &parsed_program).and_then(
+            |statements| {
+                let result = match &parsed_result {
+                    Some(parsed_expr) => Some(
+                        analyzer::analyze_expr(&mut self.variables, parsed_expr)
+                            .map_err(|err| vec![err])?,
+                    ),
+                    None => None,
+                };
+                Ok(Formula {
+                    engine_id: self.id,
+                    statements,
+                    result,
+                })
+            },
+        );
+        formula.map_err(|errors| {
+            self.variables = previous_variables;
+            render(source, &errors)
+        })
+    }
+
+    // The input and output statements use the console.
+    pub fn evaluate(&mut self, formula: &Formula) -> Result<Option<Value>, String> {
+        self.evaluate_with_io(formula, &mut ConsoleIo)
+    }
+
+    pub fn evaluate_with_io(
+        &mut self,
+        formula: &Formula,
+        io: &mut dyn ProgramIo,
+    ) -> Result<Option<Value>, String> {
+        if formula.engine_id != self.id {
+            return Err("Error: The formula was compiled by another engine.".to_string());
+        }
+        executor::execute_program(&mut self.variables, &formula.statements, io)?;
+        match &formula.result {
+            Some(expr) => executor::evaluate_expression(&mut self.variables, expr, io).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn render(source: &str, errors: &[Diagnostic]) -> String {
+    errors
+        .iter()
+        .map(|err| diagnostics::render(source, SOURCE_NAME, err))
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
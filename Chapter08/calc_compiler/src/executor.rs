@@ -1,62 +1,317 @@
 use crate::analyzer::{
-    AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement, AnalyzedTerm,
+    AnalyzedCondition, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
 };
-use crate::parser::{ExprOperator, TermOperator};
-use crate::symbol_table::SymbolTable;
+use crate::program_io::ProgramIo;
+use crate::symbol_table::{NativeFunction, SymbolTable};
+use crate::value::Value;
 
-fn evaluate_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> f64 {
+// Observes the execution of a program, statement by statement.
+pub trait ExecutionObserver {
+    // Called before executing a statement that begins at the given line,
+    // in the given function, or in the main program,
+    // so that a debugger can stop the program there.
+    fn before_statement(
+        &mut self,
+        variables: &SymbolTable,
+        function: Option<usize>,
+        line: usize,
+    ) -> Result<(), String>;
+}
+
+// The observer of the programs that are not observed.
+struct Unobserved;
+
+impl ExecutionObserver for Unobserved {
+    fn before_statement(
+        &mut self,
+        _variables: &SymbolTable,
+        _function: Option<usize>,
+        _line: usize,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// The number of nested calls after which a program is stopped,
+// before the recursion overflows the stack of the interpreter.
+// The virtual machine stops at the same depth.
+pub const MAX_CALL_DEPTH: usize = 200;
+
+// The devices of a running program, the observer of its statements,
+// the function being executed, if any, and the number of nested calls.
+struct Context<'a> {
+    io: &'a mut dyn ProgramIo,
+    observer: &'a mut dyn ExecutionObserver,
+    function: Option<usize>,
+    depth: usize,
+}
+
+impl<'a> Context<'a> {
+    fn observe(&mut self, variables: &SymbolTable, line: usize) -> Result<(), String> {
+        self.observer
+            .before_statement(variables, self.function, line)
+    }
+}
+
+fn evaluate_factor(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    factor: &AnalyzedFactor,
+) -> Result<Value, String> {
     match factor {
-        AnalyzedFactor::Literal(value) => *value,
-        AnalyzedFactor::Identifier(handle) => variables.get_value(*handle),
-        AnalyzedFactor::SubExpression(expr) => evaluate_expr(variables, expr),
+        AnalyzedFactor::Literal(value) => Ok(value.clone()),
+        AnalyzedFactor::Identifier(handle) => Ok(variables.get_value(*handle)),
+        AnalyzedFactor::Element(handle, index) => {
+            let index = evaluate_expr(variables, context, index)?;
+            variables.get_element(*handle, &index)
+        }
+        AnalyzedFactor::SubExpression(expr) => evaluate_expr(variables, context, expr),
+        AnalyzedFactor::FunctionCall(handle, arguments) => {
+            call_function(variables, context, *handle, arguments)
+        }
+        AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+            let argument_values = evaluate_arguments(variables, context, arguments)?;
+            builtin.apply(&argument_values)
+        }
+        AnalyzedFactor::NativeCall(handle, arguments) => {
+            let argument_values = evaluate_arguments(variables, context, arguments)?;
+            call_native(variables.get_native(*handle), argument_values)
+        }
+        AnalyzedFactor::Negation(factor) => evaluate_factor(variables, context, factor)?.negate(),
+        AnalyzedFactor::Power(base, exponent) => {
+            let base = evaluate_factor(variables, context, base)?;
+            base.power(evaluate_factor(variables, context, exponent)?)
+        }
     }
 }
 
-fn evaluate_term(variables: &SymbolTable, term: &AnalyzedTerm) -> f64 {
-    let mut result = evaluate_factor(variables, &term.0);
+fn evaluate_arguments(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    arguments: &[AnalyzedExpr],
+) -> Result<Vec<Value>, String> {
+    arguments
+        .iter()
+        .map(|argument| evaluate_expr(variables, context, argument))
+        .collect()
+}
+
+fn evaluate_term(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    term: &AnalyzedTerm,
+) -> Result<Value, String> {
+    let mut result = evaluate_factor(variables, context, &term.0)?;
     for factor in &term.1 {
-        match factor.0 {
-            TermOperator::Multiply => result *= evaluate_factor(variables, &factor.1),
-            TermOperator::Divide => result /= evaluate_factor(variables, &factor.1),
-        }
+        result = result
+            .apply_term_operator(factor.0, evaluate_factor(variables, context, &factor.1)?)?;
     }
-    result
+    Ok(result)
 }
 
-fn evaluate_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> f64 {
-    let mut result = evaluate_term(variables, &expr.0);
+fn evaluate_expr(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    expr: &AnalyzedExpr,
+) -> Result<Value, String> {
+    let mut result = evaluate_term(variables, context, &expr.0)?;
     for term in &expr.1 {
-        match term.0 {
-            ExprOperator::Add => result += evaluate_term(variables, &term.1),
-            ExprOperator::Subtract => result -= evaluate_term(variables, &term.1),
-        }
+        result = result.apply_expr_operator(term.0, evaluate_term(variables, context, &term.1)?)?;
     }
-    result
+    Ok(result)
+}
+
+fn evaluate_condition(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    condition: &AnalyzedCondition,
+) -> Result<bool, String> {
+    let left = evaluate_expr(variables, context, &condition.0)?;
+    let right = evaluate_expr(variables, context, &condition.2)?;
+    Ok(left.compare(condition.1, &right))
 }
 
-fn execute_statement(variables: &mut SymbolTable, statement: &AnalyzedStatement) {
+// The local variables of a function are saved before the call
+// and restored after it, so that recursive calls do not clobber them.
+fn call_function(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    handle: usize,
+    arguments: &[AnalyzedExpr],
+) -> Result<Value, String> {
+    let function = variables.get_function(handle);
+    let locals = function.locals.clone();
+    let body = function.body.clone();
+    let return_type = function.return_type;
+    let argument_values = evaluate_arguments(variables, context, arguments)?;
+    if context.depth == MAX_CALL_DEPTH {
+        return Err(format!(
+            "Error: Too many nested calls, calling function '{}'.",
+            variables.get_function(handle).name
+        ));
+    }
+    let saved_values: Vec<Value> = locals
+        .clone()
+        .map(|local| variables.get_value(local))
+        .collect();
+    for (local, value) in locals.clone().zip(argument_values) {
+        variables.set_value(local, value);
+    }
+    let caller = context.function.replace(handle);
+    context.depth += 1;
+    let result = execute_block(variables, context, &body);
+    context.depth -= 1;
+    context.function = caller;
+    for (local, value) in locals.zip(saved_values) {
+        variables.set_value(local, value);
+    }
+    Ok(result?
+        .unwrap_or_else(|| return_type.default_value())
+        .convert_to(return_type))
+}
+
+// The arguments are converted to the types of the parameters,
+// and the result must have the return type of the function.
+fn call_native(native: &NativeFunction, argument_values: Vec<Value>) -> Result<Value, String> {
+    let argument_values: Vec<Value> = argument_values
+        .into_iter()
+        .zip(&native.parameter_types)
+        .map(|(value, parameter_type)| value.convert_to(*parameter_type))
+        .collect();
+    let result = (native.function)(&argument_values)?.convert_to(native.return_type);
+    match result {
+        Value::Array(_) => Err(format!(
+            "Error: Function '{}' returned an array instead of a value of type {}.",
+            native.name, native.return_type
+        )),
+        _ if result.get_type() != native.return_type => Err(format!(
+            "Error: Function '{}' returned a value of type {} instead of {}.",
+            native.name,
+            result.get_type(),
+            native.return_type
+        )),
+        _ => Ok(result),
+    }
+}
+
+fn execute_statement(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    statement: &AnalyzedStatement,
+    line: usize,
+) -> Result<Option<Value>, String> {
     match statement {
         AnalyzedStatement::Assignment(handle, expr) => {
-            variables.set_value(*handle, evaluate_expr(variables, expr));
+            let value = evaluate_expr(variables, context, expr)?;
+            variables.set_value(*handle, value);
+        }
+        // The value is evaluated before the index.
+        AnalyzedStatement::ElementAssignment(handle, index, expr) => {
+            let value = evaluate_expr(variables, context, expr)?;
+            let index = evaluate_expr(variables, context, index)?;
+            variables.set_element(*handle, &index, value)?;
+        }
+        AnalyzedStatement::Declaration(handle) => {
+            let value = variables.default_value(*handle);
+            variables.set_value(*handle, value);
         }
-        AnalyzedStatement::Declaration(_) => {}
         AnalyzedStatement::InputOperation(handle) => {
-            let mut text = String::new();
-            eprint!("? ");
-            std::io::stdin()
-                .read_line(&mut text)
-                .expect("Cannot read line.");
-            let value = text.trim().parse::<f64>().unwrap_or(0.);
+            let text = context.io.input_line()?;
+            let value = Value::parse(&text, variables.get_type(*handle));
             variables.set_value(*handle, value);
         }
-        AnalyzedStatement::OutputOperation(expr) => {
-            println!("{}", evaluate_expr(variables, expr));
+        AnalyzedStatement::ElementInput(handle, index) => {
+            let text = context.io.input_line()?;
+            let value = Value::parse(&text, variables.get_type(*handle));
+            let index = evaluate_expr(variables, context, index)?;
+            variables.set_element(*handle, &index, value)?;
+        }
+        AnalyzedStatement::OutputOperation(exprs) => {
+            let mut text = String::new();
+            for value in evaluate_arguments(variables, context, exprs)? {
+                text += &value.to_string();
+            }
+            context.io.output_line(&text)?;
+        }
+        AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+            if evaluate_condition(variables, context, condition)? {
+                return execute_block(variables, context, then_block);
+            } else {
+                return execute_block(variables, context, else_block);
+            }
+        }
+        // Every evaluation of the condition is a new step of the loop statement.
+        AnalyzedStatement::WhileLoop(condition, body) => {
+            while evaluate_condition(variables, context, condition)? {
+                if let Some(value) = execute_block(variables, context, body)? {
+                    return Ok(Some(value));
+                }
+                context.observe(variables, line)?;
+            }
+        }
+        AnalyzedStatement::FunctionDefinition(_) => {}
+        AnalyzedStatement::Return(_, expr) => {
+            return Ok(Some(evaluate_expr(variables, context, expr)?));
         }
     }
+    Ok(None)
 }
 
-pub fn execute_program(variables: &mut SymbolTable, program: &AnalyzedProgram) {
-    for statement in program {
-        execute_statement(variables, statement);
+// Returns the value of the executed return statement, if any.
+fn execute_block(
+    variables: &mut SymbolTable,
+    context: &mut Context,
+    block: &AnalyzedProgram,
+) -> Result<Option<Value>, String> {
+    for (statement, line) in block {
+        // Function definitions are not executed, so they are not steps.
+        if !matches!(statement, AnalyzedStatement::FunctionDefinition(_)) {
+            context.observe(variables, *line)?;
+        }
+        if let Some(value) = execute_statement(variables, context, statement, *line)? {
+            return Ok(Some(value));
+        }
     }
+    Ok(None)
+}
+
+// The input and output statements use the given devices.
+pub fn execute_program(
+    variables: &mut SymbolTable,
+    program: &AnalyzedProgram,
+    io: &mut dyn ProgramIo,
+) -> Result<(), String> {
+    execute_program_with_observer(variables, program, io, &mut Unobserved)
+}
+
+// The given observer is called before every statement.
+pub fn execute_program_with_observer(
+    variables: &mut SymbolTable,
+    program: &AnalyzedProgram,
+    io: &mut dyn ProgramIo,
+    observer: &mut dyn ExecutionObserver,
+) -> Result<(), String> {
+    let mut context = Context {
+        io,
+        observer,
+        function: None,
+        depth: 0,
+    };
+    execute_block(variables, &mut context, program)?;
+    Ok(())
+}
+
+pub fn evaluate_expression(
+    variables: &mut SymbolTable,
+    expr: &AnalyzedExpr,
+    io: &mut dyn ProgramIo,
+) -> Result<Value, String> {
+    let mut context = Context {
+        io,
+        observer: &mut Unobserved,
+        function: None,
+        depth: 0,
+    };
+    evaluate_expr(variables, &mut context, expr)
 }
@@ -0,0 +1,214 @@
+use crate::analyzer::{comparison_operator_symbol, expr_operator_symbol, term_operator_symbol};
+use crate::parser::{
+    ParsedCondition, ParsedExpr, ParsedFactor, ParsedProgram, ParsedStatement, ParsedTerm,
+};
+use crate::value::Type;
+
+const INDENTATION: &str = "    ";
+
+// The precedence levels of expressions, from the loosest to the tightest.
+// A parenthesized expression is printed without its parentheses
+// if its level is not lower than the one required by its position.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const PRIMARY: u8 = 5;
+
+fn expr_level(expr: &ParsedExpr) -> u8 {
+    if !expr.1.is_empty() {
+        SUM
+    } else if !(expr.0).1.is_empty() {
+        PRODUCT
+    } else {
+        factor_level(&(expr.0).0)
+    }
+}
+
+fn factor_level(factor: &ParsedFactor) -> u8 {
+    match factor {
+        ParsedFactor::SubExpression(expr) => expr_level(expr),
+        ParsedFactor::Negation(_) => NEGATION,
+        ParsedFactor::Power(_, _) => POWER,
+        _ => PRIMARY,
+    }
+}
+
+// Floating-point literals keep a fractional part or an exponent,
+// so that they are not read back as integers.
+fn format_factor(factor: &ParsedFactor, required_level: u8) -> String {
+    match factor {
+        ParsedFactor::Literal(value) => format!("{:?}", value),
+        ParsedFactor::IntegerLiteral(value) => value.to_string(),
+        ParsedFactor::BooleanLiteral(value) => value.to_string(),
+        ParsedFactor::StringLiteral(text) => format!("\"{}\"", text),
+        ParsedFactor::Identifier(name) => name.to_string(),
+        ParsedFactor::Element(name, index) => format!("{}[{}]", name, format_expr(index)),
+        ParsedFactor::SubExpression(expr) => {
+            if expr_level(expr) < required_level {
+                format!("({})", format_expr(expr))
+            } else if expr.1.is_empty() && (expr.0).1.is_empty() {
+                format_factor(&(expr.0).0, required_level)
+            } else {
+                format_expr(expr)
+            }
+        }
+        ParsedFactor::FunctionCall(name, arguments) => format!(
+            "{}({})",
+            name,
+            arguments
+                .iter()
+                .map(format_expr)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        ParsedFactor::Negation(factor) => format!("-{}", format_factor(factor, NEGATION)),
+        ParsedFactor::Power(base, exponent) => format!(
+            "{} ^ {}",
+            format_factor(base, PRIMARY),
+            format_factor(exponent, NEGATION)
+        ),
+    }
+}
+
+// Operations of the same level are evaluated from left to right,
+// so only the first operand of a sequence can drop its parentheses.
+fn format_term(term: &ParsedTerm, first_level: u8) -> String {
+    let first_level = if term.1.is_empty() {
+        first_level
+    } else {
+        PRODUCT
+    };
+    let mut result = format_factor(&term.0, first_level);
+    for (operator, factor) in &term.1 {
+        result += &format!(
+            " {} {}",
+            term_operator_symbol(*operator),
+            format_factor(factor, NEGATION)
+        );
+    }
+    result
+}
+
+pub fn format_expr(expr: &ParsedExpr) -> String {
+    let mut result = format_term(&expr.0, SUM);
+    for (operator, term) in &expr.1 {
+        result += &format!(
+            " {} {}",
+            expr_operator_symbol(*operator),
+            format_term(term, PRODUCT)
+        );
+    }
+    result
+}
+
+fn format_condition(condition: &ParsedCondition) -> String {
+    match &condition.1 {
+        Some((operator, right)) => format!(
+            "{} {} {}",
+            format_expr(&condition.0),
+            comparison_operator_symbol(*operator),
+            format_expr(right)
+        ),
+        None => format_expr(&condition.0),
+    }
+}
+
+fn format_type_annotation(annotation: Option<Type>) -> String {
+    match annotation {
+        Some(annotated_type) => format!(": {}", annotated_type),
+        None => String::new(),
+    }
+}
+
+fn format_block(block: &ParsedProgram, indentation: usize) -> String {
+    if block.is_empty() {
+        return "{}".to_string();
+    }
+    format!(
+        "{{\n{}{}}}",
+        format_statements(block, indentation + 1),
+        INDENTATION.repeat(indentation)
+    )
+}
+
+// An else branch made only of an if statement is printed as "else if".
+fn format_statement(statement: &ParsedStatement, indentation: usize) -> String {
+    match statement {
+        ParsedStatement::Declaration(name, annotation) => {
+            format!("@{}{}", name, format_type_annotation(*annotation))
+        }
+        ParsedStatement::ArrayDeclaration(name, size, annotation) => {
+            format!("@{}[{}]{}", name, size, format_type_annotation(*annotation))
+        }
+        ParsedStatement::InputOperation(name) => format!(">{}", name),
+        ParsedStatement::ElementInput(name, index) => format!(">{}[{}]", name, format_expr(index)),
+        ParsedStatement::OutputOperation(exprs) => format!(
+            "<{}",
+            exprs
+                .iter()
+                .map(format_expr)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        ParsedStatement::Assignment(name, expr) => format!("{} := {}", name, format_expr(expr)),
+        ParsedStatement::ElementAssignment(name, index, expr) => {
+            format!("{}[{}] := {}", name, format_expr(index), format_expr(expr))
+        }
+        ParsedStatement::IfElse(condition, then_block, else_block) => {
+            let mut result = format!(
+                "if {} {}",
+                format_condition(condition),
+                format_block(then_block, indentation)
+            );
+            match else_block.as_slice() {
+                [] => {}
+                [(else_if @ ParsedStatement::IfElse(..), _)] => {
+                    result += " else ";
+                    result += &format_statement(else_if, indentation);
+                }
+                _ => {
+                    result += " else ";
+                    result += &format_block(else_block, indentation);
+                }
+            }
+            result
+        }
+        ParsedStatement::WhileLoop(condition, body) => format!(
+            "while {} {}",
+            format_condition(condition),
+            format_block(body, indentation)
+        ),
+        ParsedStatement::FunctionDefinition(name, parameters, return_type, body) => format!(
+            "fn {}({}){} {}",
+            name,
+            parameters
+                .iter()
+                .map(|(parameter, annotation)| parameter.to_string()
+                    + &format_type_annotation(*annotation))
+                .collect::<Vec<String>>()
+                .join(", "),
+            match return_type {
+                Some(return_type) => format!(" -> {}", return_type),
+                None => String::new(),
+            },
+            format_block(body, indentation)
+        ),
+        ParsedStatement::Return(_, expr) => format!("return {}", format_expr(expr)),
+    }
+}
+
+fn format_statements(block: &ParsedProgram, indentation: usize) -> String {
+    let mut result = String::new();
+    for (statement, _) in block {
+        result += &INDENTATION.repeat(indentation);
+        result += &format_statement(statement, indentation);
+        result += "\n";
+    }
+    result
+}
+
+// Prints a program with one statement per line, and with the blocks indented.
+pub fn format_program(program: &ParsedProgram) -> String {
+    format_statements(program, 0)
+}
@@ -0,0 +1,24 @@
+// The calc language: its parser, analyzer, interpreter and compilers,
+// and an engine to evaluate calc formulas in Rust programs.
+pub mod analyzer;
+pub mod byte_machine;
+pub mod bytecode;
+pub mod c_backend;
+pub mod compiler;
+pub mod debugger;
+pub mod diagnostics;
+pub mod engine;
+pub mod executor;
+pub mod formatter;
+pub mod optimizer;
+pub mod parser;
+pub mod program_io;
+pub mod repl;
+pub mod rust_backend;
+pub mod symbol_table;
+pub mod value;
+pub mod vm;
+pub mod wat_backend;
+
+pub use engine::{Engine, Formula};
+pub use value::{Type, Value};
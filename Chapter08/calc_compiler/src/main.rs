@@ -1,125 +1,656 @@
-mod analyzer;
-mod compiler;
-mod executor;
-mod parser;
-mod symbol_table;
+use calc_compiler::{
+    analyzer, byte_machine, bytecode, compiler, debugger, diagnostics, executor, formatter,
+    optimizer, parser, program_io, repl, symbol_table, vm,
+};
+use std::io::{IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+const CALC_SUFFIX: &str = ".calc";
+const BYTECODE_SUFFIX: &str = ".calcb";
+const BYTE_MACHINE_SUFFIX: &str = ".bin";
 
 fn main() {
-    let mut args = std::env::args();
-    let current_program_path = args.next().unwrap();
-    let source_path = args.next();
-    if source_path.is_none() {
-        run_interpreter();
-    } else {
-        process_file(&current_program_path, &source_path.unwrap());
+    let args: Vec<String> = std::env::args().collect();
+    let current_program_path = &args[0];
+    match args.len() {
+        1 => run_interpreter(),
+        _ if args[1] == "--batch" && args.len() >= 3 => {
+            run_in_batch(current_program_path, &args[2], &args[3..])
+        }
+        _ if args[1] == "--target" && args.len() == 4 => {
+            compile_to_target(current_program_path, &args[2], &args[3])
+        }
+        2 => compile_to_target(current_program_path, "rust", &args[1]),
+        3 => match args[1].as_str() {
+            "--run" => run_with_interpreter(current_program_path, &args[2]),
+            "--bytecode" => compile_to_bytecode(current_program_path, &args[2]),
+            "--vm" => run_with_vm(current_program_path, &args[2]),
+            "--byte-machine" => compile_to_byte_machine(current_program_path, &args[2]),
+            "--emulate" => run_with_emulator(current_program_path, &args[2]),
+            "--debug" => run_with_debugger(current_program_path, &args[2]),
+            "--fmt" => format_file(current_program_path, &args[2]),
+            "--check" => check_backends(current_program_path, &args[2]),
+            "--dump-optimization" => dump_optimization(current_program_path, &args[2]),
+            option => {
+                eprintln!("{}: Invalid option '{}'", current_program_path, option);
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!(
+                "Usage: {} [--run|--bytecode|--vm|--byte-machine|--emulate|--debug|--fmt|--check|--dump-optimization] [file{}]",
+                current_program_path, CALC_SUFFIX
+            );
+            eprintln!(
+                "       {} --target {} file{}",
+                current_program_path,
+                compiler::TARGETS.join("|"),
+                CALC_SUFFIX
+            );
+            eprintln!(
+                "       {} --batch file{} [--inputs file | input...]",
+                current_program_path, CALC_SUFFIX
+            );
+            std::process::exit(1);
+        }
     }
 }
 
-fn process_file(current_program_path: &str, source_path: &str) {
-    const CALC_SUFFIX: &str = ".calc";
-    if !source_path.ends_with(CALC_SUFFIX) {
+fn strip_suffix(current_program_path: &str, source_path: &str, suffix: &str) -> Option<String> {
+    if source_path.ends_with(suffix) {
+        Some(source_path[0..source_path.len() - suffix.len()].to_string())
+    } else {
         eprintln!(
             "{}: Invalid argument '{}': It must end with {}",
-            current_program_path, source_path, CALC_SUFFIX
+            current_program_path, source_path, suffix
         );
-        return;
+        None
     }
-    let target_path = source_path[0..source_path.len() - CALC_SUFFIX.len()].to_string() + ".rs";
-    let source_code = std::fs::read_to_string(&source_path);
-    if source_code.is_err() {
-        eprintln!(
-            "Failed to read from file {}: ({})",
-            source_path,
-            source_code.unwrap_err()
-        );
-        return;
-    }
-    let source_code = source_code.unwrap();
-
-    let parsed_program;
-    match parser::parse_program(&source_code) {
-        Ok((rest, syntax_tree)) => {
-            let trimmed_rest = rest.trim();
-            if trimmed_rest.len() > 0 {
-                eprintln!(
-                    "Invalid remaining code in '{}': {}",
-                    source_path, trimmed_rest
-                );
-                return;
-            }
-            parsed_program = syntax_tree;
+}
+
+// Loads a source file and optimizes it,
+// reporting the variables that may be read before being assigned.
+fn load_program(
+    source_path: &str,
+) -> Option<(symbol_table::SymbolTable, analyzer::AnalyzedProgram)> {
+    let (mut variables, analyzed_program) = analyze_file(source_path)?;
+    for warning in optimizer::check_assignments(&variables, &analyzed_program) {
+        eprintln!("Warning in '{}': {}", source_path, warning);
+    }
+    let optimized_program = optimizer::optimize_program(&mut variables, &analyzed_program);
+    Some((variables, optimized_program))
+}
+
+// Parses the whole source code of a file, reporting where the syntax error is, if any.
+fn parse_source<'a>(source_path: &str, source_code: &'a str) -> Option<parser::ParsedProgram<'a>> {
+    match parser::parse_whole_program(source_code) {
+        Ok(parsed_program) => Some(parsed_program),
+        Err(err) => {
+            eprint!("{}", diagnostics::render(source_code, source_path, &err));
+            None
         }
+    }
+}
+
+fn read_source(source_path: &str) -> Option<String> {
+    match std::fs::read_to_string(source_path) {
+        Ok(source_code) => Some(source_code),
         Err(err) => {
-            eprintln!("Invalid code in '{}': {:?}", source_path, err);
-            return;
+            eprintln!("Failed to read from file {}: ({})", source_path, err);
+            None
         }
     }
+}
+
+// Reads, parses and analyzes a source file, reporting any error found.
+fn analyze_file(
+    source_path: &str,
+) -> Option<(symbol_table::SymbolTable, analyzer::AnalyzedProgram)> {
+    analyze_source(source_path, &read_source(source_path)?)
+}
+
+// Parses and analyzes the source code of a file, reporting any error found.
+fn analyze_source(
+    source_path: &str,
+    source_code: &str,
+) -> Option<(symbol_table::SymbolTable, analyzer::AnalyzedProgram)> {
+    let parsed_program = parse_source(source_path, source_code)?;
 
-    let analyzed_program;
     let mut variables = symbol_table::SymbolTable::new();
     match analyzer::analyze_program(&mut variables, &parsed_program) {
-        Ok(analyzed_tree) => {
-            analyzed_program = analyzed_tree;
+        Ok(analyzed_program) => Some((variables, analyzed_program)),
+        Err(errors) => {
+            for err in &errors {
+                eprint!("{}", diagnostics::render(source_code, source_path, err));
+            }
+            eprintln!(
+                "Invalid code in '{}': {} error{} found.",
+                source_path,
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" }
+            );
+            None
+        }
+    }
+}
+
+// Prints the analyzed tree of the program and of its functions,
+// before and after the optimization.
+fn dump_optimization(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let (mut variables, analyzed_program) = match analyze_file(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    for warning in optimizer::check_assignments(&variables, &analyzed_program) {
+        eprintln!("Warning in '{}': {}", source_path, warning);
+    }
+    println!("Before optimization:");
+    print!("{}", optimizer::dump_program(&variables, &analyzed_program));
+    let optimized_program = optimizer::optimize_program(&mut variables, &analyzed_program);
+    println!("After optimization:");
+    print!(
+        "{}",
+        optimizer::dump_program(&variables, &optimized_program)
+    );
+}
+
+// Translates a source file into the language of the given target,
+// writing a file named after the source, with the suffix of the target.
+fn compile_to_target(current_program_path: &str, target: &str, source_path: &str) {
+    let backend = match compiler::find_backend(target) {
+        Some(backend) => backend,
+        None => {
+            eprintln!(
+                "{}: Invalid target '{}': It must be one of {}",
+                current_program_path,
+                target,
+                compiler::TARGETS.join(", ")
+            );
+            std::process::exit(1);
+        }
+    };
+    let target_path = match strip_suffix(current_program_path, source_path, CALC_SUFFIX) {
+        Some(stem) => stem + backend.file_suffix(),
+        None => std::process::exit(1),
+    };
+    let (variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let code = match backend.translate_program(&variables, &analyzed_program) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Cannot compile '{}': {}", source_path, err);
+            std::process::exit(1);
         }
+    };
+    match std::fs::write(&target_path, code) {
+        Ok(_) => eprintln!("Compiled {} to {}.", source_path, target_path),
         Err(err) => {
-            eprintln!("Invalid code in '{}': {}", source_path, err);
-            return;
+            eprintln!("Failed to write to file {}: ({})", target_path, err);
+            std::process::exit(1);
         }
     }
+}
 
-    match std::fs::write(
-        &target_path,
-        compiler::translate_to_rust_program(&variables, &analyzed_program),
+fn run_with_interpreter(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let (mut variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    if let Err(err) = executor::execute_program(
+        &mut variables,
+        &analyzed_program,
+        &mut program_io::ConsoleIo,
     ) {
+        eprintln!("Runtime error in '{}': {}", source_path, err);
+        std::process::exit(1);
+    }
+}
+
+// Rewrites a source file with the standard layout.
+// The file is left unchanged if the formatted code would not format to itself,
+// as that would mean that the printed program is not the parsed one.
+fn format_file(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let source_code = match read_source(source_path) {
+        Some(source_code) => source_code,
+        None => std::process::exit(1),
+    };
+    let parsed_program = match parse_source(source_path, &source_code) {
+        Some(parsed_program) => parsed_program,
+        None => std::process::exit(1),
+    };
+    let formatted_code = formatter::format_program(&parsed_program);
+    match parser::parse_program(&formatted_code) {
+        Ok((rest, reparsed_program))
+            if rest.trim().is_empty()
+                && formatter::format_program(&reparsed_program) == formatted_code => {}
+        _ => {
+            eprintln!(
+                "{}: Cannot format '{}': the formatted code does not parse back to the same program.",
+                current_program_path, source_path
+            );
+            std::process::exit(1);
+        }
+    }
+    if formatted_code != source_code {
+        if let Err(err) = std::fs::write(source_path, formatted_code) {
+            eprintln!("Failed to write to file {}: ({})", source_path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Runs the program under the debugger, reading its commands and the program inputs
+// from the terminal, or from a script piped into stdin.
+// The program is not optimized, so that every statement of the source is a step.
+fn run_with_debugger(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let source_code = match read_source(source_path) {
+        Some(source_code) => source_code,
+        None => std::process::exit(1),
+    };
+    let (mut variables, analyzed_program) = match analyze_source(source_path, &source_code) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let mut reader: Box<dyn repl::LineReader> = if std::io::stdin().is_terminal() {
+        Box::new(repl::EditorReader::new())
+    } else {
+        Box::new(repl::ScriptReader::new(std::io::stdin().lock()))
+    };
+    let mut debugger = debugger::Debugger::new(&source_code, &analyzed_program, reader.as_mut());
+    let mut io = debugger.io();
+    if let Err(err) = executor::execute_program_with_observer(
+        &mut variables,
+        &analyzed_program,
+        &mut io,
+        &mut debugger,
+    ) {
+        eprintln!("Runtime error in '{}': {}", source_path, err);
+        std::process::exit(1);
+    }
+}
+
+// Runs the program with the given inputs, or with the lines of the given inputs file,
+// without prompting, and exits with an error status if it fails.
+fn run_in_batch(current_program_path: &str, source_path: &str, arguments: &[String]) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let inputs: Vec<String> = match arguments {
+        [option, inputs_path] if option == "--inputs" => {
+            match std::fs::read_to_string(inputs_path) {
+                Ok(text) => text.lines().map(String::from).collect(),
+                Err(err) => {
+                    eprintln!("Failed to read from file {}: ({})", inputs_path, err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        inputs => inputs.to_vec(),
+    };
+    let (mut variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let stdout = std::io::stdout();
+    let mut io = program_io::BatchIo::new(inputs, stdout.lock());
+    if let Err(err) = executor::execute_program(&mut variables, &analyzed_program, &mut io) {
+        eprintln!("Runtime error in '{}': {}", source_path, err);
+        std::process::exit(1);
+    }
+}
+
+fn compile_to_bytecode(current_program_path: &str, source_path: &str) {
+    let target_path = match strip_suffix(current_program_path, source_path, CALC_SUFFIX) {
+        Some(stem) => stem + BYTECODE_SUFFIX,
+        None => std::process::exit(1),
+    };
+    let (variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let bytecode_program = match bytecode::compile_program(&variables, &analyzed_program) {
+        Ok(bytecode_program) => bytecode_program,
+        Err(err) => {
+            eprintln!("Cannot compile '{}': {}", source_path, err);
+            std::process::exit(1);
+        }
+    };
+    match std::fs::write(&target_path, bytecode::serialize_program(&bytecode_program)) {
         Ok(_) => eprintln!("Compiled {} to {}.", source_path, target_path),
-        Err(err) => eprintln!("Failed to write to file {}: ({})", target_path, err),
+        Err(err) => {
+            eprintln!("Failed to write to file {}: ({})", target_path, err);
+            std::process::exit(1);
+        }
     }
 }
 
-fn run_interpreter() {
-    eprintln!("* Calc interactive interpreter *");
-    let mut variables = symbol_table::SymbolTable::new();
-    loop {
-        let command = input_command();
-        if command.len() == 0 {
-            break;
-        }
-        match command.trim() {
-            "q" => break,
-            "c" => {
-                variables = symbol_table::SymbolTable::new();
-                eprintln!("Cleared variables.");
+// Runs either a source file, compiling it in memory, or a bytecode file.
+fn run_with_vm(current_program_path: &str, path: &str) {
+    let bytecode_program = if path.ends_with(BYTECODE_SUFFIX) {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("Failed to read from file {}: ({})", path, err);
+                std::process::exit(1);
             }
-            "v" => {
-                eprintln!("Variables:");
-                for v in variables.iter() {
-                    eprintln!("  {}: {}", v.0, v.1);
-                }
+        };
+        match bytecode::deserialize_program(&bytes) {
+            Ok(bytecode_program) => bytecode_program,
+            Err(err) => {
+                eprintln!("Invalid bytecode in '{}': {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        if strip_suffix(current_program_path, path, CALC_SUFFIX).is_none() {
+            std::process::exit(1);
+        }
+        let (variables, analyzed_program) = match load_program(path) {
+            Some(program) => program,
+            None => std::process::exit(1),
+        };
+        match bytecode::compile_program(&variables, &analyzed_program) {
+            Ok(bytecode_program) => bytecode_program,
+            Err(err) => {
+                eprintln!("Cannot compile '{}': {}", path, err);
+                std::process::exit(1);
             }
-            trimmed_command => match parser::parse_program(&trimmed_command) {
-                Ok((rest, parsed_program)) => {
-                    if rest.len() > 0 {
-                        eprintln!("Unparsed input: `{}`.", rest)
-                    } else {
-                        match analyzer::analyze_program(&mut variables, &parsed_program) {
-                            Ok(analyzed_program) => {
-                                executor::execute_program(&mut variables, &analyzed_program)
-                            }
-                            Err(err) => eprintln!("Error: {}", err),
-                        }
-                    }
+        }
+    };
+    if let Err(err) = vm::execute_bytecode(&bytecode_program, &mut program_io::ConsoleIo) {
+        eprintln!("Runtime error in '{}': {}", path, err);
+        std::process::exit(1);
+    }
+}
+
+fn compile_to_byte_machine(current_program_path: &str, source_path: &str) {
+    let target_path = match strip_suffix(current_program_path, source_path, CALC_SUFFIX) {
+        Some(stem) => stem + BYTE_MACHINE_SUFFIX,
+        None => std::process::exit(1),
+    };
+    let (variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let image = match byte_machine::translate_to_byte_machine(&variables, &analyzed_program) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("Cannot compile '{}': {}", source_path, err);
+            std::process::exit(1);
+        }
+    };
+    match std::fs::write(&target_path, image) {
+        Ok(_) => eprintln!("Compiled {} to {}.", source_path, target_path),
+        Err(err) => {
+            eprintln!("Failed to write to file {}: ({})", target_path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Compiles a source file for the byte machine, and runs it on its emulator.
+fn run_with_emulator(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    let (variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let image = match byte_machine::translate_to_byte_machine(&variables, &analyzed_program) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("Cannot compile '{}': {}", source_path, err);
+            std::process::exit(1);
+        }
+    };
+    if let Err(fault) = nom_byte_machine::emulator::execute_program(&image) {
+        eprintln!("Runtime error in '{}': {}", source_path, fault);
+        std::process::exit(1);
+    }
+}
+
+fn run_interpreter_backend(
+    variables: &mut symbol_table::SymbolTable,
+    analyzed_program: &analyzer::AnalyzedProgram,
+    input: &str,
+) -> Result<String, String> {
+    let mut output = Vec::<u8>::new();
+    let mut io = program_io::StreamIo::new(input.as_bytes(), &mut output);
+    executor::execute_program(variables, analyzed_program, &mut io)?;
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
+
+// The input is written by another thread while the output is read,
+// and the backends may exit without reading all of it.
+fn run_backend(command: &mut Command, input: &str) -> Result<String, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    if let Err(err) = writer.join().unwrap() {
+        if err.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(err.to_string());
+        }
+    }
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!("exited with {}", output.status))
+    }
+}
+
+// Translates the program for the given target, builds it with the given compiler,
+// followed by the given libraries, and runs it.
+fn run_native_backend(
+    variables: &symbol_table::SymbolTable,
+    analyzed_program: &analyzer::AnalyzedProgram,
+    target: &str,
+    compiler_command: &str,
+    libraries: &[&str],
+    input: &str,
+) -> Result<String, String> {
+    let backend = compiler::find_backend(target).unwrap();
+    let base_path =
+        std::env::temp_dir().join(format!("calc_check_{}_{}", target, std::process::id()));
+    let source_path = base_path.with_extension(backend.file_suffix().trim_start_matches('.'));
+    std::fs::write(
+        &source_path,
+        backend.translate_program(variables, analyzed_program)?,
+    )
+    .map_err(|err| err.to_string())?;
+    let compilation = Command::new(compiler_command)
+        .arg("-o")
+        .arg(&base_path)
+        .arg(&source_path)
+        .args(libraries)
+        .stderr(Stdio::null())
+        .status();
+    let _ = std::fs::remove_file(&source_path);
+    match compilation {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(format!("{} exited with {}", compiler_command, status)),
+        Err(err) => return Err(format!("cannot run {} ({})", compiler_command, err)),
+    }
+    let output = run_backend(&mut Command::new(&base_path), input);
+    let _ = std::fs::remove_file(&base_path);
+    output
+}
+
+// The targets built into executables by --check, with their compiler and libraries.
+const NATIVE_TARGETS: [(&str, &str, &[&str]); 2] = [("rust", "rustc", &[]), ("c", "cc", &["-lm"])];
+
+// The WebAssembly modules are run by node, with the host functions of this script.
+const WASM_HOST: &str = include_str!("wasm_host.js");
+
+// Assembles the WebAssembly text, and runs the module with the host script.
+fn run_wat_backend(code: &str, input: &str) -> Result<String, String> {
+    let binary = wat::parse_str(code).map_err(|err| err.to_string())?;
+    let base_path = std::env::temp_dir().join(format!("calc_check_wat_{}", std::process::id()));
+    let module_path = base_path.with_extension("wasm");
+    let host_path = base_path.with_extension("js");
+    let output = std::fs::write(&module_path, binary)
+        .and_then(|_| std::fs::write(&host_path, WASM_HOST))
+        .map_err(|err| err.to_string())
+        .and_then(|_| {
+            run_backend(
+                Command::new("node").arg(&host_path).arg(&module_path),
+                input,
+            )
+        });
+    let _ = std::fs::remove_file(&module_path);
+    let _ = std::fs::remove_file(&host_path);
+    output
+}
+
+// Whether the given tool can be run.
+fn is_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+// Runs the program on every backend that can run it, with the same standard input,
+// and checks that all of them print the same output.
+fn check_backends(current_program_path: &str, source_path: &str) {
+    if strip_suffix(current_program_path, source_path, CALC_SUFFIX).is_none() {
+        std::process::exit(1);
+    }
+    // The interpreter runs the program as written, to check the optimizer too.
+    let (mut original_variables, original_program) = match analyze_file(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let (variables, analyzed_program) = match load_program(source_path) {
+        Some(program) => program,
+        None => std::process::exit(1),
+    };
+    let mut input = String::new();
+    if let Err(err) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("Failed to read the input: ({})", err);
+        std::process::exit(1);
+    }
+    let current_exe = match std::env::current_exe() {
+        Ok(current_exe) => current_exe,
+        Err(err) => {
+            eprintln!("Cannot find the current executable: ({})", err);
+            std::process::exit(1);
+        }
+    };
+    let mut outputs = vec![
+        (
+            "interpreter",
+            run_interpreter_backend(&mut original_variables, &original_program, &input),
+        ),
+        (
+            "bytecode",
+            run_backend(
+                Command::new(&current_exe).arg("--vm").arg(source_path),
+                &input,
+            ),
+        ),
+    ];
+    // The backends that cannot run the program are reported, but they are not failures.
+    let mut skipped = Vec::<(&str, String)>::new();
+    for (target, compiler_command, libraries) in NATIVE_TARGETS {
+        if is_available(compiler_command) {
+            outputs.push((
+                target,
+                run_native_backend(
+                    &variables,
+                    &analyzed_program,
+                    target,
+                    compiler_command,
+                    libraries,
+                    &input,
+                ),
+            ));
+        } else {
+            skipped.push((target, format!("{} is not available", compiler_command)));
+        }
+    }
+    if !is_available("node") {
+        skipped.push(("wat", "node is not available".to_string()));
+    } else {
+        match compiler::find_backend("wat")
+            .unwrap()
+            .translate_program(&variables, &analyzed_program)
+        {
+            Ok(code) => outputs.push(("wat", run_wat_backend(&code, &input))),
+            Err(err) => skipped.push(("wat", err)),
+        }
+    }
+    // Only the programs using just integers and booleans can run on the byte machine.
+    match byte_machine::translate_to_byte_machine(&variables, &analyzed_program) {
+        Ok(_) => outputs.push((
+            "byte machine",
+            run_backend(
+                Command::new(&current_exe).arg("--emulate").arg(source_path),
+                &input,
+            ),
+        )),
+        Err(err) => skipped.push(("byte machine", err)),
+    }
+    let mut expected_output: Option<&String> = None;
+    let mut all_match = true;
+    for (backend, output) in &outputs {
+        match output {
+            Ok(output) => match expected_output {
+                Some(expected_output) if expected_output != output => {
+                    eprintln!("{}: output differs:\n{}", backend, output);
+                    all_match = false;
+                }
+                Some(_) => eprintln!("{}: ok", backend),
+                None => {
+                    eprintln!("{}: ok", backend);
+                    expected_output = Some(output);
                 }
-                Err(err) => eprintln!("Error: {:?}", err),
             },
+            Err(err) => {
+                eprintln!("{}: failed: {}", backend, err);
+                all_match = false;
+            }
         }
     }
+    for (backend, reason) in &skipped {
+        eprintln!("{}: skipped: {}", backend, reason);
+    }
+    if let Some(expected_output) = expected_output {
+        print!("{}", expected_output);
+    }
+    if !all_match {
+        std::process::exit(1);
+    }
 }
 
-fn input_command() -> String {
-    let mut text = String::new();
-    eprint!("> ");
-    std::io::stdin()
-        .read_line(&mut text)
-        .expect("Cannot read line.");
-    text
+// Scripts piped into stdin are read without line editing.
+fn run_interpreter() {
+    if std::io::stdin().is_terminal() {
+        repl::run(&mut repl::EditorReader::new());
+    } else {
+        repl::run(&mut repl::ScriptReader::new(std::io::stdin().lock()));
+    }
 }
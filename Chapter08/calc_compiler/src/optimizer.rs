@@ -0,0 +1,726 @@
+use crate::analyzer::{
+    comparison_operator_symbol, expr_operator_symbol, factor_type, term_operator_symbol,
+    AnalyzedCondition, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
+};
+use crate::parser::{ExprOperator, TermOperator};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+// Optimizes the main program and the bodies of all the functions.
+pub fn optimize_program(
+    variables: &mut SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> AnalyzedProgram {
+    let mut program = fold_block(variables, analyzed_program);
+    let mut bodies: Vec<AnalyzedProgram> = (0..variables.function_count())
+        .map(|handle| fold_block(variables, &variables.get_function(handle).body))
+        .collect();
+
+    // Removing a statement can make other statements useless,
+    // so the removal is repeated until nothing changes.
+    loop {
+        let mut usage = Usage::new(variables.symbol_count());
+        usage.scan_block(&program);
+        for body in &bodies {
+            usage.scan_block(body);
+        }
+        let mut changed = remove_useless_statements(&mut program, &usage);
+        for body in &mut bodies {
+            changed |= remove_useless_statements(body, &usage);
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for (handle, body) in bodies.into_iter().enumerate() {
+        variables.replace_function_body(handle, body);
+    }
+    program
+}
+
+fn literal(factor: &AnalyzedFactor) -> Option<&Value> {
+    match factor {
+        AnalyzedFactor::Literal(value) => Some(value),
+        _ => None,
+    }
+}
+
+// The infinite results of divisions by zero cannot be written as literals.
+fn is_finite(value: &Value) -> bool {
+    match value {
+        Value::Float(value) => value.is_finite(),
+        _ => true,
+    }
+}
+
+fn is_one(factor: &AnalyzedFactor) -> bool {
+    matches!(
+        literal(factor),
+        Some(Value::Int(1)) | Some(Value::Float(1.))
+    )
+}
+
+fn is_zero(term: &AnalyzedTerm) -> bool {
+    term.1.is_empty()
+        && matches!(
+            literal(&term.0),
+            Some(Value::Int(0)) | Some(Value::Float(0.))
+        )
+}
+
+// Returns the only factor of an expression, if it has just one.
+fn single_factor(expr: &AnalyzedExpr) -> Option<&AnalyzedFactor> {
+    if expr.1.is_empty() && (expr.0).1.is_empty() {
+        Some(&(expr.0).0)
+    } else {
+        None
+    }
+}
+
+// Operations that would fail at runtime are not folded,
+// so that they still fail when the program is run.
+fn fold_factor(variables: &SymbolTable, analyzed_factor: &AnalyzedFactor) -> AnalyzedFactor {
+    match analyzed_factor {
+        AnalyzedFactor::Literal(_) | AnalyzedFactor::Identifier(_) => analyzed_factor.clone(),
+        AnalyzedFactor::Element(handle, index) => {
+            AnalyzedFactor::Element(*handle, Box::new(fold_expr(variables, index)))
+        }
+        AnalyzedFactor::SubExpression(expr) => {
+            let expr = fold_expr(variables, expr);
+            match single_factor(&expr) {
+                Some(factor) => factor.clone(),
+                None => AnalyzedFactor::SubExpression(Box::new(expr)),
+            }
+        }
+        AnalyzedFactor::FunctionCall(handle, arguments) => AnalyzedFactor::FunctionCall(
+            *handle,
+            arguments
+                .iter()
+                .map(|argument| fold_expr(variables, argument))
+                .collect(),
+        ),
+        // Native functions may have side effects, so they are never evaluated in advance.
+        AnalyzedFactor::NativeCall(handle, arguments) => AnalyzedFactor::NativeCall(
+            *handle,
+            arguments
+                .iter()
+                .map(|argument| fold_expr(variables, argument))
+                .collect(),
+        ),
+        AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+            let arguments: Vec<AnalyzedExpr> = arguments
+                .iter()
+                .map(|argument| fold_expr(variables, argument))
+                .collect();
+            let values: Option<Vec<Value>> = arguments
+                .iter()
+                .map(|argument| single_factor(argument).and_then(literal).cloned())
+                .collect();
+            match values.map(|values| builtin.apply(&values)) {
+                Some(Ok(value)) => AnalyzedFactor::Literal(value),
+                _ => AnalyzedFactor::BuiltinCall(*builtin, arguments),
+            }
+        }
+        AnalyzedFactor::Negation(factor) => {
+            let factor = fold_factor(variables, factor);
+            match literal(&factor).map(|value| value.clone().negate()) {
+                Some(Ok(value)) => AnalyzedFactor::Literal(value),
+                _ => AnalyzedFactor::Negation(Box::new(factor)),
+            }
+        }
+        AnalyzedFactor::Power(base, exponent) => {
+            let base = fold_factor(variables, base);
+            let exponent = fold_factor(variables, exponent);
+            match (literal(&base), literal(&exponent)) {
+                (Some(base_value), Some(exponent_value)) => {
+                    match base_value.clone().power(exponent_value.clone()) {
+                        Ok(value) => AnalyzedFactor::Literal(value),
+                        Err(_) => AnalyzedFactor::Power(Box::new(base), Box::new(exponent)),
+                    }
+                }
+                _ => AnalyzedFactor::Power(Box::new(base), Box::new(exponent)),
+            }
+        }
+    }
+}
+
+// As the operations are applied from left to right, only the literals
+// at the start of a term can be folded together.
+// Multiplying or dividing by one is removed if it does not change the type.
+fn fold_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm) -> AnalyzedTerm {
+    let mut first = fold_factor(variables, &analyzed_term.0);
+    let mut result_type = factor_type(variables, &first);
+    let mut others = Vec::<(TermOperator, AnalyzedFactor)>::new();
+    for (operator, factor) in &analyzed_term.1 {
+        let factor = fold_factor(variables, factor);
+        let factor_type = factor_type(variables, &factor);
+        let new_type = result_type.term_result(*operator, factor_type);
+        if others.is_empty() {
+            if let (Some(left), Some(right)) = (literal(&first), literal(&factor)) {
+                let result = left.clone().apply_term_operator(*operator, right.clone());
+                if let Some(value) = result.ok().filter(is_finite) {
+                    first = AnalyzedFactor::Literal(value);
+                    result_type = new_type;
+                    continue;
+                }
+            }
+            if *operator == TermOperator::Multiply && is_one(&first) && new_type == factor_type {
+                first = factor;
+                result_type = new_type;
+                continue;
+            }
+        }
+        if *operator != TermOperator::Remainder && is_one(&factor) && new_type == result_type {
+            continue;
+        }
+        others.push((*operator, factor));
+        result_type = new_type;
+    }
+    (first, others, analyzed_term.2)
+}
+
+// Adding zero is removed only for integers,
+// because adding zero to a negative zero gives a positive zero.
+fn fold_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr) -> AnalyzedExpr {
+    let mut first = fold_term(variables, &analyzed_expr.0);
+    let mut result_type = first.2;
+    let mut others = Vec::<(ExprOperator, AnalyzedTerm)>::new();
+    for (operator, term) in &analyzed_expr.1 {
+        let term = fold_term(variables, term);
+        let new_type = if result_type.is_numeric() {
+            result_type.numeric_result(term.2)
+        } else {
+            result_type
+        };
+        if others.is_empty() && first.1.is_empty() && term.1.is_empty() {
+            if let (Some(left), Some(right)) = (literal(&first.0), literal(&term.0)) {
+                if let Ok(value) = left.clone().apply_expr_operator(*operator, right.clone()) {
+                    first = (AnalyzedFactor::Literal(value), vec![], new_type);
+                    result_type = new_type;
+                    continue;
+                }
+            }
+        }
+        if others.is_empty()
+            && *operator == ExprOperator::Add
+            && new_type == Type::Int
+            && is_zero(&first)
+        {
+            first = term;
+            result_type = new_type;
+            continue;
+        }
+        if is_zero(&term)
+            && new_type == result_type
+            && (*operator == ExprOperator::Subtract || new_type == Type::Int)
+        {
+            continue;
+        }
+        others.push((*operator, term));
+        result_type = new_type;
+    }
+    (first, others, analyzed_expr.2)
+}
+
+fn fold_condition(
+    variables: &SymbolTable,
+    analyzed_condition: &AnalyzedCondition,
+) -> AnalyzedCondition {
+    (
+        fold_expr(variables, &analyzed_condition.0),
+        analyzed_condition.1,
+        fold_expr(variables, &analyzed_condition.2),
+    )
+}
+
+fn fold_block(variables: &SymbolTable, analyzed_block: &AnalyzedProgram) -> AnalyzedProgram {
+    analyzed_block
+        .iter()
+        .map(|(statement, line)| (fold_statement(variables, statement), *line))
+        .collect()
+}
+
+fn fold_statement(
+    variables: &SymbolTable,
+    analyzed_statement: &AnalyzedStatement,
+) -> AnalyzedStatement {
+    match analyzed_statement {
+        AnalyzedStatement::Assignment(handle, expr) => {
+            AnalyzedStatement::Assignment(*handle, fold_expr(variables, expr))
+        }
+        AnalyzedStatement::ElementAssignment(handle, index, expr) => {
+            AnalyzedStatement::ElementAssignment(
+                *handle,
+                fold_expr(variables, index),
+                fold_expr(variables, expr),
+            )
+        }
+        AnalyzedStatement::ElementInput(handle, index) => {
+            AnalyzedStatement::ElementInput(*handle, fold_expr(variables, index))
+        }
+        AnalyzedStatement::OutputOperation(exprs) => AnalyzedStatement::OutputOperation(
+            exprs
+                .iter()
+                .map(|expr| fold_expr(variables, expr))
+                .collect(),
+        ),
+        AnalyzedStatement::IfElse(condition, then_block, else_block) => AnalyzedStatement::IfElse(
+            fold_condition(variables, condition),
+            fold_block(variables, then_block),
+            fold_block(variables, else_block),
+        ),
+        AnalyzedStatement::WhileLoop(condition, body) => AnalyzedStatement::WhileLoop(
+            fold_condition(variables, condition),
+            fold_block(variables, body),
+        ),
+        AnalyzedStatement::Return(handle, expr) => {
+            AnalyzedStatement::Return(*handle, fold_expr(variables, expr))
+        }
+        AnalyzedStatement::Declaration(handle) => AnalyzedStatement::Declaration(*handle),
+        AnalyzedStatement::InputOperation(handle) => AnalyzedStatement::InputOperation(*handle),
+        AnalyzedStatement::FunctionDefinition(handle) => {
+            AnalyzedStatement::FunctionDefinition(*handle)
+        }
+    }
+}
+
+// Which variables are read, and which are used in any way, in some code.
+struct Usage {
+    read: Vec<bool>,
+    used: Vec<bool>,
+}
+
+impl Usage {
+    fn new(symbol_count: usize) -> Usage {
+        Usage {
+            read: vec![false; symbol_count],
+            used: vec![false; symbol_count],
+        }
+    }
+    fn of_statement(symbol_count: usize, statement: &AnalyzedStatement) -> Usage {
+        let mut usage = Usage::new(symbol_count);
+        usage.scan_statement(statement);
+        usage
+    }
+    fn of_expr(symbol_count: usize, expr: &AnalyzedExpr) -> Usage {
+        let mut usage = Usage::new(symbol_count);
+        usage.scan_expr(expr);
+        usage
+    }
+    fn scan_factor(&mut self, factor: &AnalyzedFactor) {
+        match factor {
+            AnalyzedFactor::Literal(_) => {}
+            AnalyzedFactor::Identifier(handle) => {
+                self.read[*handle] = true;
+                self.used[*handle] = true;
+            }
+            AnalyzedFactor::Element(handle, index) => {
+                self.read[*handle] = true;
+                self.used[*handle] = true;
+                self.scan_expr(index);
+            }
+            AnalyzedFactor::SubExpression(expr) => self.scan_expr(expr),
+            AnalyzedFactor::FunctionCall(_, arguments)
+            | AnalyzedFactor::NativeCall(_, arguments)
+            | AnalyzedFactor::BuiltinCall(_, arguments) => {
+                for argument in arguments {
+                    self.scan_expr(argument);
+                }
+            }
+            AnalyzedFactor::Negation(factor) => self.scan_factor(factor),
+            AnalyzedFactor::Power(base, exponent) => {
+                self.scan_factor(base);
+                self.scan_factor(exponent);
+            }
+        }
+    }
+    fn scan_expr(&mut self, expr: &AnalyzedExpr) {
+        for term in std::iter::once(&expr.0).chain(expr.1.iter().map(|term| &term.1)) {
+            self.scan_factor(&term.0);
+            for factor in &term.1 {
+                self.scan_factor(&factor.1);
+            }
+        }
+    }
+    fn scan_statement(&mut self, statement: &AnalyzedStatement) {
+        match statement {
+            AnalyzedStatement::Assignment(handle, expr) => {
+                self.used[*handle] = true;
+                self.scan_expr(expr);
+            }
+            AnalyzedStatement::Declaration(_) | AnalyzedStatement::FunctionDefinition(_) => {}
+            AnalyzedStatement::InputOperation(handle) => self.used[*handle] = true,
+            AnalyzedStatement::ElementAssignment(handle, index, expr) => {
+                self.used[*handle] = true;
+                self.scan_expr(index);
+                self.scan_expr(expr);
+            }
+            AnalyzedStatement::ElementInput(handle, index) => {
+                self.used[*handle] = true;
+                self.scan_expr(index);
+            }
+            AnalyzedStatement::OutputOperation(exprs) => {
+                for expr in exprs {
+                    self.scan_expr(expr);
+                }
+            }
+            AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+                self.scan_expr(&condition.0);
+                self.scan_expr(&condition.2);
+                self.scan_block(then_block);
+                self.scan_block(else_block);
+            }
+            AnalyzedStatement::WhileLoop(condition, body) => {
+                self.scan_expr(&condition.0);
+                self.scan_expr(&condition.2);
+                self.scan_block(body);
+            }
+            AnalyzedStatement::Return(_, expr) => self.scan_expr(expr),
+        }
+    }
+    fn scan_block(&mut self, block: &AnalyzedProgram) {
+        for (statement, _) in block {
+            self.scan_statement(statement);
+        }
+    }
+}
+
+// A store is dead if its variable is never read, or if it is overwritten
+// later in the same block before being read.
+// Stores into the elements of arrays are always kept,
+// and so are the stores whose expression could fail at runtime.
+// Functions cannot access the variables of their callers,
+// so calls do not read the variables of the current block.
+fn is_dead_store(block: &AnalyzedProgram, position: usize, usage: &Usage) -> bool {
+    let (handle, expr) = match &block[position].0 {
+        AnalyzedStatement::Assignment(handle, expr) => (*handle, expr),
+        _ => return false,
+    };
+    if !cannot_fail(expr) {
+        return false;
+    }
+    let symbol_count = usage.read.len();
+    if !usage.read[handle] {
+        return true;
+    }
+    for (statement, _) in &block[position + 1..] {
+        match statement {
+            AnalyzedStatement::Assignment(target, expr) if *target == handle => {
+                return !Usage::of_expr(symbol_count, expr).read[handle];
+            }
+            AnalyzedStatement::InputOperation(target) if *target == handle => return true,
+            AnalyzedStatement::Return(_, _) => return false,
+            statement => {
+                if Usage::of_statement(symbol_count, statement).read[handle] {
+                    return false;
+                }
+            }
+        }
+    }
+    false
+}
+
+// Only the literals and the variables are evaluated without any check,
+// while every operation, index or call may give an error.
+fn cannot_fail(expr: &AnalyzedExpr) -> bool {
+    matches!(
+        single_factor(expr),
+        Some(AnalyzedFactor::Literal(_)) | Some(AnalyzedFactor::Identifier(_))
+    )
+}
+
+// Removes the dead stores and the declarations of the unused variables,
+// returning whether anything was removed.
+fn remove_useless_statements(block: &mut AnalyzedProgram, usage: &Usage) -> bool {
+    let mut changed = false;
+    let mut position = 0;
+    while position < block.len() {
+        let useless = match &block[position].0 {
+            AnalyzedStatement::Declaration(handle) => !usage.used[*handle],
+            _ => is_dead_store(block, position, usage),
+        };
+        if useless {
+            block.remove(position);
+            changed = true;
+            continue;
+        }
+        match &mut block[position].0 {
+            AnalyzedStatement::IfElse(_, then_block, else_block) => {
+                changed |= remove_useless_statements(then_block, usage);
+                changed |= remove_useless_statements(else_block, usage);
+            }
+            AnalyzedStatement::WhileLoop(_, body) => {
+                changed |= remove_useless_statements(body, usage);
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    changed
+}
+
+// Returns a warning for every variable that may be read before being assigned,
+// in which case it has the default value of its type.
+pub fn check_assignments(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Vec<String> {
+    let mut checker = AssignmentChecker {
+        variables,
+        warned: vec![false; variables.symbol_count()],
+        warnings: Vec::<String>::new(),
+        context: String::new(),
+    };
+    let mut assigned = vec![false; variables.symbol_count()];
+    checker.check_block(analyzed_program, &mut assigned);
+    for handle in 0..variables.function_count() {
+        let function = variables.get_function(handle);
+        let mut assigned = vec![false; variables.symbol_count()];
+        for parameter in function.locals.clone().take(function.parameter_count) {
+            assigned[parameter] = true;
+        }
+        checker.context = format!(" in function '{}'", function.name);
+        checker.check_block(&function.body, &mut assigned);
+    }
+    checker.warnings
+}
+
+struct AssignmentChecker<'a> {
+    variables: &'a SymbolTable,
+    warned: Vec<bool>,
+    warnings: Vec<String>,
+    context: String,
+}
+
+impl<'a> AssignmentChecker<'a> {
+    fn check_expr(&mut self, expr: &AnalyzedExpr, assigned: &[bool]) {
+        let usage = Usage::of_expr(self.variables.symbol_count(), expr);
+        for (handle, &read) in usage.read.iter().enumerate() {
+            if read && !assigned[handle] && !self.warned[handle] {
+                self.warned[handle] = true;
+                self.warnings.push(format!(
+                    "Variable '{}' may be read before being assigned{}.",
+                    self.variables.get_name(handle),
+                    self.context
+                ));
+            }
+        }
+    }
+    fn check_condition(&mut self, condition: &AnalyzedCondition, assigned: &[bool]) {
+        self.check_expr(&condition.0, assigned);
+        self.check_expr(&condition.2, assigned);
+    }
+    // A variable is assigned after an if statement only if both branches assign it,
+    // and after a while statement only if it was assigned before the loop.
+    // The elements of arrays are not tracked, so arrays count as assigned.
+    fn check_block(&mut self, block: &AnalyzedProgram, assigned: &mut [bool]) {
+        for (statement, _) in block {
+            match statement {
+                AnalyzedStatement::Declaration(handle) => {
+                    assigned[*handle] = self.variables.array_size(*handle).is_some()
+                }
+                AnalyzedStatement::Assignment(handle, expr) => {
+                    self.check_expr(expr, assigned);
+                    assigned[*handle] = true;
+                }
+                AnalyzedStatement::ElementAssignment(_, index, expr) => {
+                    self.check_expr(expr, assigned);
+                    self.check_expr(index, assigned);
+                }
+                AnalyzedStatement::InputOperation(handle) => assigned[*handle] = true,
+                AnalyzedStatement::ElementInput(_, index) => self.check_expr(index, assigned),
+                AnalyzedStatement::OutputOperation(exprs) => {
+                    for expr in exprs {
+                        self.check_expr(expr, assigned);
+                    }
+                }
+                AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+                    self.check_condition(condition, assigned);
+                    let mut then_assigned = assigned.to_vec();
+                    self.check_block(then_block, &mut then_assigned);
+                    let mut else_assigned = assigned.to_vec();
+                    self.check_block(else_block, &mut else_assigned);
+                    for handle in 0..assigned.len() {
+                        assigned[handle] = then_assigned[handle] && else_assigned[handle];
+                    }
+                }
+                AnalyzedStatement::WhileLoop(condition, body) => {
+                    self.check_condition(condition, assigned);
+                    self.check_block(body, &mut assigned.to_vec());
+                }
+                AnalyzedStatement::FunctionDefinition(_) => {}
+                AnalyzedStatement::Return(_, expr) => self.check_expr(expr, assigned),
+            }
+        }
+    }
+}
+
+// Formats an analyzed tree like calc source, to show what the optimizer did.
+// Floats always have a decimal point, to tell them from integers.
+pub fn dump_program(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> String {
+    let mut dump = dump_block(variables, analyzed_program, 0);
+    for handle in 0..variables.function_count() {
+        let function = variables.get_function(handle);
+        let parameters: Vec<String> = function
+            .locals
+            .clone()
+            .take(function.parameter_count)
+            .map(|local| {
+                format!(
+                    "{}: {}",
+                    variables.get_name(local),
+                    variables.get_type(local)
+                )
+            })
+            .collect();
+        dump += &format!(
+            "fn {}({}) -> {} {{\n{}}}\n",
+            function.name,
+            parameters.join(", "),
+            function.return_type,
+            dump_block(variables, &function.body, 1)
+        );
+    }
+    dump
+}
+
+fn dump_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> String {
+    let dump_arguments = |arguments: &[AnalyzedExpr]| {
+        arguments
+            .iter()
+            .map(|argument| dump_expr(variables, argument))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    match factor {
+        AnalyzedFactor::Literal(Value::Float(value)) => format!("{:?}", value),
+        AnalyzedFactor::Literal(Value::Str(value)) => format!("{:?}", value),
+        AnalyzedFactor::Literal(value) => value.to_string(),
+        AnalyzedFactor::Identifier(handle) => variables.get_name(*handle),
+        AnalyzedFactor::Element(handle, index) => format!(
+            "{}[{}]",
+            variables.get_name(*handle),
+            dump_expr(variables, index)
+        ),
+        AnalyzedFactor::SubExpression(expr) => format!("({})", dump_expr(variables, expr)),
+        AnalyzedFactor::FunctionCall(handle, arguments) => format!(
+            "{}({})",
+            variables.get_function(*handle).name,
+            dump_arguments(arguments)
+        ),
+        AnalyzedFactor::BuiltinCall(builtin, arguments) => {
+            format!("{}({})", builtin.name(), dump_arguments(arguments))
+        }
+        AnalyzedFactor::NativeCall(handle, arguments) => format!(
+            "{}({})",
+            variables.get_native(*handle).name,
+            dump_arguments(arguments)
+        ),
+        AnalyzedFactor::Negation(factor) => format!("-{}", dump_factor(variables, factor)),
+        AnalyzedFactor::Power(base, exponent) => format!(
+            "{} ^ {}",
+            dump_factor(variables, base),
+            dump_factor(variables, exponent)
+        ),
+    }
+}
+
+fn dump_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> String {
+    let dump_term = |term: &AnalyzedTerm| {
+        let mut dump = dump_factor(variables, &term.0);
+        for (operator, factor) in &term.1 {
+            dump += &format!(
+                " {} {}",
+                term_operator_symbol(*operator),
+                dump_factor(variables, factor)
+            );
+        }
+        dump
+    };
+    let mut dump = dump_term(&expr.0);
+    for (operator, term) in &expr.1 {
+        dump += &format!(" {} {}", expr_operator_symbol(*operator), dump_term(term));
+    }
+    dump
+}
+
+fn dump_block(variables: &SymbolTable, block: &AnalyzedProgram, indentation: usize) -> String {
+    let indent = "    ".repeat(indentation);
+    let dump_condition = |condition: &AnalyzedCondition| {
+        format!(
+            "{} {} {}",
+            dump_expr(variables, &condition.0),
+            comparison_operator_symbol(condition.1),
+            dump_expr(variables, &condition.2)
+        )
+    };
+    let mut dump = String::new();
+    for (statement, _) in block {
+        dump += &indent;
+        dump += &match statement {
+            AnalyzedStatement::Declaration(handle) => match variables.array_size(*handle) {
+                Some(size) => format!(
+                    "@{}[{}]: {}\n",
+                    variables.get_name(*handle),
+                    size,
+                    variables.get_type(*handle)
+                ),
+                None => format!(
+                    "@{}: {}\n",
+                    variables.get_name(*handle),
+                    variables.get_type(*handle)
+                ),
+            },
+            AnalyzedStatement::InputOperation(handle) => {
+                format!(">{}\n", variables.get_name(*handle))
+            }
+            AnalyzedStatement::ElementInput(handle, index) => format!(
+                ">{}[{}]\n",
+                variables.get_name(*handle),
+                dump_expr(variables, index)
+            ),
+            AnalyzedStatement::OutputOperation(exprs) => format!(
+                "<{}\n",
+                exprs
+                    .iter()
+                    .map(|expr| dump_expr(variables, expr))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            AnalyzedStatement::Assignment(handle, expr) => format!(
+                "{} := {}\n",
+                variables.get_name(*handle),
+                dump_expr(variables, expr)
+            ),
+            AnalyzedStatement::ElementAssignment(handle, index, expr) => format!(
+                "{}[{}] := {}\n",
+                variables.get_name(*handle),
+                dump_expr(variables, index),
+                dump_expr(variables, expr)
+            ),
+            AnalyzedStatement::IfElse(condition, then_block, else_block) => format!(
+                "if {} {{\n{}{}}} else {{\n{}{}}}\n",
+                dump_condition(condition),
+                dump_block(variables, then_block, indentation + 1),
+                indent,
+                dump_block(variables, else_block, indentation + 1),
+                indent
+            ),
+            AnalyzedStatement::WhileLoop(condition, body) => format!(
+                "while {} {{\n{}{}}}\n",
+                dump_condition(condition),
+                dump_block(variables, body, indentation + 1),
+                indent
+            ),
+            AnalyzedStatement::FunctionDefinition(handle) => {
+                format!("fn {}\n", variables.get_function(*handle).name)
+            }
+            AnalyzedStatement::Return(_, expr) => {
+                format!("return {}\n", dump_expr(variables, expr))
+            }
+        };
+    }
+    dump
+}
@@ -3,25 +3,39 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     bytes::complete::take_while,
-    character::complete::{alpha1, char},
-    combinator::map,
-    multi::many0,
+    character::complete::{alpha1, alphanumeric1, anychar, char, digit1, none_of, one_of},
+    combinator::{cut, map, map_res, not, opt, peek, recognize, verify},
+    multi::{many0, separated_list, separated_nonempty_list},
     number::complete::double,
-    sequence::{delimited, preceded, tuple},
-    IResult,
+    sequence::{delimited, preceded, terminated, tuple},
+    IResult, Offset,
 };
 
+use crate::diagnostics::Diagnostic;
+use crate::value::Type;
+
+const KEYWORDS: [&str; 7] = ["if", "else", "while", "fn", "return", "true", "false"];
+
 #[derive(Debug, PartialEq)]
 pub enum ParsedFactor<'a> {
     Literal(f64),
+    IntegerLiteral(i64),
+    BooleanLiteral(bool),
+    StringLiteral(&'a str),
     Identifier(&'a str),
+    // An element of an array, and its index.
+    Element(&'a str, Box<ParsedExpr<'a>>),
     SubExpression(Box<ParsedExpr<'a>>),
+    FunctionCall(&'a str, Vec<ParsedExpr<'a>>),
+    Negation(Box<ParsedFactor<'a>>),
+    Power(Box<ParsedFactor<'a>>, Box<ParsedFactor<'a>>),
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TermOperator {
     Multiply,
     Divide,
+    Remainder,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -30,48 +44,140 @@ pub enum ExprOperator {
     Subtract,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
 pub type ParsedTerm<'a> = (ParsedFactor<'a>, Vec<(TermOperator, ParsedFactor<'a>)>);
 
-pub type ParsedExpr<'a> = (ParsedTerm<'a>, Vec<(ExprOperator, ParsedTerm<'a>)>);
+// The last item is the source code of the expression, used to report type errors.
+pub type ParsedExpr<'a> = (ParsedTerm<'a>, Vec<(ExprOperator, ParsedTerm<'a>)>, &'a str);
+
+// A condition is either a comparison or a boolean expression.
+pub type ParsedCondition<'a> = (ParsedExpr<'a>, Option<(ComparisonOperator, ParsedExpr<'a>)>);
 
 #[derive(Debug)]
 pub enum ParsedStatement<'a> {
-    Declaration(&'a str),
+    Declaration(&'a str, Option<Type>),
+    // The size of an array is the text of its literal, checked by the analyzer.
+    ArrayDeclaration(&'a str, &'a str, Option<Type>),
     InputOperation(&'a str),
-    OutputOperation(ParsedExpr<'a>),
+    ElementInput(&'a str, ParsedExpr<'a>),
+    OutputOperation(Vec<ParsedExpr<'a>>),
     Assignment(&'a str, ParsedExpr<'a>),
+    ElementAssignment(&'a str, ParsedExpr<'a>, ParsedExpr<'a>),
+    IfElse(ParsedCondition<'a>, ParsedProgram<'a>, ParsedProgram<'a>),
+    WhileLoop(ParsedCondition<'a>, ParsedProgram<'a>),
+    FunctionDefinition(
+        &'a str,
+        Vec<(&'a str, Option<Type>)>,
+        Option<Type>,
+        ParsedProgram<'a>,
+    ),
+    Return(&'a str, ParsedExpr<'a>),
+}
+
+// Every statement is paired with the line where it begins.
+pub type ParsedProgram<'a> = Vec<(ParsedStatement<'a>, usize)>;
+
+pub fn parse_program(input: &str) -> IResult<&str, ParsedProgram<'_>> {
+    parse_statements(input, input)
+}
+
+// Parses a whole program, returning the diagnostic of the innermost invalid code, if any.
+pub fn parse_whole_program(input: &str) -> Result<ParsedProgram<'_>, Diagnostic<'_>> {
+    match parse_program(input) {
+        Ok((rest, parsed_program)) => match rest.trim() {
+            "" => Ok(parsed_program),
+            rest => Err(Diagnostic::InvalidStatement(rest)),
+        },
+        Err(err) => Err(Diagnostic::from_parse_error(input, err)),
+    }
 }
 
-pub type ParsedProgram<'a> = Vec<ParsedStatement<'a>>;
+// The lines are counted from the start of the whole source.
+fn parse_statements<'a>(source: &'a str, input: &'a str) -> IResult<&'a str, ParsedProgram<'a>> {
+    many0(preceded(skip_spaces, |input| {
+        let line = line_number(source, input);
+        parse_statement(source, input).map(|(input, statement)| (input, (statement, line)))
+    }))(input)
+}
+
+fn line_number(source: &str, input: &str) -> usize {
+    source[..source.offset(input)].matches('\n').count() + 1
+}
 
-pub fn parse_program(input: &str) -> IResult<&str, ParsedProgram> {
-    many0(preceded(
+fn parse_statement<'a>(source: &'a str, input: &'a str) -> IResult<&'a str, ParsedStatement<'a>> {
+    alt((
+        parse_array_declaration,
+        parse_declaration,
+        parse_element_input_statement,
+        parse_input_statement,
+        parse_output_statement,
+        |input| parse_if_statement(source, input),
+        |input| parse_while_statement(source, input),
+        |input| parse_function_definition(source, input),
+        parse_return_statement,
+        parse_element_assignment,
+        parse_assignment,
+    ))(input)
+}
+
+fn parse_declaration(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        char('@'),
         skip_spaces,
-        alt((
-            parse_declaration,
-            parse_input_statement,
-            parse_output_statement,
-            parse_assignment,
-        )),
+        parse_identifier,
+        opt(parse_type_annotation),
+    ))(input)
+    .map(|(input, output)| (input, ParsedStatement::Declaration(output.2, output.3)))
+}
+
+fn parse_array_declaration(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        char('@'),
+        skip_spaces,
+        parse_identifier,
+        delimited(
+            preceded(skip_spaces, char('[')),
+            preceded(skip_spaces, digit1),
+            preceded(skip_spaces, char(']')),
+        ),
+        opt(parse_type_annotation),
     ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::ArrayDeclaration(output.2, output.3, output.4),
+        )
+    })
 }
 
-fn parse_declaration(input: &str) -> IResult<&str, ParsedStatement> {
-    tuple((char('@'), skip_spaces, parse_identifier))(input)
-        .map(|(input, output)| (input, ParsedStatement::Declaration(output.2)))
+fn parse_element_input_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((char('>'), skip_spaces, parse_identifier, parse_index))(input)
+        .map(|(input, output)| (input, ParsedStatement::ElementInput(output.2, output.3)))
 }
 
-fn parse_input_statement(input: &str) -> IResult<&str, ParsedStatement> {
+fn parse_input_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
     tuple((char('>'), skip_spaces, parse_identifier))(input)
         .map(|(input, output)| (input, ParsedStatement::InputOperation(output.2)))
 }
 
-fn parse_output_statement(input: &str) -> IResult<&str, ParsedStatement> {
-    tuple((char('<'), skip_spaces, parse_expr))(input)
-        .map(|(input, output)| (input, ParsedStatement::OutputOperation(output.2)))
+fn parse_output_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        char('<'),
+        separated_nonempty_list(preceded(skip_spaces, char(',')), parse_expr),
+    ))(input)
+    .map(|(input, output)| (input, ParsedStatement::OutputOperation(output.1)))
 }
 
-fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement> {
+fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement<'_>> {
     tuple((
         parse_identifier,
         skip_spaces,
@@ -82,11 +188,154 @@ fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement> {
     .map(|(input, output)| (input, ParsedStatement::Assignment(output.0, output.4)))
 }
 
+fn parse_element_assignment(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        parse_identifier,
+        parse_index,
+        skip_spaces,
+        tag(":="),
+        skip_spaces,
+        parse_expr,
+    ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::ElementAssignment(output.0, output.1, output.5),
+        )
+    })
+}
+
+fn parse_if_statement<'a>(
+    source: &'a str,
+    input: &'a str,
+) -> IResult<&'a str, ParsedStatement<'a>> {
+    tuple((
+        parse_keyword("if"),
+        parse_condition,
+        |input| parse_block(source, input),
+        opt(preceded(
+            preceded(skip_spaces, parse_keyword("else")),
+            alt((
+                |input| parse_block(source, input),
+                |input| {
+                    let (input, _) = skip_spaces(input)?;
+                    let line = line_number(source, input);
+                    parse_if_statement(source, input)
+                        .map(|(input, statement)| (input, vec![(statement, line)]))
+                },
+            )),
+        )),
+    ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::IfElse(output.1, output.2, output.3.unwrap_or_default()),
+        )
+    })
+}
+
+fn parse_while_statement<'a>(
+    source: &'a str,
+    input: &'a str,
+) -> IResult<&'a str, ParsedStatement<'a>> {
+    tuple((parse_keyword("while"), parse_condition, |input| {
+        parse_block(source, input)
+    }))(input)
+    .map(|(input, output)| (input, ParsedStatement::WhileLoop(output.1, output.2)))
+}
+
+fn parse_function_definition<'a>(
+    source: &'a str,
+    input: &'a str,
+) -> IResult<&'a str, ParsedStatement<'a>> {
+    tuple((
+        parse_keyword("fn"),
+        preceded(skip_spaces, parse_identifier),
+        delimited(
+            preceded(skip_spaces, char('(')),
+            separated_list(
+                preceded(skip_spaces, char(',')),
+                tuple((
+                    preceded(skip_spaces, parse_identifier),
+                    opt(parse_type_annotation),
+                )),
+            ),
+            preceded(skip_spaces, char(')')),
+        ),
+        opt(preceded(
+            preceded(skip_spaces, tag("->")),
+            preceded(skip_spaces, parse_type),
+        )),
+        |input| parse_block(source, input),
+    ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::FunctionDefinition(output.1, output.2, output.3, output.4),
+        )
+    })
+}
+
+fn parse_return_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((parse_keyword("return"), parse_expr))(input)
+        .map(|(input, output)| (input, ParsedStatement::Return(output.0, output.1)))
+}
+
+// Once a block is open, its statements must be valid up to its closing brace,
+// so that an invalid statement is reported where it is,
+// instead of at the beginning of the enclosing statement.
+fn parse_block<'a>(source: &'a str, input: &'a str) -> IResult<&'a str, ParsedProgram<'a>> {
+    preceded(
+        preceded(skip_spaces, char('{')),
+        cut(terminated(
+            |input| parse_statements(source, input),
+            preceded(skip_spaces, char('}')),
+        )),
+    )(input)
+}
+
+fn parse_keyword<'a>(keyword: &'static str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag(keyword), not(peek(alt((alphanumeric1, tag("_"))))))
+}
+
+fn parse_type(input: &str) -> IResult<&str, Type> {
+    alt((
+        map(parse_keyword("int"), |_| Type::Int),
+        map(parse_keyword("float"), |_| Type::Float),
+        map(parse_keyword("bool"), |_| Type::Bool),
+        map(parse_keyword("string"), |_| Type::Str),
+    ))(input)
+}
+
+fn parse_type_annotation(input: &str) -> IResult<&str, Type> {
+    preceded(
+        preceded(skip_spaces, char(':')),
+        preceded(skip_spaces, parse_type),
+    )(input)
+}
+
+// Identifiers begin with a letter, which may be followed by letters, digits and underscores.
 fn parse_identifier(input: &str) -> IResult<&str, &str> {
-    alpha1(input)
+    verify(
+        recognize(tuple((alpha1, many0(alt((alphanumeric1, tag("_"))))))),
+        |name: &str| !KEYWORDS.contains(&name),
+    )(input)
 }
 
-fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr> {
+fn parse_index(input: &str) -> IResult<&str, ParsedExpr<'_>> {
+    delimited(
+        preceded(skip_spaces, char('[')),
+        parse_expr,
+        preceded(skip_spaces, char(']')),
+    )(input)
+}
+
+fn parse_element(input: &str) -> IResult<&str, ParsedFactor<'_>> {
+    tuple((parse_identifier, parse_index))(input)
+        .map(|(input, output)| (input, ParsedFactor::Element(output.0, Box::new(output.1))))
+}
+
+fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr<'_>> {
     delimited(
         preceded(skip_spaces, char('(')),
         parse_expr,
@@ -94,12 +343,55 @@ fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr> {
     )(input)
 }
 
-fn parse_factor(input: &str) -> IResult<&str, ParsedFactor> {
+// Numbers without a fractional part or an exponent are integers.
+fn parse_integer(input: &str) -> IResult<&str, i64> {
+    terminated(
+        map_res(digit1, |digits: &str| digits.parse::<i64>()),
+        not(peek(one_of(".eE"))),
+    )(input)
+}
+
+// Returns the text between the quotes, whose escape sequences
+// are replaced by the analyzer.
+fn parse_string(input: &str) -> IResult<&str, &str> {
+    delimited(
+        char('"'),
+        recognize(many0(alt((
+            map(preceded(char('\\'), anychar), |_| ()),
+            map(none_of("\"\\\n"), |_| ()),
+        )))),
+        char('"'),
+    )(input)
+}
+
+fn parse_function_call(input: &str) -> IResult<&str, ParsedFactor<'_>> {
+    tuple((
+        parse_identifier,
+        delimited(
+            preceded(skip_spaces, char('(')),
+            separated_list(preceded(skip_spaces, char(',')), parse_expr),
+            preceded(skip_spaces, char(')')),
+        ),
+    ))(input)
+    .map(|(input, output)| (input, ParsedFactor::FunctionCall(output.0, output.1)))
+}
+
+fn parse_primary(input: &str) -> IResult<&str, ParsedFactor<'_>> {
     preceded(
         skip_spaces,
         alt((
+            parse_function_call,
+            map(parse_keyword("true"), |_| {
+                ParsedFactor::BooleanLiteral(true)
+            }),
+            map(parse_keyword("false"), |_| {
+                ParsedFactor::BooleanLiteral(false)
+            }),
+            parse_element,
             map(parse_identifier, ParsedFactor::Identifier),
+            map(parse_integer, ParsedFactor::IntegerLiteral),
             map(double, ParsedFactor::Literal),
+            map(parse_string, ParsedFactor::StringLiteral),
             map(parse_subexpr, |expr| {
                 ParsedFactor::SubExpression(Box::new(expr))
             }),
@@ -107,7 +399,35 @@ fn parse_factor(input: &str) -> IResult<&str, ParsedFactor> {
     )(input)
 }
 
-fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
+// The exponent is itself a factor, so that the power operator
+// is right-associative and binds tighter than unary minus.
+fn parse_power(input: &str) -> IResult<&str, ParsedFactor<'_>> {
+    tuple((
+        parse_primary,
+        opt(preceded(preceded(skip_spaces, char('^')), parse_factor)),
+    ))(input)
+    .map(|(input, output)| match output.1 {
+        Some(exponent) => (
+            input,
+            ParsedFactor::Power(Box::new(output.0), Box::new(exponent)),
+        ),
+        None => (input, output.0),
+    })
+}
+
+// The minus sign is parsed before any number,
+// so that "-2^2" is the negation of "2^2".
+fn parse_factor(input: &str) -> IResult<&str, ParsedFactor<'_>> {
+    alt((
+        map(
+            preceded(preceded(skip_spaces, char('-')), parse_factor),
+            |factor| ParsedFactor::Negation(Box::new(factor)),
+        ),
+        parse_power,
+    ))(input)
+}
+
+fn parse_term(input: &str) -> IResult<&str, ParsedTerm<'_>> {
     tuple((
         parse_factor,
         many0(tuple((
@@ -116,6 +436,7 @@ fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
                 alt((
                     map(char('*'), |_| TermOperator::Multiply),
                     map(char('/'), |_| TermOperator::Divide),
+                    map(char('%'), |_| TermOperator::Remainder),
                 )),
             ),
             parse_factor,
@@ -123,8 +444,9 @@ fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
     ))(input)
 }
 
-fn parse_expr(input: &str) -> IResult<&str, ParsedExpr> {
-    tuple((
+pub fn parse_expr(input: &str) -> IResult<&str, ParsedExpr<'_>> {
+    let (input, _) = skip_spaces(input)?;
+    let (rest, (first_term, other_terms)) = tuple((
         parse_term,
         many0(tuple((
             preceded(
@@ -136,6 +458,30 @@ fn parse_expr(input: &str) -> IResult<&str, ParsedExpr> {
             ),
             parse_term,
         ))),
+    ))(input)?;
+    Ok((
+        rest,
+        (first_term, other_terms, &input[..input.offset(rest)]),
+    ))
+}
+
+fn parse_condition(input: &str) -> IResult<&str, ParsedCondition<'_>> {
+    tuple((
+        parse_expr,
+        opt(tuple((
+            preceded(
+                skip_spaces,
+                alt((
+                    map(tag("=="), |_| ComparisonOperator::Equal),
+                    map(tag("!="), |_| ComparisonOperator::NotEqual),
+                    map(tag("<="), |_| ComparisonOperator::LessOrEqual),
+                    map(tag(">="), |_| ComparisonOperator::GreaterOrEqual),
+                    map(char('<'), |_| ComparisonOperator::Less),
+                    map(char('>'), |_| ComparisonOperator::Greater),
+                )),
+            ),
+            parse_expr,
+        ))),
     ))(input)
 }
 
@@ -0,0 +1,82 @@
+use std::io::{BufRead, Write};
+
+// The devices used by the input and output statements of a running program.
+pub trait ProgramIo {
+    // Returns the next line typed by the user, without its line terminator.
+    fn input_line(&mut self) -> Result<String, String>;
+    // Writes a line produced by an output statement.
+    fn output_line(&mut self, text: &str) -> Result<(), String>;
+}
+
+// Prompts the user on stderr, reads stdin and writes to stdout.
+pub struct ConsoleIo;
+
+impl ProgramIo for ConsoleIo {
+    // The end of the input reads as an empty line.
+    fn input_line(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        eprint!("? ");
+        std::io::stdin()
+            .read_line(&mut text)
+            .map_err(|err| format!("Error: Cannot read line: {}.", err))?;
+        Ok(text.trim_end_matches(&['\n', '\r'][..]).to_string())
+    }
+    fn output_line(&mut self, text: &str) -> Result<(), String> {
+        println!("{}", text);
+        Ok(())
+    }
+}
+
+// Takes the inputs from a given list, without prompting,
+// and writes the output to any writer.
+pub struct BatchIo<W: Write> {
+    inputs: std::vec::IntoIter<String>,
+    writer: W,
+}
+
+impl<W: Write> BatchIo<W> {
+    pub fn new(inputs: Vec<String>, writer: W) -> BatchIo<W> {
+        BatchIo {
+            inputs: inputs.into_iter(),
+            writer,
+        }
+    }
+}
+
+impl<W: Write> ProgramIo for BatchIo<W> {
+    fn input_line(&mut self) -> Result<String, String> {
+        self.inputs
+            .next()
+            .ok_or_else(|| "Error: No more inputs.".to_string())
+    }
+    fn output_line(&mut self, text: &str) -> Result<(), String> {
+        writeln!(self.writer, "{}", text).map_err(|err| format!("Error: Cannot write: {}.", err))
+    }
+}
+
+// Reads the inputs from any reader, without prompting,
+// and writes the output to any writer.
+// As on the console, the end of the input reads as an empty line.
+pub struct StreamIo<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: BufRead, W: Write> StreamIo<R, W> {
+    pub fn new(reader: R, writer: W) -> StreamIo<R, W> {
+        StreamIo { reader, writer }
+    }
+}
+
+impl<R: BufRead, W: Write> ProgramIo for StreamIo<R, W> {
+    fn input_line(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        self.reader
+            .read_line(&mut text)
+            .map_err(|err| format!("Error: Cannot read line: {}.", err))?;
+        Ok(text.trim_end_matches(&['\n', '\r'][..]).to_string())
+    }
+    fn output_line(&mut self, text: &str) -> Result<(), String> {
+        writeln!(self.writer, "{}", text).map_err(|err| format!("Error: Cannot write: {}.", err))
+    }
+}
@@ -0,0 +1,339 @@
+use crate::analyzer;
+use crate::diagnostics;
+use crate::executor;
+use crate::parser;
+use crate::program_io::ProgramIo;
+use crate::symbol_table::SymbolTable;
+use crate::value::Value;
+use rustyline::error::ReadlineError;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+const HISTORY_FILE_NAME: &str = ".calc_history";
+
+const HELP: &str = "\
+Statements and expressions are executed as soon as they are complete.
+Commands:
+  :ast <expr>     Show the parsed form of an expression
+  :type <expr>    Show the type of an expression
+  :vars           Show the variables and their values
+  :load <file>    Execute a calc file in this session
+  :save <file>    Save the statements entered in this session
+  :reset          Remove all variables and functions
+  :help           Show this help
+  :quit           Exit";
+
+// What the user typed after a prompt.
+#[derive(Debug, PartialEq)]
+pub enum ReadResult {
+    Line(String),
+    // The line being typed was discarded with Ctrl-C.
+    Interrupted,
+    End,
+}
+
+// The source of the lines typed by the user.
+pub trait LineReader {
+    fn read_line(&mut self, prompt: &str) -> ReadResult;
+    fn add_history(&mut self, _entry: &str) {}
+}
+
+// Reads the lines from a buffered reader, like a script piped into stdin.
+pub struct ScriptReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> ScriptReader<R> {
+    pub fn new(reader: R) -> ScriptReader<R> {
+        ScriptReader { reader }
+    }
+}
+
+impl<R: BufRead> LineReader for ScriptReader<R> {
+    fn read_line(&mut self, prompt: &str) -> ReadResult {
+        eprint!("{}", prompt);
+        let mut text = String::new();
+        match self.reader.read_line(&mut text) {
+            Ok(0) | Err(_) => ReadResult::End,
+            Ok(_) => ReadResult::Line(text.trim_end_matches(&['\n', '\r'][..]).to_string()),
+        }
+    }
+}
+
+// Reads the lines from the terminal, with line editing
+// and a history that is kept in the home directory across sessions.
+pub struct EditorReader {
+    editor: rustyline::Editor<()>,
+    history_path: Option<PathBuf>,
+}
+
+impl Default for EditorReader {
+    fn default() -> EditorReader {
+        EditorReader::new()
+    }
+}
+
+impl EditorReader {
+    pub fn new() -> EditorReader {
+        let mut editor = rustyline::Editor::<()>::new();
+        let history_path =
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME));
+        if let Some(path) = &history_path {
+            // The history file does not exist before the first session.
+            let _ = editor.load_history(path);
+        }
+        EditorReader {
+            editor,
+            history_path,
+        }
+    }
+}
+
+impl LineReader for EditorReader {
+    fn read_line(&mut self, prompt: &str) -> ReadResult {
+        match self.editor.readline(prompt) {
+            Ok(line) => ReadResult::Line(line),
+            Err(ReadlineError::Interrupted) => ReadResult::Interrupted,
+            Err(_) => ReadResult::End,
+        }
+    }
+    fn add_history(&mut self, entry: &str) {
+        self.editor.add_history_entry(entry);
+        if let Some(path) = &self.history_path {
+            if let Err(err) = self.editor.save_history(path) {
+                eprintln!("Failed to write to file {}: ({})", path.display(), err);
+            }
+        }
+    }
+}
+
+// Shows the type and the value of a variable, and the size of an array.
+pub fn describe_variable(name: &str, value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!(
+            "{}: {}[{}] = {}",
+            name,
+            value.get_type(),
+            items.len(),
+            value
+        ),
+        _ => format!("{}: {} = {}", name, value.get_type(), value),
+    }
+}
+
+// An entry is unfinished if it has unclosed parentheses, braces or strings,
+// or if it ends with an operator or a comma.
+fn is_unfinished(text: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in text.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    in_string
+        || depth > 0
+        || text
+            .trim_end()
+            .ends_with(&['+', '-', '*', '/', '%', '^', ',', '=', '<', '>', ':', '@'][..])
+}
+
+// Reads a command, or a complete entry that may span several lines.
+// An empty line ends an unfinished entry anyway,
+// while Ctrl-C on any of its lines discards the whole entry.
+pub fn read_entry(reader: &mut dyn LineReader) -> ReadResult {
+    let mut entry = match reader.read_line(PROMPT) {
+        ReadResult::Line(line) => line,
+        other => return other,
+    };
+    if entry.trim_start().starts_with(':') {
+        return ReadResult::Line(entry);
+    }
+    while is_unfinished(&entry) {
+        match reader.read_line(CONTINUATION_PROMPT) {
+            ReadResult::Line(line) if !line.trim().is_empty() => {
+                entry += "\n";
+                entry += &line;
+            }
+            ReadResult::Interrupted => return ReadResult::Interrupted,
+            _ => break,
+        }
+    }
+    ReadResult::Line(entry)
+}
+
+// The inputs of the programs, read from the lines of the session.
+struct ReaderIo<'a> {
+    reader: &'a mut dyn LineReader,
+}
+
+impl<'a> ProgramIo for ReaderIo<'a> {
+    // The end of the input, or an interrupted line, reads as an empty line.
+    fn input_line(&mut self) -> Result<String, String> {
+        match self.reader.read_line("? ") {
+            ReadResult::Line(line) => Ok(line),
+            ReadResult::Interrupted | ReadResult::End => Ok(String::new()),
+        }
+    }
+    fn output_line(&mut self, text: &str) -> Result<(), String> {
+        println!("{}", text);
+        Ok(())
+    }
+}
+
+struct Session {
+    variables: SymbolTable,
+    // The entries that were executed, which are written by ":save".
+    entries: Vec<String>,
+}
+
+impl Session {
+    // A source containing errors must leave no declarations behind.
+    // The inputs of the program are read from the same lines as the entries.
+    fn execute(&mut self, source: &str, source_name: &str, reader: &mut dyn LineReader) {
+        let parsed_program = match parser::parse_whole_program(source) {
+            Ok(parsed_program) => parsed_program,
+            Err(err) => {
+                eprint!("{}", diagnostics::render(source, source_name, &err));
+                return;
+            }
+        };
+        let previous_variables = self.variables.clone();
+        match analyzer::analyze_program(&mut self.variables, &parsed_program) {
+            Ok(analyzed_program) => {
+                self.entries.push(source.trim().to_string());
+                if let Err(err) = executor::execute_program(
+                    &mut self.variables,
+                    &analyzed_program,
+                    &mut ReaderIo { reader },
+                ) {
+                    eprintln!("{}", err);
+                }
+            }
+            Err(errors) => {
+                for err in &errors {
+                    eprint!("{}", diagnostics::render(source, source_name, err));
+                }
+                self.variables = previous_variables;
+            }
+        }
+    }
+
+    fn parse_expression<'a>(&self, text: &'a str) -> Option<parser::ParsedExpr<'a>> {
+        match parser::parse_expr(text) {
+            Ok((rest, parsed_expr)) if rest.trim().is_empty() => Some(parsed_expr),
+            Ok((rest, _)) => {
+                eprint!(
+                    "{}",
+                    diagnostics::render(
+                        text,
+                        "<stdin>",
+                        &diagnostics::Diagnostic::InvalidStatement(rest.trim())
+                    )
+                );
+                None
+            }
+            Err(_) => {
+                eprintln!("Error: Invalid expression.");
+                None
+            }
+        }
+    }
+
+    // Returns false if the session must end.
+    fn run_command(&mut self, command: &str, reader: &mut dyn LineReader) -> bool {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(position) => (&command[..position], command[position..].trim()),
+            None => (command, ""),
+        };
+        match name {
+            ":quit" | ":q" => return false,
+            ":help" => eprintln!("{}", HELP),
+            ":reset" => {
+                self.variables = SymbolTable::new();
+                self.entries.clear();
+                eprintln!("Removed all variables and functions.");
+            }
+            ":vars" => {
+                for (name, value) in self.variables.iter() {
+                    println!("{}", describe_variable(name, value));
+                }
+            }
+            ":ast" => {
+                if let Some(parsed_expr) = self.parse_expression(argument) {
+                    println!("{:#?}", parsed_expr);
+                }
+            }
+            ":type" => {
+                if let Some(parsed_expr) = self.parse_expression(argument) {
+                    match analyzer::analyze_expr(&mut self.variables, &parsed_expr) {
+                        Ok(analyzed_expr) => println!("{}", analyzed_expr.2),
+                        Err(err) => eprint!("{}", diagnostics::render(argument, "<stdin>", &err)),
+                    }
+                }
+            }
+            ":load" if !argument.is_empty() => match std::fs::read_to_string(argument) {
+                Ok(source) => self.execute(&source, argument, reader),
+                Err(err) => eprintln!("Failed to read from file {}: ({})", argument, err),
+            },
+            ":save" if !argument.is_empty() => {
+                let mut source = self.entries.join("\n");
+                source += "\n";
+                match std::fs::write(argument, source) {
+                    Ok(_) => eprintln!("Saved the session to {}.", argument),
+                    Err(err) => eprintln!("Failed to write to file {}: ({})", argument, err),
+                }
+            }
+            ":load" | ":save" => eprintln!("Error: The command '{}' needs a file name.", name),
+            _ => eprintln!(
+                "Error: Unknown command '{}'. Type :help for the list of commands.",
+                name
+            ),
+        }
+        true
+    }
+}
+
+pub fn run(reader: &mut dyn LineReader) {
+    eprintln!("* Calc interactive interpreter *");
+    eprintln!("Type :help for the list of commands.");
+    let mut session = Session {
+        variables: SymbolTable::new(),
+        entries: Vec::<String>::new(),
+    };
+    loop {
+        let entry = match read_entry(reader) {
+            ReadResult::Line(entry) => entry,
+            ReadResult::Interrupted => continue,
+            ReadResult::End => break,
+        };
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        reader.add_history(entry);
+        if entry.starts_with(':') {
+            if !session.run_command(entry, reader) {
+                break;
+            }
+        } else {
+            session.execute(entry, "<stdin>", reader);
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use crate::analyzer::{
+    comparison_operator_symbol, AnalyzedExpr, AnalyzedProgram, AnalyzedStatement,
+};
+use crate::compiler::{
+    lower_condition, lower_expr, lower_expr_to, parenthesize, precedence, render_infix, Backend,
+    LoweredCondition, LoweredExpr, Operation, ATOM_PRECEDENCE, NEGATION_PRECEDENCE,
+};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+pub struct RustBackend;
+
+impl Backend for RustBackend {
+    fn file_suffix(&self) -> &'static str {
+        ".rs"
+    }
+    fn translate_program(
+        &self,
+        variables: &SymbolTable,
+        analyzed_program: &AnalyzedProgram,
+    ) -> Result<String, String> {
+        translate_to_rust_program(variables, analyzed_program)
+    }
+}
+
+fn rust_type(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "i64",
+        Type::Float => "f64",
+        Type::Bool => "bool",
+        Type::Str => "String",
+    }
+}
+
+fn translate_to_rust_value(value: &Value) -> String {
+    match value {
+        Value::Int(value) => value.to_string() + "i64",
+        Value::Float(value) if value.is_nan() => "f64::NAN".to_string(),
+        Value::Float(value) if value.is_infinite() && *value < 0. => {
+            "f64::NEG_INFINITY".to_string()
+        }
+        Value::Float(value) if value.is_infinite() => "f64::INFINITY".to_string(),
+        Value::Float(value) => value.to_string() + "f64",
+        Value::Bool(value) => value.to_string(),
+        Value::Str(value) => format!("String::from({:?})", value),
+        Value::Array(items) => format!(
+            "vec![{}]",
+            items
+                .iter()
+                .map(translate_to_rust_value)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+// The conversions bind more tightly than the arithmetic operators,
+// so they are parenthesized only as method receivers, as negation operands,
+// and before comparisons, as "as f64 <" would be taken for generic arguments.
+fn is_conversion(lowered_expr: &LoweredExpr) -> bool {
+    matches!(lowered_expr.0, Operation::ToFloat(_))
+}
+
+// The receiver of a method call must be parenthesized unless it is atomic.
+fn translate_to_rust_operand(variables: &SymbolTable, lowered_expr: &LoweredExpr) -> String {
+    parenthesize(
+        translate_to_rust_expr(variables, lowered_expr),
+        precedence(lowered_expr) < ATOM_PRECEDENCE || is_conversion(lowered_expr),
+    )
+}
+
+fn translate_to_rust_list(variables: &SymbolTable, lowered_exprs: &[LoweredExpr]) -> String {
+    lowered_exprs
+        .iter()
+        .map(|lowered_expr| translate_to_rust_expr(variables, lowered_expr))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// The index is checked by the "index" function of the generated program.
+fn translate_to_rust_element(
+    variables: &SymbolTable,
+    handle: usize,
+    index: &LoweredExpr,
+) -> String {
+    format!(
+        "_{}[index({}, {})]",
+        variables.get_name(handle),
+        translate_to_rust_expr(variables, index),
+        variables.array_size(handle).unwrap_or(0)
+    )
+}
+
+fn translate_to_rust_expr(variables: &SymbolTable, lowered_expr: &LoweredExpr) -> String {
+    match &lowered_expr.0 {
+        Operation::Literal(value) => translate_to_rust_value(value),
+        Operation::Variable(handle) => {
+            let name = "_".to_string() + &variables.get_name(*handle);
+            if lowered_expr.1 == Type::Str {
+                name + ".clone()"
+            } else {
+                name
+            }
+        }
+        Operation::Element(handle, index) => {
+            let element = translate_to_rust_element(variables, *handle, index);
+            if lowered_expr.1 == Type::Str {
+                element + ".clone()"
+            } else {
+                element
+            }
+        }
+        Operation::ToFloat(operand) => {
+            format!("{} as f64", translate_to_rust_operand(variables, operand))
+        }
+        Operation::Negation(operand) => {
+            "-".to_string()
+                + &parenthesize(
+                    translate_to_rust_expr(variables, operand),
+                    precedence(operand) <= NEGATION_PRECEDENCE || is_conversion(operand),
+                )
+        }
+        Operation::Arithmetic(..) => render_infix(lowered_expr, &|operand| {
+            translate_to_rust_expr(variables, operand)
+        }),
+        Operation::Power(base, exponent) => {
+            if lowered_expr.1 == Type::Int {
+                format!(
+                    "{}.pow({} as u32)",
+                    translate_to_rust_operand(variables, base),
+                    translate_to_rust_operand(variables, exponent)
+                )
+            } else {
+                format!(
+                    "{}.powf({})",
+                    translate_to_rust_operand(variables, base),
+                    translate_to_rust_expr(variables, exponent)
+                )
+            }
+        }
+        // Strings are concatenated by formatting them.
+        Operation::Concatenation(operands) => format!(
+            "format!(\"{}\", {})",
+            "{}".repeat(operands.len()),
+            translate_to_rust_list(variables, operands)
+        ),
+        Operation::FunctionCall(handle, arguments) => format!(
+            "fn_{}({})",
+            variables.get_function(*handle).name,
+            translate_to_rust_list(variables, arguments)
+        ),
+        Operation::BuiltinCall(builtin, arguments) => format!(
+            "{}.{}({})",
+            translate_to_rust_operand(variables, &arguments[0]),
+            builtin.name(),
+            translate_to_rust_list(variables, &arguments[1..])
+        ),
+    }
+}
+
+fn translate_to_rust_condition(variables: &SymbolTable, condition: &LoweredCondition) -> String {
+    let (left, operator, right) = condition;
+    format!(
+        "{} {} {}",
+        parenthesize(translate_to_rust_expr(variables, left), is_conversion(left)),
+        comparison_operator_symbol(*operator),
+        translate_to_rust_expr(variables, right)
+    )
+}
+
+// The value is computed before the index, and the index before borrowing the array,
+// as the index can contain elements of the same array.
+fn translate_to_rust_store(
+    variables: &SymbolTable,
+    handle: usize,
+    index: &AnalyzedExpr,
+    value: &str,
+) -> Result<String, String> {
+    Ok(format!(
+        "{{ let value = {}; let position = index({}, {}); _{}[position] = value; }}",
+        value,
+        translate_to_rust_expr(variables, &lower_expr(variables, index)?),
+        variables.array_size(handle).unwrap_or(0),
+        variables.get_name(handle)
+    ))
+}
+
+fn translate_to_rust_statement(
+    variables: &SymbolTable,
+    analyzed_statement: &AnalyzedStatement,
+    indentation: usize,
+) -> Result<String, String> {
+    Ok(match analyzed_statement {
+        AnalyzedStatement::Assignment(handle, expr) => format!(
+            "_{} = {};",
+            variables.get_name(*handle),
+            translate_to_rust_expr(
+                variables,
+                &lower_expr_to(variables, expr, variables.get_type(*handle))?
+            )
+        ),
+        AnalyzedStatement::ElementAssignment(handle, index, expr) => translate_to_rust_store(
+            variables,
+            *handle,
+            index,
+            &translate_to_rust_expr(
+                variables,
+                &lower_expr_to(variables, expr, variables.get_type(*handle))?,
+            ),
+        )?,
+        AnalyzedStatement::Declaration(handle) => {
+            let variable_type = variables.get_type(*handle);
+            let default_value = translate_to_rust_value(&variable_type.default_value());
+            match variables.array_size(*handle) {
+                Some(size) => format!(
+                    "let mut _{}: Vec<{}> = vec![{}; {}];",
+                    variables.get_name(*handle),
+                    rust_type(variable_type),
+                    default_value,
+                    size
+                ),
+                None => format!(
+                    "let mut _{}: {} = {};",
+                    variables.get_name(*handle),
+                    rust_type(variable_type),
+                    default_value
+                ),
+            }
+        }
+        AnalyzedStatement::InputOperation(handle) => {
+            format!("_{} = input();", variables.get_name(*handle))
+        }
+        AnalyzedStatement::ElementInput(handle, index) => {
+            translate_to_rust_store(variables, *handle, index, "input()")?
+        }
+        AnalyzedStatement::OutputOperation(exprs) => format!(
+            "println!(\"{}\", {});",
+            "{}".repeat(exprs.len()),
+            translate_to_rust_list(
+                variables,
+                &exprs
+                    .iter()
+                    .map(|expr| lower_expr(variables, expr))
+                    .collect::<Result<Vec<LoweredExpr>, String>>()?
+            )
+        ),
+        AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+            let mut result = format!(
+                "if {} {{\n{}",
+                translate_to_rust_condition(variables, &lower_condition(variables, condition)?),
+                translate_to_rust_block(variables, then_block, indentation + 1)?
+            );
+            if !else_block.is_empty() {
+                result += &"    ".repeat(indentation);
+                result += "} else {\n";
+                result += &translate_to_rust_block(variables, else_block, indentation + 1)?;
+            }
+            result += &"    ".repeat(indentation);
+            result += "}";
+            result
+        }
+        AnalyzedStatement::WhileLoop(condition, body) => format!(
+            "while {} {{\n{}{}}}",
+            translate_to_rust_condition(variables, &lower_condition(variables, condition)?),
+            translate_to_rust_block(variables, body, indentation + 1)?,
+            "    ".repeat(indentation)
+        ),
+        AnalyzedStatement::FunctionDefinition(handle) => {
+            translate_to_rust_function(variables, *handle)?
+        }
+        AnalyzedStatement::Return(handle, expr) => {
+            let return_type = variables.get_function(*handle).return_type;
+            format!(
+                "return {};",
+                translate_to_rust_expr(variables, &lower_expr_to(variables, expr, return_type)?)
+            )
+        }
+    })
+}
+
+// Functions that end without a return statement return the default value of their type.
+fn translate_to_rust_function(variables: &SymbolTable, handle: usize) -> Result<String, String> {
+    let function = variables.get_function(handle);
+    let parameters = function
+        .locals
+        .clone()
+        .take(function.parameter_count)
+        .map(|local| {
+            format!(
+                "mut _{}: {}",
+                variables.get_name(local),
+                rust_type(variables.get_type(local))
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let mut result = String::new();
+    result += "#[allow(unused_mut, unreachable_code)]\n";
+    result += &format!(
+        "fn fn_{}({}) -> {} {{\n",
+        function.name,
+        parameters,
+        rust_type(function.return_type)
+    );
+    result += &translate_to_rust_block(variables, &function.body, 1)?;
+    result += &format!(
+        "    {}\n",
+        translate_to_rust_value(&function.return_type.default_value())
+    );
+    result += "}\n";
+    Ok(result)
+}
+
+fn translate_to_rust_block(
+    variables: &SymbolTable,
+    analyzed_block: &AnalyzedProgram,
+    indentation: usize,
+) -> Result<String, String> {
+    let mut result = String::new();
+    for (statement, _) in analyzed_block {
+        result += &"    ".repeat(indentation);
+        result += &translate_to_rust_statement(variables, statement, indentation)?;
+        result += "\n";
+    }
+    Ok(result)
+}
+
+pub fn translate_to_rust_program(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Result<String, String> {
+    let mut rust_program = String::new();
+    rust_program += "use std::io::Write;\n";
+    rust_program += "\n";
+    rust_program += "#[allow(dead_code)]\n";
+    rust_program += "fn input<T: std::str::FromStr + Default>() -> T {\n";
+    rust_program += "    let mut text = String::new();\n";
+    rust_program += "    eprint!(\"? \");\n";
+    rust_program += "    std::io::stderr().flush().unwrap();\n";
+    rust_program += "    std::io::stdin()\n";
+    rust_program += "        .read_line(&mut text)\n";
+    rust_program += "        .expect(\"Cannot read line.\");\n";
+    rust_program += "    text.trim().parse::<T>().unwrap_or_default()\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    rust_program += "#[allow(dead_code)]\n";
+    rust_program += "fn index(index: i64, size: usize) -> usize {\n";
+    rust_program += "    if index < 0 || index as u64 >= size as u64 {\n";
+    rust_program += "        eprintln!(\n";
+    rust_program += "            \"Error: Index {} is out of bounds for an array of size {}.\",\n";
+    rust_program += "            index, size\n";
+    rust_program += "        );\n";
+    rust_program += "        std::process::exit(1);\n";
+    rust_program += "    }\n";
+    rust_program += "    index as usize\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    let mut main_body = String::new();
+    for (statement, _) in analyzed_program {
+        if let AnalyzedStatement::FunctionDefinition(handle) = statement {
+            rust_program += &translate_to_rust_function(variables, *handle)?;
+            rust_program += "\n";
+        } else {
+            main_body += "    ";
+            main_body += &translate_to_rust_statement(variables, statement, 1)?;
+            main_body += "\n";
+        }
+    }
+    rust_program += "fn main() {\n";
+    rust_program += &main_body;
+    rust_program += "}\n";
+    Ok(rust_program)
+}
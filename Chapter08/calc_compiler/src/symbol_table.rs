@@ -1,50 +1,255 @@
-#[derive(Debug)]
+use crate::analyzer::AnalyzedProgram;
+use crate::diagnostics::Diagnostic;
+use crate::value::{Type, Value};
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub struct FunctionEntry {
+    pub name: String,
+    pub parameter_count: usize,
+    pub return_type: Type,
+    pub locals: Range<usize>,
+    pub body: Rc<AnalyzedProgram>,
+}
+
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, String>;
+
+// A function implemented in Rust by the program embedding calc.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub parameter_types: Vec<Type>,
+    pub return_type: Type,
+    pub function: Rc<NativeFn>,
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SymbolTable {
-    entries: Vec<(String, f64)>,
+    entries: Vec<(String, Value)>,
+    scopes: Vec<Vec<usize>>,
+    frame_start: usize,
+    current_function: Option<usize>,
+    functions: Vec<FunctionEntry>,
+    natives: Vec<NativeFunction>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> SymbolTable {
+        SymbolTable::new()
+    }
 }
 
 impl SymbolTable {
     pub fn new() -> SymbolTable {
         SymbolTable {
-            entries: Vec::<(String, f64)>::new(),
+            entries: Vec::<(String, Value)>::new(),
+            scopes: vec![Vec::<usize>::new()],
+            frame_start: 0,
+            current_function: None,
+            functions: Vec::<FunctionEntry>::new(),
+            natives: Vec::<NativeFunction>::new(),
         }
     }
-    pub fn insert_symbol(&mut self, identifier: &str) -> Result<usize, String> {
-        if self
-            .entries
-            .iter()
-            .find(|item| item.0 == identifier)
-            .is_some()
-        {
-            Err(format!(
-                "Error: Identifier '{}' declared several times.",
-                identifier
-            ))
+    // The type of a variable is the type of its value, which is never changed.
+    pub fn insert_symbol<'a>(
+        &mut self,
+        identifier: &'a str,
+        symbol_type: Type,
+    ) -> Result<usize, Diagnostic<'a>> {
+        self.insert_value(identifier, symbol_type.default_value())
+    }
+    pub fn insert_array<'a>(
+        &mut self,
+        identifier: &'a str,
+        element_type: Type,
+        size: usize,
+    ) -> Result<usize, Diagnostic<'a>> {
+        self.insert_value(
+            identifier,
+            Value::Array(vec![element_type.default_value(); size]),
+        )
+    }
+    // The type of the variable is the type of its initial value.
+    pub fn insert_value<'a>(
+        &mut self,
+        identifier: &'a str,
+        value: Value,
+    ) -> Result<usize, Diagnostic<'a>> {
+        let entries = &self.entries;
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.iter().any(|&handle| entries[handle].0 == identifier) {
+            Err(Diagnostic::DuplicateDeclaration(identifier))
         } else {
-            self.entries.push((identifier.to_string(), 0.));
+            self.entries.push((identifier.to_string(), value));
+            scope.push(self.entries.len() - 1);
             Ok(self.entries.len() - 1)
         }
     }
-    pub fn find_symbol(&self, identifier: &str) -> Result<usize, String> {
-        if let Some(pos) = self.entries.iter().position(|item| item.0 == identifier) {
-            Ok(pos)
+    pub fn find_symbol<'a>(&self, identifier: &'a str) -> Result<usize, Diagnostic<'a>> {
+        for scope in self.scopes[self.frame_start..].iter().rev() {
+            if let Some(&handle) = scope
+                .iter()
+                .rev()
+                .find(|&&handle| self.entries[handle].0 == identifier)
+            {
+                return Ok(handle);
+            }
+        }
+        Err(Diagnostic::UndeclaredIdentifier(identifier))
+    }
+    pub fn open_scope(&mut self) {
+        self.scopes.push(Vec::<usize>::new());
+    }
+    pub fn close_scope(&mut self) {
+        self.scopes.pop();
+    }
+    pub fn current_function(&self) -> Option<usize> {
+        self.current_function
+    }
+    pub fn insert_function<'a>(
+        &mut self,
+        identifier: &'a str,
+        parameter_count: usize,
+        return_type: Type,
+    ) -> Result<usize, Diagnostic<'a>> {
+        if self.functions.iter().any(|item| item.name == identifier) {
+            Err(Diagnostic::DuplicateFunction(identifier))
         } else {
-            Err(format!(
-                "Error: Identifier '{}' used before having been declared.",
-                identifier
-            ))
+            self.functions.push(FunctionEntry {
+                name: identifier.to_string(),
+                parameter_count,
+                return_type,
+                locals: self.entries.len()..self.entries.len(),
+                body: Rc::new(AnalyzedProgram::new()),
+            });
+            Ok(self.functions.len() - 1)
         }
     }
-    pub fn get_value(&self, handle: usize) -> f64 {
-        self.entries[handle].1
+    pub fn find_function<'a>(
+        &self,
+        identifier: &'a str,
+        argument_count: usize,
+    ) -> Result<usize, Diagnostic<'a>> {
+        match self
+            .functions
+            .iter()
+            .position(|item| item.name == identifier)
+        {
+            Some(handle) if self.functions[handle].parameter_count == argument_count => Ok(handle),
+            Some(handle) => Err(Diagnostic::WrongArgumentCount(
+                identifier,
+                self.functions[handle].parameter_count,
+                argument_count,
+            )),
+            None => Err(Diagnostic::UndefinedFunction(identifier)),
+        }
+    }
+    pub fn insert_native<'a>(
+        &mut self,
+        identifier: &'a str,
+        parameter_types: Vec<Type>,
+        return_type: Type,
+        function: Rc<NativeFn>,
+    ) -> Result<usize, Diagnostic<'a>> {
+        if self.find_native(identifier).is_some() {
+            Err(Diagnostic::DuplicateFunction(identifier))
+        } else {
+            self.natives.push(NativeFunction {
+                name: identifier.to_string(),
+                parameter_types,
+                return_type,
+                function,
+            });
+            Ok(self.natives.len() - 1)
+        }
+    }
+    pub fn find_native(&self, identifier: &str) -> Option<usize> {
+        self.natives.iter().position(|item| item.name == identifier)
+    }
+    pub fn get_native(&self, handle: usize) -> &NativeFunction {
+        &self.natives[handle]
+    }
+    // Opens the scope of the parameters and of the local variables of a function.
+    // While it is open, the variables of the enclosing scopes are not visible.
+    pub fn open_function_scope(&mut self, handle: usize) -> usize {
+        let previous_frame_start = self.frame_start;
+        self.open_scope();
+        self.frame_start = self.scopes.len() - 1;
+        self.current_function = Some(handle);
+        previous_frame_start
+    }
+    pub fn close_function_scope(&mut self, previous_frame_start: usize) {
+        self.close_scope();
+        self.frame_start = previous_frame_start;
+        self.current_function = None;
+    }
+    pub fn set_function_body(&mut self, handle: usize, body: AnalyzedProgram) {
+        let function = &mut self.functions[handle];
+        function.locals.end = self.entries.len();
+        function.body = Rc::new(body);
+    }
+    // Used by the optimizer, which keeps the local variables.
+    pub fn replace_function_body(&mut self, handle: usize, body: AnalyzedProgram) {
+        self.functions[handle].body = Rc::new(body);
+    }
+    pub fn function_count(&self) -> usize {
+        self.functions.len()
     }
-    pub fn set_value(&mut self, handle: usize, value: f64) {
-        self.entries[handle].1 = value;
+    pub fn symbol_count(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn get_function(&self, handle: usize) -> &FunctionEntry {
+        &self.functions[handle]
+    }
+    pub fn get_type(&self, handle: usize) -> Type {
+        self.entries[handle].1.get_type()
+    }
+    pub fn array_size(&self, handle: usize) -> Option<usize> {
+        match &self.entries[handle].1 {
+            Value::Array(items) => Some(items.len()),
+            _ => None,
+        }
+    }
+    // The value of a variable when it is declared.
+    pub fn default_value(&self, handle: usize) -> Value {
+        let default_value = self.get_type(handle).default_value();
+        match self.array_size(handle) {
+            Some(size) => Value::Array(vec![default_value; size]),
+            None => default_value,
+        }
+    }
+    pub fn get_value(&self, handle: usize) -> Value {
+        self.entries[handle].1.clone()
+    }
+    // The value is converted to the type of the variable.
+    pub fn set_value(&mut self, handle: usize, value: Value) {
+        let symbol_type = self.get_type(handle);
+        self.entries[handle].1 = value.convert_to(symbol_type);
+    }
+    pub fn get_element(&self, handle: usize, index: &Value) -> Result<Value, String> {
+        self.entries[handle].1.get_element(index)
+    }
+    pub fn set_element(
+        &mut self,
+        handle: usize,
+        index: &Value,
+        value: Value,
+    ) -> Result<(), String> {
+        self.entries[handle].1.set_element(index, value)
     }
     pub fn get_name(&self, handle: usize) -> String {
         self.entries[handle].0.clone()
     }
-    pub fn iter(&self) -> std::slice::Iter<(String, f64)> {
-        self.entries.iter()
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Value)> {
+        self.scopes[0]
+            .iter()
+            .map(move |&handle| &self.entries[handle])
     }
 }
@@ -0,0 +1,253 @@
+use crate::parser::{ComparisonOperator, ExprOperator, TermOperator};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+
+impl Type {
+    pub fn is_numeric(self) -> bool {
+        self == Type::Int || self == Type::Float
+    }
+    // Integers are promoted to floats when mixed with them.
+    pub fn numeric_result(self, other: Type) -> Type {
+        if self == Type::Int && other == Type::Int {
+            Type::Int
+        } else {
+            Type::Float
+        }
+    }
+    // Divisions give floats even between integers, so that 1 / 3 is not 0.
+    pub fn term_result(self, operator: TermOperator, other: Type) -> Type {
+        if operator == TermOperator::Divide {
+            Type::Float
+        } else {
+            self.numeric_result(other)
+        }
+    }
+    // Whether a value of the given type can be stored in a variable of this type.
+    pub fn accepts(self, found: Type) -> bool {
+        self == found || (self == Type::Float && found == Type::Int)
+    }
+    pub fn default_value(self) -> Value {
+        match self {
+            Type::Int => Value::Int(0),
+            Type::Float => Value::Float(0.),
+            Type::Bool => Value::Bool(false),
+            Type::Str => Value::Str(String::new()),
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Str => "string",
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    // Arrays have a fixed size, and they are never empty.
+    Array(Vec<Value>),
+}
+
+impl Value {
+    // The type of an array is the type of its elements.
+    pub fn get_type(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::Str(_) => Type::Str,
+            Value::Array(items) => items[0].get_type(),
+        }
+    }
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Value::Int(value) => *value as f64,
+            Value::Float(value) => *value,
+            _ => 0.,
+        }
+    }
+    pub fn convert_to(self, target: Type) -> Value {
+        match self {
+            Value::Int(value) if target == Type::Float => Value::Float(value as f64),
+            value => value,
+        }
+    }
+    // Parses a line typed by the user, using the default value if it is invalid.
+    pub fn parse(text: &str, target: Type) -> Value {
+        let text = text.trim();
+        match target {
+            Type::Int => Value::Int(text.parse::<i64>().unwrap_or(0)),
+            Type::Float => Value::Float(text.parse::<f64>().unwrap_or(0.)),
+            Type::Bool => Value::Bool(text.parse::<bool>().unwrap_or(false)),
+            Type::Str => Value::Str(text.to_string()),
+        }
+    }
+    pub fn get_element(&self, index: &Value) -> Result<Value, String> {
+        match self {
+            Value::Array(items) => Ok(items[element_position(items, index)?].clone()),
+            _ => Err(not_an_array()),
+        }
+    }
+    // The value is converted to the type of the elements.
+    pub fn set_element(&mut self, index: &Value, value: Value) -> Result<(), String> {
+        match self {
+            Value::Array(items) => {
+                let position = element_position(items, index)?;
+                let element_type = items[position].get_type();
+                items[position] = value.convert_to(element_type);
+                Ok(())
+            }
+            _ => Err(not_an_array()),
+        }
+    }
+    pub fn negate(self) -> Result<Value, String> {
+        match self {
+            Value::Int(value) => value.checked_neg().map(Value::Int).ok_or_else(overflow),
+            value => Ok(Value::Float(-value.as_float())),
+        }
+    }
+    pub fn power(self, exponent: Value) -> Result<Value, String> {
+        match (self, exponent) {
+            (Value::Int(base), Value::Int(exponent)) => {
+                if exponent < 0 {
+                    return Err("Error: Negative exponent of an integer.".to_string());
+                }
+                if exponent > u32::MAX as i64 {
+                    return Err(overflow());
+                }
+                base.checked_pow(exponent as u32)
+                    .map(Value::Int)
+                    .ok_or_else(overflow)
+            }
+            (base, exponent) => Ok(Value::Float(base.as_float().powf(exponent.as_float()))),
+        }
+    }
+    pub fn apply_term_operator(
+        self,
+        operator: TermOperator,
+        right: Value,
+    ) -> Result<Value, String> {
+        match (self, right) {
+            (Value::Int(left), Value::Int(right)) => match operator {
+                TermOperator::Multiply => left.checked_mul(right).ok_or_else(overflow),
+                TermOperator::Divide => return Ok(Value::Float(left as f64 / right as f64)),
+                TermOperator::Remainder if right == 0 => {
+                    Err("Error: Division by zero.".to_string())
+                }
+                TermOperator::Remainder => left.checked_rem(right).ok_or_else(overflow),
+            }
+            .map(Value::Int),
+            (left, right) => {
+                let (left, right) = (left.as_float(), right.as_float());
+                Ok(Value::Float(match operator {
+                    TermOperator::Multiply => left * right,
+                    TermOperator::Divide => left / right,
+                    TermOperator::Remainder => left % right,
+                }))
+            }
+        }
+    }
+    pub fn apply_expr_operator(
+        self,
+        operator: ExprOperator,
+        right: Value,
+    ) -> Result<Value, String> {
+        match (self, right) {
+            (Value::Str(left), Value::Str(right)) => Ok(Value::Str(left + &right)),
+            (Value::Int(left), Value::Int(right)) => match operator {
+                ExprOperator::Add => left.checked_add(right),
+                ExprOperator::Subtract => left.checked_sub(right),
+            }
+            .map(Value::Int)
+            .ok_or_else(overflow),
+            (left, right) => {
+                let (left, right) = (left.as_float(), right.as_float());
+                Ok(Value::Float(match operator {
+                    ExprOperator::Add => left + right,
+                    ExprOperator::Subtract => left - right,
+                }))
+            }
+        }
+    }
+    pub fn compare(&self, operator: ComparisonOperator, right: &Value) -> bool {
+        let ordering = match (self, right) {
+            (Value::Int(left), Value::Int(right)) => left.partial_cmp(right),
+            (Value::Bool(left), Value::Bool(right)) => left.partial_cmp(right),
+            (Value::Str(left), Value::Str(right)) => left.partial_cmp(right),
+            (left, right) => left.as_float().partial_cmp(&right.as_float()),
+        };
+        use std::cmp::Ordering::*;
+        match operator {
+            ComparisonOperator::Equal => ordering == Some(Equal),
+            ComparisonOperator::NotEqual => ordering != Some(Equal),
+            ComparisonOperator::Less => ordering == Some(Less),
+            ComparisonOperator::LessOrEqual => ordering == Some(Less) || ordering == Some(Equal),
+            ComparisonOperator::Greater => ordering == Some(Greater),
+            ComparisonOperator::GreaterOrEqual => {
+                ordering == Some(Greater) || ordering == Some(Equal)
+            }
+        }
+    }
+}
+
+// Arrays are indexed from zero, and every access is checked.
+fn element_position(items: &[Value], index: &Value) -> Result<usize, String> {
+    match index {
+        Value::Int(index) if *index >= 0 && (*index as u64) < items.len() as u64 => {
+            Ok(*index as usize)
+        }
+        Value::Int(index) => Err(format!(
+            "Error: Index {} is out of bounds for an array of size {}.",
+            index,
+            items.len()
+        )),
+        _ => Err("Error: Invalid array index.".to_string()),
+    }
+}
+
+fn not_an_array() -> String {
+    "Error: Indexed value is not an array.".to_string()
+}
+
+fn overflow() -> String {
+    "Error: Integer overflow.".to_string()
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (position, item) in items.iter().enumerate() {
+                    if position > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
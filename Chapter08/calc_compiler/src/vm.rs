@@ -0,0 +1,216 @@
+use crate::bytecode::{self, BytecodeProgram, Instruction};
+use crate::executor::MAX_CALL_DEPTH;
+use crate::parser::{ComparisonOperator, ExprOperator, TermOperator};
+use crate::program_io::ProgramIo;
+use crate::value::Value;
+
+struct Frame {
+    return_address: usize,
+    function: usize,
+    saved_values: Vec<Value>,
+}
+
+struct Machine<'a> {
+    program: &'a BytecodeProgram,
+    memory: Vec<Value>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    ip: usize,
+}
+
+impl<'a> Machine<'a> {
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| format!("Error: Stack underflow at {}.", self.ip - 1))
+    }
+    fn check_address(&self, address: u32) -> Result<usize, String> {
+        if (address as usize) < self.memory.len() {
+            Ok(address as usize)
+        } else {
+            Err(format!(
+                "Error: Invalid variable {} at {}.",
+                address,
+                self.ip - 1
+            ))
+        }
+    }
+    fn term_operation(&mut self, operator: TermOperator) -> Result<(), String> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.stack.push(left.apply_term_operator(operator, right)?);
+        Ok(())
+    }
+    fn expr_operation(&mut self, operator: ExprOperator) -> Result<(), String> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.stack.push(left.apply_expr_operator(operator, right)?);
+        Ok(())
+    }
+    fn comparison(&mut self, operator: ComparisonOperator) -> Result<(), String> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.stack.push(Value::Bool(left.compare(operator, &right)));
+        Ok(())
+    }
+    fn pop_values(&mut self, count: usize) -> Result<Vec<Value>, String> {
+        if count > self.stack.len() {
+            return Err(format!("Error: Stack underflow at {}.", self.ip - 1));
+        }
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+    fn call(&mut self, function_index: u32) -> Result<(), String> {
+        let function = *self
+            .program
+            .functions
+            .get(function_index as usize)
+            .ok_or_else(|| format!("Error: Invalid function {}.", function_index))?;
+        let locals = function.locals_start as usize..function.locals_end as usize;
+        let parameter_count = function.parameter_count as usize;
+        if locals.end > self.memory.len()
+            || parameter_count > locals.len()
+            || parameter_count > self.stack.len()
+        {
+            return Err(format!("Error: Invalid call at {}.", self.ip - 1));
+        }
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(format!("Error: Too many nested calls at {}.", self.ip - 1));
+        }
+        let arguments = self.stack.split_off(self.stack.len() - parameter_count);
+        let saved_values = self.memory[locals.clone()].to_vec();
+        for (local, argument) in locals.clone().zip(arguments) {
+            self.memory[local] = argument;
+        }
+        self.frames.push(Frame {
+            return_address: self.ip,
+            function: function_index as usize,
+            saved_values,
+        });
+        self.ip = function.address as usize;
+        Ok(())
+    }
+    fn return_from_call(&mut self) -> Result<(), String> {
+        let value = self.pop()?;
+        let frame = self
+            .frames
+            .pop()
+            .ok_or_else(|| format!("Error: Return outside of a function at {}.", self.ip - 1))?;
+        let function = self.program.functions[frame.function];
+        let locals_start = function.locals_start as usize;
+        for (offset, value) in frame.saved_values.into_iter().enumerate() {
+            self.memory[locals_start + offset] = value;
+        }
+        self.ip = frame.return_address;
+        self.stack.push(value);
+        Ok(())
+    }
+}
+
+pub fn execute_bytecode(program: &BytecodeProgram, io: &mut dyn ProgramIo) -> Result<(), String> {
+    use Instruction::*;
+    bytecode::check_variables(program)?;
+    let mut m = Machine {
+        program,
+        memory: vec![Value::Float(0.); program.variable_count as usize],
+        stack: Vec::<Value>::new(),
+        frames: Vec::<Frame>::new(),
+        ip: 0,
+    };
+    loop {
+        let instruction = *program
+            .code
+            .get(m.ip)
+            .ok_or_else(|| format!("Error: Jump out of the code to {}.", m.ip))?;
+        m.ip += 1;
+        match instruction {
+            Halt => return Ok(()),
+            Push(value) => m.stack.push(Value::Float(value)),
+            Load(address) => {
+                let address = m.check_address(address)?;
+                m.stack.push(m.memory[address].clone());
+            }
+            Store(address) => {
+                let address = m.check_address(address)?;
+                m.memory[address] = m.pop()?;
+            }
+            Add => m.expr_operation(ExprOperator::Add)?,
+            Subtract => m.expr_operation(ExprOperator::Subtract)?,
+            Multiply => m.term_operation(TermOperator::Multiply)?,
+            Divide => m.term_operation(TermOperator::Divide)?,
+            Equal => m.comparison(ComparisonOperator::Equal)?,
+            NotEqual => m.comparison(ComparisonOperator::NotEqual)?,
+            Less => m.comparison(ComparisonOperator::Less)?,
+            LessOrEqual => m.comparison(ComparisonOperator::LessOrEqual)?,
+            Greater => m.comparison(ComparisonOperator::Greater)?,
+            GreaterOrEqual => m.comparison(ComparisonOperator::GreaterOrEqual)?,
+            Jump(target) => m.ip = target as usize,
+            JumpIfFalse(target) => {
+                if m.pop()? == Value::Bool(false) {
+                    m.ip = target as usize;
+                }
+            }
+            Input(address, value_type) => {
+                let address = m.check_address(address)?;
+                m.memory[address] = Value::parse(&io.input_line()?, value_type);
+            }
+            Output(count) => {
+                let mut text = String::new();
+                for value in m.pop_values(count as usize)? {
+                    text += &value.to_string();
+                }
+                io.output_line(&text)?;
+            }
+            Call(function_index) => m.call(function_index)?,
+            Return => m.return_from_call()?,
+            Remainder => m.term_operation(TermOperator::Remainder)?,
+            Power => {
+                let exponent = m.pop()?;
+                let base = m.pop()?;
+                m.stack.push(base.power(exponent)?);
+            }
+            Negate => {
+                let value = m.pop()?;
+                m.stack.push(value.negate()?);
+            }
+            CallBuiltin(builtin) => {
+                let arguments = m.pop_values(builtin.parameter_count())?;
+                m.stack.push(builtin.apply(&arguments)?);
+            }
+            PushInt(value) => m.stack.push(Value::Int(value)),
+            PushBool(value) => m.stack.push(Value::Bool(value)),
+            PushString(index) => {
+                let string = program
+                    .strings
+                    .get(index as usize)
+                    .ok_or_else(|| format!("Error: Invalid string {} at {}.", index, m.ip - 1))?;
+                m.stack.push(Value::Str(string.clone()));
+            }
+            ToFloat => {
+                let value = m.pop()?;
+                m.stack.push(Value::Float(value.as_float()));
+            }
+            NewArray(size, value_type) => {
+                if size == 0 {
+                    return Err(format!("Error: Empty array at {}.", m.ip - 1));
+                }
+                m.stack.push(Value::Array(vec![
+                    value_type.default_value();
+                    size as usize
+                ]));
+            }
+            LoadElement(address) => {
+                let address = m.check_address(address)?;
+                let index = m.pop()?;
+                let element = m.memory[address].get_element(&index)?;
+                m.stack.push(element);
+            }
+            StoreElement(address) => {
+                let address = m.check_address(address)?;
+                let index = m.pop()?;
+                let value = m.pop()?;
+                m.memory[address].set_element(&index, value)?;
+            }
+            PushInput(value_type) => m.stack.push(Value::parse(&io.input_line()?, value_type)),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Runs a calc program compiled to WebAssembly, given the path of its binary module.
+// The input lines are read from stdin, and the output is written to stdout
+// in the same format used by the other backends.
+const fs = require("fs");
+
+const lines = fs.readFileSync(0, "utf8").split("\n");
+let nextLine = 0;
+let output = "";
+let memory;
+
+function inputLine() {
+    process.stderr.write("? ");
+    const line = nextLine < lines.length ? lines[nextLine++] : "";
+    return line.trim();
+}
+
+// Like Rust, prints the fewest digits that read back as the same value,
+// and never uses an exponent.
+function formatFloat(value) {
+    if (Number.isNaN(value)) {
+        return "NaN";
+    }
+    if (!Number.isFinite(value)) {
+        return value < 0 ? "-inf" : "inf";
+    }
+    const sign = value < 0 || Object.is(value, -0) ? "-" : "";
+    const [mantissa, exponentText] = Math.abs(value).toExponential().split("e");
+    const digits = mantissa.replace(".", "");
+    const exponent = Number(exponentText);
+    if (exponent < 0) {
+        return sign + "0." + "0".repeat(-exponent - 1) + digits;
+    }
+    if (exponent + 1 >= digits.length) {
+        return sign + digits + "0".repeat(exponent + 1 - digits.length);
+    }
+    return sign + digits.slice(0, exponent + 1) + "." + digits.slice(exponent + 1);
+}
+
+const imports = {
+    calc: {
+        input_int: () => {
+            const text = inputLine();
+            if (!/^[+-]?\d+$/.test(text)) {
+                return 0n;
+            }
+            const value = BigInt(text);
+            return BigInt.asIntN(64, value) === value ? value : 0n;
+        },
+        input_float: () => {
+            const text = inputLine();
+            return /^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$/.test(text) ? Number(text) : 0;
+        },
+        input_bool: () => (inputLine() === "true" ? 1 : 0),
+        print_int: (value) => {
+            output += value.toString();
+        },
+        print_float: (value) => {
+            output += formatFloat(value);
+        },
+        print_bool: (value) => {
+            output += value ? "true" : "false";
+        },
+        print_text: (offset, length) => {
+            output += Buffer.from(memory.buffer, offset, length).toString("utf8");
+        },
+        print_newline: () => {
+            output += "\n";
+        },
+        sin: Math.sin,
+        pow: Math.pow,
+        fmod: (left, right) => left % right,
+    },
+};
+
+const wasmModule = new WebAssembly.Module(fs.readFileSync(process.argv[2]));
+const instance = new WebAssembly.Instance(wasmModule, imports);
+memory = instance.exports.memory;
+try {
+    instance.exports.main();
+} finally {
+    process.stdout.write(output);
+}
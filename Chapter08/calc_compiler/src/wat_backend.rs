@@ -0,0 +1,443 @@
+use crate::analyzer::{AnalyzedProgram, AnalyzedStatement, BuiltinFunction};
+use crate::compiler::{
+    lower_condition, lower_expr, lower_expr_to, ArithmeticOperator, Backend, LoweredCondition,
+    LoweredExpr, Operation,
+};
+use crate::parser::ComparisonOperator;
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+pub struct WatBackend;
+
+impl Backend for WatBackend {
+    fn file_suffix(&self) -> &'static str {
+        ".wat"
+    }
+    fn translate_program(
+        &self,
+        variables: &SymbolTable,
+        analyzed_program: &AnalyzedProgram,
+    ) -> Result<String, String> {
+        translate_to_wat_program(variables, analyzed_program)
+    }
+}
+
+// The functions imported from the host, and the ones used by the generated code.
+const IMPORTS: &str = r#"  (import "calc" "input_int" (func $input_int (result i64)))
+  (import "calc" "input_float" (func $input_float (result f64)))
+  (import "calc" "input_bool" (func $input_bool (result i32)))
+  (import "calc" "print_int" (func $print_int (param i64)))
+  (import "calc" "print_float" (func $print_float (param f64)))
+  (import "calc" "print_bool" (func $print_bool (param i32)))
+  (import "calc" "print_text" (func $print_text (param i32 i32)))
+  (import "calc" "print_newline" (func $print_newline))
+  (import "calc" "sin" (func $sin (param f64) (result f64)))
+  (import "calc" "pow" (func $pow (param f64 f64) (result f64)))
+  (import "calc" "fmod" (func $fmod (param f64 f64) (result f64)))
+"#;
+
+const RUNTIME: &str = r#"  (func $pow_int (param $base i64) (param $exponent i64) (result i64)
+    (local $result i64)
+    (local.set $result (i64.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i64.le_s (local.get $exponent) (i64.const 0)))
+        (local.set $result (i64.mul (local.get $result) (local.get $base)))
+        (local.set $exponent (i64.sub (local.get $exponent) (i64.const 1)))
+        (br $next)))
+    (local.get $result))
+  (func $abs_int (param $value i64) (result i64)
+    (select
+      (i64.sub (i64.const 0) (local.get $value))
+      (local.get $value)
+      (i64.lt_s (local.get $value) (i64.const 0))))
+  (func $min_int (param $first i64) (param $second i64) (result i64)
+    (select
+      (local.get $first)
+      (local.get $second)
+      (i64.lt_s (local.get $first) (local.get $second))))
+  (func $max_int (param $first i64) (param $second i64) (result i64)
+    (select
+      (local.get $first)
+      (local.get $second)
+      (i64.gt_s (local.get $first) (local.get $second))))
+"#;
+
+const PAGE_SIZE: usize = 65536;
+
+fn wat_type(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "i64",
+        Type::Float => "f64",
+        Type::Bool | Type::Str => "i32",
+    }
+}
+
+// The name of the imported function handling values of the given type.
+fn host_suffix(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "int",
+        Type::Float => "float",
+        _ => "bool",
+    }
+}
+
+// Variables are named after their handle too, as the nested blocks
+// of a function share its list of locals.
+fn local_name(variables: &SymbolTable, handle: usize) -> String {
+    format!("${}_{}", variables.get_name(handle), handle)
+}
+
+fn translate_to_wat_value(value: &Value) -> Result<String, String> {
+    Ok(match value {
+        Value::Int(value) => format!("(i64.const {})", value),
+        Value::Float(value) if value.is_nan() => "(f64.const nan)".to_string(),
+        Value::Float(value) => format!("(f64.const {:?})", value),
+        Value::Bool(value) => format!("(i32.const {})", *value as i32),
+        Value::Str(_) => return Err(string_error()),
+        Value::Array(_) => return Err(array_error()),
+    })
+}
+
+fn string_error() -> String {
+    "Error: WebAssembly supports strings only as output items.".to_string()
+}
+
+fn array_error() -> String {
+    "Error: WebAssembly does not support arrays.".to_string()
+}
+
+struct WatTranslator<'a> {
+    variables: &'a SymbolTable,
+    // The text of the string literals, which is stored in the memory of the module.
+    texts: Vec<u8>,
+    loop_count: usize,
+}
+
+impl<'a> WatTranslator<'a> {
+    fn translate_list(&self, lowered_exprs: &[LoweredExpr]) -> Result<String, String> {
+        let mut result = String::new();
+        for lowered_expr in lowered_exprs {
+            result += " ";
+            result += &self.translate_expr(lowered_expr)?;
+        }
+        Ok(result)
+    }
+
+    fn translate_expr(&self, lowered_expr: &LoweredExpr) -> Result<String, String> {
+        let value_type = wat_type(lowered_expr.1);
+        Ok(match &lowered_expr.0 {
+            Operation::Literal(value) => translate_to_wat_value(value)?,
+            Operation::Variable(handle) => {
+                format!("(local.get {})", local_name(self.variables, *handle))
+            }
+            Operation::Element(..) => return Err(array_error()),
+            Operation::ToFloat(operand) => {
+                format!("(f64.convert_i64_s {})", self.translate_expr(operand)?)
+            }
+            Operation::Negation(operand) if lowered_expr.1 == Type::Int => {
+                format!("(i64.sub (i64.const 0) {})", self.translate_expr(operand)?)
+            }
+            Operation::Negation(operand) => format!("(f64.neg {})", self.translate_expr(operand)?),
+            Operation::Arithmetic(operator, left, right) => {
+                let instruction = match (operator, lowered_expr.1) {
+                    (ArithmeticOperator::Add, _) => "add",
+                    (ArithmeticOperator::Subtract, _) => "sub",
+                    (ArithmeticOperator::Multiply, _) => "mul",
+                    (ArithmeticOperator::Divide, _) => "div",
+                    (ArithmeticOperator::Remainder, Type::Int) => "rem_s",
+                    // WebAssembly has no instruction for the remainder of floats.
+                    (ArithmeticOperator::Remainder, _) => {
+                        return Ok(format!(
+                            "(call $fmod {} {})",
+                            self.translate_expr(left)?,
+                            self.translate_expr(right)?
+                        ))
+                    }
+                };
+                format!(
+                    "({}.{} {} {})",
+                    value_type,
+                    instruction,
+                    self.translate_expr(left)?,
+                    self.translate_expr(right)?
+                )
+            }
+            Operation::Power(base, exponent) => format!(
+                "(call {} {} {})",
+                if lowered_expr.1 == Type::Int {
+                    "$pow_int"
+                } else {
+                    "$pow"
+                },
+                self.translate_expr(base)?,
+                self.translate_expr(exponent)?
+            ),
+            Operation::Concatenation(_) => return Err(string_error()),
+            Operation::FunctionCall(handle, arguments) => format!(
+                "(call $fn_{}{})",
+                self.variables.get_function(*handle).name,
+                self.translate_list(arguments)?
+            ),
+            Operation::BuiltinCall(builtin, arguments) => {
+                let function = match (builtin, lowered_expr.1) {
+                    (BuiltinFunction::Sqrt, _) => "f64.sqrt",
+                    (BuiltinFunction::Sin, _) => "call $sin",
+                    (BuiltinFunction::Abs, Type::Int) => "call $abs_int",
+                    (BuiltinFunction::Abs, _) => "f64.abs",
+                    (BuiltinFunction::Min, Type::Int) => "call $min_int",
+                    (BuiltinFunction::Min, _) => "f64.min",
+                    (BuiltinFunction::Max, Type::Int) => "call $max_int",
+                    (BuiltinFunction::Max, _) => "f64.max",
+                };
+                format!("({}{})", function, self.translate_list(arguments)?)
+            }
+        })
+    }
+
+    fn translate_condition(&self, condition: &LoweredCondition) -> Result<String, String> {
+        let (left, operator, right) = condition;
+        let signed = if left.1 == Type::Float { "" } else { "_s" };
+        let instruction = match operator {
+            ComparisonOperator::Equal => "eq".to_string(),
+            ComparisonOperator::NotEqual => "ne".to_string(),
+            ComparisonOperator::Less => "lt".to_string() + signed,
+            ComparisonOperator::LessOrEqual => "le".to_string() + signed,
+            ComparisonOperator::Greater => "gt".to_string() + signed,
+            ComparisonOperator::GreaterOrEqual => "ge".to_string() + signed,
+        };
+        Ok(format!(
+            "({}.{} {} {})",
+            wat_type(left.1),
+            instruction,
+            self.translate_expr(left)?,
+            self.translate_expr(right)?
+        ))
+    }
+
+    // String literals are printed from the memory of the module.
+    fn translate_output_item(&mut self, lowered_expr: &LoweredExpr) -> Result<String, String> {
+        if let (Operation::Literal(Value::Str(text)), _) = lowered_expr {
+            let offset = self.texts.len();
+            self.texts.extend_from_slice(text.as_bytes());
+            Ok(format!(
+                "(call $print_text (i32.const {}) (i32.const {}))",
+                offset,
+                text.len()
+            ))
+        } else {
+            Ok(format!(
+                "(call $print_{} {})",
+                host_suffix(lowered_expr.1),
+                self.translate_expr(lowered_expr)?
+            ))
+        }
+    }
+
+    fn translate_statement(
+        &mut self,
+        analyzed_statement: &AnalyzedStatement,
+        indentation: usize,
+    ) -> Result<String, String> {
+        let variables = self.variables;
+        let padding = "  ".repeat(indentation);
+        Ok(match analyzed_statement {
+            AnalyzedStatement::Assignment(handle, expr) => format!(
+                "{}(local.set {} {})\n",
+                padding,
+                local_name(variables, *handle),
+                self.translate_expr(&lower_expr_to(
+                    variables,
+                    expr,
+                    variables.get_type(*handle)
+                )?)?
+            ),
+            AnalyzedStatement::Declaration(handle) => format!(
+                "{}(local.set {} {})\n",
+                padding,
+                local_name(variables, *handle),
+                translate_to_wat_value(&variables.get_type(*handle).default_value())?
+            ),
+            AnalyzedStatement::InputOperation(handle) => format!(
+                "{}(local.set {} (call $input_{}))\n",
+                padding,
+                local_name(variables, *handle),
+                host_suffix(variables.get_type(*handle))
+            ),
+            AnalyzedStatement::ElementAssignment(..) | AnalyzedStatement::ElementInput(..) => {
+                return Err(array_error())
+            }
+            AnalyzedStatement::OutputOperation(exprs) => {
+                let mut result = String::new();
+                for expr in exprs {
+                    result += &padding;
+                    result += &self.translate_output_item(&lower_expr(variables, expr)?)?;
+                    result += "\n";
+                }
+                result + &padding + "(call $print_newline)\n"
+            }
+            AnalyzedStatement::IfElse(condition, then_block, else_block) => {
+                let mut result = format!(
+                    "{}(if {}\n{}  (then\n",
+                    padding,
+                    self.translate_condition(&lower_condition(variables, condition)?)?,
+                    padding
+                );
+                result += &self.translate_block(then_block, indentation + 2)?;
+                result += &padding;
+                result += "  )\n";
+                if !else_block.is_empty() {
+                    result += &padding;
+                    result += "  (else\n";
+                    result += &self.translate_block(else_block, indentation + 2)?;
+                    result += &padding;
+                    result += "  )\n";
+                }
+                result + &padding + ")\n"
+            }
+            // The loop is left by branching to the end of the enclosing block.
+            AnalyzedStatement::WhileLoop(condition, body) => {
+                self.loop_count += 1;
+                let label = self.loop_count;
+                let mut result = format!(
+                    "{}(block $while_end_{}\n{}  (loop $while_{}\n{}    (br_if $while_end_{} (i32.eqz {}))\n",
+                    padding,
+                    label,
+                    padding,
+                    label,
+                    padding,
+                    label,
+                    self.translate_condition(&lower_condition(variables, condition)?)?
+                );
+                result += &self.translate_block(body, indentation + 2)?;
+                result + &format!("{}    (br $while_{})))\n", padding, label)
+            }
+            AnalyzedStatement::FunctionDefinition(_) => String::new(),
+            AnalyzedStatement::Return(handle, expr) => {
+                let return_type = variables.get_function(*handle).return_type;
+                format!(
+                    "{}(return {})\n",
+                    padding,
+                    self.translate_expr(&lower_expr_to(variables, expr, return_type)?)?
+                )
+            }
+        })
+    }
+
+    fn translate_block(
+        &mut self,
+        analyzed_block: &AnalyzedProgram,
+        indentation: usize,
+    ) -> Result<String, String> {
+        let mut result = String::new();
+        for (statement, _) in analyzed_block {
+            result += &self.translate_statement(statement, indentation)?;
+        }
+        Ok(result)
+    }
+
+    fn translate_locals(&self, handles: impl Iterator<Item = usize>) -> String {
+        let mut result = String::new();
+        for handle in handles {
+            result += &format!(
+                "    (local {} {})\n",
+                local_name(self.variables, handle),
+                wat_type(self.variables.get_type(handle))
+            );
+        }
+        result
+    }
+
+    // Functions that end without a return statement return the default value of their type.
+    fn translate_function(&mut self, handle: usize) -> Result<String, String> {
+        let variables = self.variables;
+        let function = variables.get_function(handle);
+        let mut result = format!("  (func $fn_{}", function.name);
+        for parameter in function.locals.clone().take(function.parameter_count) {
+            result += &format!(
+                " (param {} {})",
+                local_name(variables, parameter),
+                wat_type(variables.get_type(parameter))
+            );
+        }
+        result += &format!(" (result {})\n", wat_type(function.return_type));
+        result += &self.translate_locals(function.locals.clone().skip(function.parameter_count));
+        result += &self.translate_block(&function.body, 2)?;
+        result += &format!(
+            "    {})\n",
+            translate_to_wat_value(&function.return_type.default_value())?
+        );
+        Ok(result)
+    }
+}
+
+// Escapes all the bytes that are not printable characters.
+fn translate_to_wat_string(bytes: &[u8]) -> String {
+    let mut result = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b' '..=b'~' if *byte != b'"' && *byte != b'\\' => result.push(*byte as char),
+            _ => result += &format!("\\{:02x}", byte),
+        }
+    }
+    result + "\""
+}
+
+// The module exports its memory and the "main" function, which runs the program.
+// Strings can be printed, but not stored in variables.
+pub fn translate_to_wat_program(
+    variables: &SymbolTable,
+    analyzed_program: &AnalyzedProgram,
+) -> Result<String, String> {
+    let has_strings = (0..variables.symbol_count())
+        .any(|handle| variables.get_type(handle) == Type::Str)
+        || (0..variables.function_count())
+            .any(|handle| variables.get_function(handle).return_type == Type::Str);
+    if has_strings {
+        return Err("Error: WebAssembly does not support string variables.".to_string());
+    }
+    if (0..variables.symbol_count()).any(|handle| variables.array_size(handle).is_some()) {
+        return Err(array_error());
+    }
+    let mut translator = WatTranslator {
+        variables,
+        texts: Vec::<u8>::new(),
+        loop_count: 0,
+    };
+    let mut functions = String::new();
+    let mut main_body = String::new();
+    for (statement, _) in analyzed_program {
+        if let AnalyzedStatement::FunctionDefinition(handle) = statement {
+            functions += &translator.translate_function(*handle)?;
+        } else {
+            main_body += &translator.translate_statement(statement, 2)?;
+        }
+    }
+    // The variables of the main program are the ones not belonging to any function.
+    let function_locals: Vec<_> = (0..variables.function_count())
+        .map(|handle| variables.get_function(handle).locals.clone())
+        .collect();
+    let main_locals = (0..variables.symbol_count())
+        .filter(|handle| !function_locals.iter().any(|locals| locals.contains(handle)));
+
+    let mut wat_program = String::from("(module\n");
+    wat_program += IMPORTS;
+    wat_program += &format!(
+        "  (memory (export \"memory\") {})\n",
+        translator.texts.len().div_ceil(PAGE_SIZE).max(1)
+    );
+    if !translator.texts.is_empty() {
+        wat_program += &format!(
+            "  (data (i32.const 0) {})\n",
+            translate_to_wat_string(&translator.texts)
+        );
+    }
+    wat_program += RUNTIME;
+    wat_program += &functions;
+    wat_program += "  (func $main (export \"main\")\n";
+    wat_program += &translator.translate_locals(main_locals);
+    wat_program += &main_body;
+    wat_program += "  )\n";
+    wat_program += ")\n";
+    Ok(wat_program)
+}
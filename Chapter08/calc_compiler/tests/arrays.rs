@@ -0,0 +1,113 @@
+mod common;
+
+use common::{calc_compiler, is_available, run_on_source, TempDir};
+use std::process::{Command, Output, Stdio};
+
+const OUT_OF_BOUNDS: &str =
+    "@a[3]: int\n@i: int\ni := 1\na[i] := 5\n<a[i]\ni := i + 2\na[i] := 6\n<\"unreachable\"\n";
+const OUT_OF_BOUNDS_ERROR: &str = "Error: Index 3 is out of bounds for an array of size 3.";
+
+fn check_out_of_bounds(backend: &str, output: Output) {
+    assert!(!output.status.success(), "{}", backend);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "5\n",
+        "{}",
+        backend
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains(OUT_OF_BOUNDS_ERROR),
+        "{}: {}",
+        backend,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn analysis_error(source: &str) -> String {
+    common::analyze(source).err().unwrap().join("\n")
+}
+
+#[test]
+fn out_of_bounds_indexes_are_errors() {
+    check_out_of_bounds(
+        "--batch",
+        run_on_source("bounds_batch", &["--batch"], OUT_OF_BOUNDS, ""),
+    );
+    check_out_of_bounds(
+        "--vm",
+        run_on_source("bounds_vm", &["--vm"], OUT_OF_BOUNDS, ""),
+    );
+    let output = run_on_source("negative", &["--batch"], "@a[2]\n<a[0 - 1]\n", "");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: Index -1 is out of bounds for an array of size 2."));
+}
+
+// The generated programs check the indexes too, and exit with an error status.
+#[test]
+fn generated_programs_check_indexes() {
+    for (target, compiler, libraries) in [("rust", "rustc", &[][..]), ("c", "cc", &["-lm"][..])] {
+        if !is_available(compiler) {
+            eprintln!(
+                "Skipping the {} target: {} is not available.",
+                target, compiler
+            );
+            continue;
+        }
+        let dir = TempDir::new(&format!("arrays_bounds_{}", target));
+        let source_path = dir.write_source("bounds", OUT_OF_BOUNDS);
+        let output = calc_compiler()
+            .args(["--target", target])
+            .arg(&source_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", target);
+        let suffix = if target == "rust" { "rs" } else { target };
+        let executable_path = source_path.with_extension("");
+        let status = Command::new(compiler)
+            .arg("-o")
+            .arg(&executable_path)
+            .arg(source_path.with_extension(suffix))
+            .args(libraries)
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}", target);
+        check_out_of_bounds(target, Command::new(&executable_path).output().unwrap());
+    }
+}
+
+#[test]
+fn arrays_are_checked_by_the_analyzer() {
+    assert!(analysis_error("@a[0]: int\n")
+        .contains("Invalid array size 0: it must be between 1 and 65536."));
+    assert!(analysis_error("@a[2]: int\n<a\n").contains("Array 'a' used without an index."));
+    assert!(analysis_error("@x: int\nx[0] := 1\n").contains("Identifier 'x' is not an array."));
+    assert!(analysis_error("@a[2]: int\n<a[0.5]\n")
+        .contains("Mismatched types: expected int, found float."));
+    assert!(analysis_error("@a[2]: int\na[0] := \"text\"\n")
+        .contains("Mismatched types: expected int, found string."));
+}
+
+#[test]
+fn identifiers_can_contain_digits_and_underscores() {
+    let source = "@x_1: int\n@if_2: int\n@while3[2]: int\nx_1 := 4\nif_2 := x_1 * 2\n\
+        while3[1] := if_2 + x_1\nif x_1 < if_2 {\n<x_1, \" \", if_2, \" \", while3[1]\n}\n";
+    let output = run_on_source("identifiers", &["--batch"], source, "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4 8 12\n");
+}
+
+#[test]
+fn other_targets_reject_arrays() {
+    let source = "@a[2]: int\na[1] := 3\n<a[1]\n";
+    let output = run_on_source("wat", &["--target", "wat"], source, "");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: WebAssembly does not support arrays."));
+    let output = run_on_source("emulate", &["--emulate"], source, "");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: The byte machine does not support arrays."));
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use calc_compiler::symbol_table::SymbolTable;
+use calc_compiler::{analyzer, bytecode, compiler, parser, Type, Value};
+use common::{
+    calc_compiler, golden_cases, golden_file, is_available, run_on_source, run_with_stdin, TempDir,
+};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+
+// The golden cases that do not use string variables.
+const WAT_CASES: [&str; 3] = ["factorial", "functions", "signs"];
+
+// Compiles the source of a golden case for the given target, and returns the generated file.
+// The source is copied into the given directory,
+// so that nothing is generated next to the original file.
+fn compile_case(target: &str, case: &str, original_path: &Path, dir: &Path) -> PathBuf {
+    let source_path = dir.join(case.to_string() + ".calc");
+    std::fs::copy(original_path, &source_path).unwrap();
+    let output = calc_compiler()
+        .args(["--target", target])
+        .arg(&source_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{} with {}: {}",
+        case,
+        target,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let suffix = match target {
+        "rust" => "rs",
+        other => other,
+    };
+    let generated_path = source_path.with_extension(suffix);
+    assert!(generated_path.exists(), "{} with {}", case, target);
+    generated_path
+}
+
+// Runs the program built for a golden case with the lines of "name.in" on stdin,
+// and checks that it prints the content of "name.out".
+fn check_case(case: &str, target: &str, command: &mut Command) {
+    let input = std::fs::read_to_string(golden_file(case, "in")).unwrap_or_default();
+    let expected = std::fs::read_to_string(golden_file(case, "out")).unwrap();
+    let output = run_with_stdin(command, &input);
+    assert!(
+        output.status.success(),
+        "{} with {}: {}",
+        case,
+        target,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "{} with {}",
+        case,
+        target
+    );
+}
+
+// The generated code must compile without warnings.
+fn check_native_target(target: &str, compiler: &str, options: &[&str], libraries: &[&str]) {
+    if !is_available(compiler) {
+        eprintln!(
+            "Skipping the {} target: {} is not available.",
+            target, compiler
+        );
+        return;
+    }
+    let dir = TempDir::new(&format!("backends_{}", target));
+    for (case, source_path) in golden_cases() {
+        let generated_path = compile_case(target, &case, &source_path, dir.path());
+        let executable_path = dir.path().join(&case);
+        let status = Command::new(compiler)
+            .args(options)
+            .arg("-o")
+            .arg(&executable_path)
+            .arg(&generated_path)
+            .args(libraries)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} with {}", case, compiler);
+        check_case(&case, target, &mut Command::new(&executable_path));
+    }
+}
+
+#[test]
+fn rust_target() {
+    check_native_target("rust", "rustc", &["-D", "warnings"], &[]);
+}
+
+#[test]
+fn c_target() {
+    check_native_target("c", "cc", &["-Wall", "-Wextra", "-Werror"], &["-lm"]);
+}
+
+// The generated modules are always assembled, and they are run only if node is available.
+#[test]
+fn wat_target() {
+    let dir = TempDir::new("backends_wat");
+    let can_run = is_available("node");
+    for (case, source_path) in golden_cases() {
+        if !WAT_CASES.contains(&case.as_str()) {
+            continue;
+        }
+        let generated_path = compile_case("wat", &case, &source_path, dir.path());
+        let binary = wat::parse_file(&generated_path)
+            .unwrap_or_else(|err| panic!("{} with wat: {}", case, err));
+        if can_run {
+            let binary_path = generated_path.with_extension("wasm");
+            std::fs::write(&binary_path, binary).unwrap();
+            check_case(
+                &case,
+                "wat",
+                Command::new("node")
+                    .arg("src/wasm_host.js")
+                    .arg(&binary_path),
+            );
+        }
+    }
+    if !can_run {
+        eprintln!("Not running the wat target: node is not available.");
+    }
+}
+
+#[test]
+fn wat_target_rejects_string_variables() {
+    let dir = TempDir::new("backends_wat_strings");
+    let source_path = dir.path().join("strings.calc");
+    std::fs::copy(golden_file("strings", "calc"), &source_path).unwrap();
+    let output = calc_compiler()
+        .args(["--target", "wat"])
+        .arg(&source_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: WebAssembly does not support string variables."));
+    assert!(!source_path.with_extension("wat").exists());
+}
+
+#[test]
+fn invalid_target() {
+    let output = calc_compiler()
+        .args(["--target", "cobol", "data/sum.calc"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Invalid target 'cobol': It must be one of rust, c, wat"));
+}
+
+// Every failure exits with an error status, and writes nothing.
+#[test]
+fn failures_exit_with_an_error_status() {
+    let dir = TempDir::new("backends_failures");
+    let invalid_path = dir.write_source("invalid", "@x\nx := 1 +\n");
+    let cases: [&[&str]; 8] = [
+        &["--target", "c"],
+        &["--run"],
+        &["--bytecode"],
+        &["--vm"],
+        &["--byte-machine"],
+        &["--debug"],
+        &["--check"],
+        &["--dump-optimization"],
+    ];
+    for arguments in &cases {
+        let output = calc_compiler()
+            .args(*arguments)
+            .arg(&invalid_path)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{:?}", arguments);
+    }
+    for arguments in &[
+        &["--run", "data/sum.txt"][..],
+        &["--unknown", "data/sum.calc"],
+    ] {
+        let output = calc_compiler().args(*arguments).output().unwrap();
+        assert!(!output.status.success(), "{:?}", arguments);
+    }
+    assert!(!calc_compiler()
+        .args(["--target", "rust", "--run", "data/sum.calc"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+// The backends may exit without reading their input,
+// even when it does not fit in the pipe.
+#[test]
+fn check_ignores_unread_input() {
+    let output = run_on_source(
+        "backends_unread",
+        &["--check"],
+        "@x: int\nx := 2\n<x * 3\n",
+        &"1\n".repeat(100_000),
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n");
+}
+
+// The backends that cannot run a program, or whose tools are missing, are reported.
+#[test]
+fn check_reports_the_skipped_backends() {
+    let source =
+        "@n: int\n@total: int\n>n\nwhile n > 0 {\ntotal := total + n\nn := n - 1\n}\n<total\n";
+    let output = run_on_source("backends_check_int", &["--check"], source, "10\n");
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", errors);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "55\n");
+    assert!(
+        errors.contains("interpreter: ok\nbytecode: ok\n"),
+        "{}",
+        errors
+    );
+    assert!(errors.contains("byte machine: ok"), "{}", errors);
+    for (backend, tool) in [("rust", "rustc"), ("c", "cc"), ("wat", "node")] {
+        let report = if is_available(tool) {
+            format!("{}: ok", backend)
+        } else {
+            format!("{}: skipped: {} is not available", backend, tool)
+        };
+        assert!(errors.contains(&report), "{}", errors);
+    }
+
+    let output = run_on_source("backends_check_float", &["--check"], "<7 / 2\n", "");
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", errors);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3.5\n");
+    assert!(
+        errors.contains("byte machine: skipped: Error: The byte machine does not support floats"),
+        "{}",
+        errors
+    );
+}
+
+#[test]
+fn native_functions_are_not_translated() {
+    let mut variables = SymbolTable::new();
+    variables
+        .insert_native(
+            "twice",
+            vec![Type::Int],
+            Type::Int,
+            Rc::new(|arguments: &[Value]| Ok(arguments[0].clone())),
+        )
+        .unwrap();
+    let parsed_program =
+        parser::parse_whole_program("fn f() {\n    return twice(2)\n}\n<f()\n").unwrap();
+    let analyzed_program = analyzer::analyze_program(&mut variables, &parsed_program).unwrap();
+    for target in compiler::TARGETS.iter() {
+        let backend = compiler::find_backend(target).unwrap();
+        assert_eq!(
+            backend.translate_program(&variables, &analyzed_program),
+            Err(
+                "Error: The native function 'twice' cannot be translated to other languages."
+                    .to_string()
+            ),
+            "{}",
+            target
+        );
+    }
+    assert_eq!(
+        bytecode::compile_program(&variables, &analyzed_program),
+        Err("Error: The native function 'twice' cannot be compiled to bytecode.".to_string())
+    );
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::run_on_source;
+
+// Compiles the given calc source for the byte machine,
+// runs it on the emulator with the given input, and returns its output.
+fn emulate(name: &str, source: &str, input: &str) -> String {
+    let output = run_on_source(name, &["--emulate"], source, input);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn arithmetic() {
+    let source = "<2 + 3 * 4\n<(2 + 3) * 4\n<7 - 10\n<0 - 32767 - 1\n<32767\n";
+    assert_eq!(
+        emulate("arithmetic", source, ""),
+        "14\n20\n-3\n-32768\n32767\n"
+    );
+}
+
+#[test]
+fn input_and_comparisons() {
+    let source = "@a: int\n@b: int\n>a\n>b\n\
+        if a < b { <1 } else { <0 }\n\
+        if a <= b { <1 } else { <0 }\n\
+        if a > b { <1 } else { <0 }\n\
+        if a >= b { <1 } else { <0 }\n\
+        if a == b { <1 } else { <0 }\n\
+        if a != b { <1 } else { <0 }\n";
+    assert_eq!(emulate("compare", source, "-12\n5\n"), "1\n1\n0\n0\n0\n1\n");
+    assert_eq!(emulate("compare", source, "5\n5\n"), "0\n1\n0\n1\n1\n0\n");
+}
+
+// The programs of the data directory, with typed variables.
+const FACTORIAL: &str = "@n: int\n@result: int\n>n\nresult := 1\n\
+    while n > 1 {\n    result := result * n\n    n := n - 1\n}\n<result\n";
+const FUNCTIONS: &str = "fn fib(n: int) -> int {\n    if n < 2 {\n        return n\n    }\n    \
+    return fib(n - 1) + fib(n - 2)\n}\n\
+    fn hypot(a: int, b: int) -> int {\n    @square: int\n    square := a * a + b * b\n    \
+    return square\n}\n\
+    @n: int\n@i: int\n>n\ni := 0\nwhile i < n {\n    @square: int\n    square := i * i\n    \
+    <fib(i) + square - hypot(i, 0)\n    i := i + 1\n}\n<hypot(3, 4)\n";
+
+#[test]
+fn loops() {
+    assert_eq!(emulate("factorial", FACTORIAL, "7\n"), "5040\n");
+}
+
+#[test]
+fn recursive_functions() {
+    assert_eq!(
+        emulate("functions", FUNCTIONS, "5\n"),
+        "0\n1\n1\n2\n3\n25\n"
+    );
+    let source = "fn f(n: int) -> int { if n > 0 { return n + f(n - 1) * 2 } }\n<f(10)\n";
+    assert_eq!(emulate("recursion", source, ""), "2036\n");
+}
+
+#[test]
+fn unary_minus_power_and_remainder() {
+    let source = "<-2^2\n<2^3^2\n<(-2)^3\n<2^-1\n<(-1)^-3\n\
+        <7 % 3\n<-7 % 3\n<7 % -3\n<2 * -3\n";
+    assert_eq!(
+        emulate("operators", source, ""),
+        "-4\n512\n-8\n0\n-1\n1\n-1\n1\n-6\n"
+    );
+}
+
+#[test]
+fn builtin_functions() {
+    let source = "<abs(-3) + abs(4)\n<min(3, -5)\n<max(3, -5)\n";
+    assert_eq!(emulate("builtins", source, ""), "7\n-5\n3\n");
+}
+
+#[test]
+fn labeled_output_and_booleans() {
+    let source = "@n: int\n@b: bool\n>n\nb := true\n<\"n = \", n, \", b = \", b\n\
+        if b == true { b := false }\n<b\n";
+    assert_eq!(
+        emulate("typed", source, "-12\n"),
+        "n = -12, b = true\nfalse\n"
+    );
+}
+
+// The integer programs print the same on the emulator and on the interpreter.
+#[test]
+fn the_emulator_runs_like_the_interpreter() {
+    for (name, source, input) in &[
+        ("factorial", FACTORIAL, "7\n"),
+        ("functions", FUNCTIONS, "5\n"),
+    ] {
+        let output = run_on_source(name, &["--run"], source, input);
+        assert!(output.status.success());
+        assert_eq!(
+            emulate(name, source, input),
+            String::from_utf8(output.stdout).unwrap()
+        );
+    }
+}
+
+// The floats would be truncated, so they are rejected instead of printing other results.
+#[test]
+fn floats_are_rejected() {
+    let sources = [
+        "@x\nx := 5\n<x * 2\n",
+        "fn half(n: int) -> float { return n }\n<1\n",
+        "<2.5 * 2\n",
+        "<abs(-2.5)\n",
+        "@n: int\nn := 7\n<n / 2\n",
+    ];
+    for source in &sources {
+        let output = run_on_source("float", &["--run"], source, "");
+        assert!(output.status.success());
+        let output = run_on_source("float", &["--emulate"], source, "");
+        assert!(!output.status.success(), "{}", source);
+        assert!(output.stdout.is_empty(), "{}", source);
+        assert!(
+            String::from_utf8_lossy(&output.stderr)
+                .contains("Error: The byte machine does not support floats"),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use calc_compiler::bytecode::{self, BytecodeProgram, Instruction};
+use calc_compiler::program_io::BatchIo;
+use calc_compiler::{vm, Type};
+use common::{analyze, golden_cases, golden_file, interpret};
+
+fn compile(source: &str) -> BytecodeProgram {
+    let (variables, analyzed_program) = analyze(source).unwrap();
+    bytecode::compile_program(&variables, &analyzed_program).unwrap()
+}
+
+// Runs the given program on the virtual machine, with the given inputs,
+// returning its output, or the first error.
+fn run(program: &BytecodeProgram, inputs: &[&str]) -> Result<String, String> {
+    let mut output = Vec::<u8>::new();
+    let inputs = inputs.iter().map(|input| input.to_string()).collect();
+    vm::execute_bytecode(program, &mut BatchIo::new(inputs, &mut output))?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn programs_survive_serialization() {
+    for (case, source_path) in golden_cases() {
+        let program = compile(&std::fs::read_to_string(source_path).unwrap());
+        let bytes = bytecode::serialize_program(&program);
+        assert_eq!(
+            bytecode::deserialize_program(&bytes),
+            Ok(program),
+            "{}",
+            case
+        );
+    }
+}
+
+#[test]
+fn the_virtual_machine_runs_like_the_interpreter() {
+    for (case, source_path) in golden_cases() {
+        let source = std::fs::read_to_string(source_path).unwrap();
+        let input = std::fs::read_to_string(golden_file(&case, "in")).unwrap_or_default();
+        let inputs: Vec<&str> = input.lines().collect();
+        let bytes = bytecode::serialize_program(&compile(&source));
+        let program = bytecode::deserialize_program(&bytes).unwrap();
+        assert_eq!(
+            run(&program, &inputs),
+            interpret(&source, &inputs),
+            "{}",
+            case
+        );
+    }
+}
+
+#[test]
+fn the_variable_count_covers_the_variables() {
+    let mut program = compile("@a: int\n@b: int\nb := 3\na := b * 2\n<a\n");
+    assert_eq!(run(&program, &[]), Ok("6\n".to_string()));
+
+    // The variable count follows the magic bytes and the format version.
+    let mut bytes = bytecode::serialize_program(&program);
+    bytes[5..9].copy_from_slice(&1u32.to_le_bytes());
+    assert_eq!(
+        bytecode::deserialize_program(&bytes),
+        Err("Error: The bytecode uses 2 variables, but declares only 1.".to_string())
+    );
+
+    program.variable_count = 0;
+    assert!(run(&program, &[]).is_err());
+    program.code = vec![Instruction::Halt];
+    assert_eq!(run(&program, &[]), Ok(String::new()));
+}
+
+#[test]
+fn the_array_sizes_are_checked() {
+    let mut program = compile("@a[3]: int\na[2] := 5\n<a[2]\n");
+    assert_eq!(run(&program, &[]), Ok("5\n".to_string()));
+    let position = program
+        .code
+        .iter()
+        .position(|instruction| *instruction == Instruction::NewArray(3, Type::Int))
+        .unwrap();
+    for size in [0, u32::MAX] {
+        program.code[position] = Instruction::NewArray(size, Type::Int);
+        assert_eq!(
+            bytecode::deserialize_program(&bytecode::serialize_program(&program)),
+            Err(format!(
+                "Error: Invalid array size {} at {}.",
+                size, position
+            ))
+        );
+    }
+}
+
+#[test]
+fn the_nested_calls_are_limited() {
+    let program = compile("fn f(n: int) -> int {\nreturn f(n + 1)\n}\n<f(0)\n");
+    assert!(run(&program, &[])
+        .unwrap_err()
+        .starts_with("Error: Too many nested calls at "));
+}
@@ -0,0 +1,136 @@
+// The helpers shared by the integration tests, which do not all use every one.
+#![allow(dead_code)]
+
+use calc_compiler::analyzer::{self, AnalyzedProgram};
+use calc_compiler::program_io::BatchIo;
+use calc_compiler::symbol_table::SymbolTable;
+use calc_compiler::{executor, parser};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+pub const GOLDEN_DIR: &str = "tests/golden";
+pub const DATA_DIR: &str = "data";
+
+pub fn calc_compiler() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_calc_compiler"))
+}
+
+// Whether the given tool can be run, to skip the tests needing it.
+pub fn is_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+pub fn run_with_stdin(command: &mut Command, input: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// A fresh directory in the temporary directory, unique for every test,
+// removed with its files when it is dropped, even if the test fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("calc_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // Writes the given calc source into the directory, and returns its path.
+    pub fn write_source(&self, name: &str, source: &str) -> PathBuf {
+        let source_path = self.0.join(name.to_string() + ".calc");
+        std::fs::write(&source_path, source).unwrap();
+        source_path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Runs the compiler with the given arguments on the given source,
+// with the given text as its standard input.
+pub fn run_on_source(name: &str, arguments: &[&str], source: &str, input: &str) -> Output {
+    let dir = TempDir::new(name);
+    let source_path = dir.write_source(name, source);
+    run_with_stdin(calc_compiler().args(arguments).arg(&source_path), input)
+}
+
+// Parses and analyzes the given source, returning the messages of the errors, if any.
+pub fn analyze(source: &str) -> Result<(SymbolTable, AnalyzedProgram), Vec<String>> {
+    let parsed_program = match parser::parse_program(source) {
+        Ok((rest, parsed_program)) if rest.trim().is_empty() => parsed_program,
+        _ => return Err(vec!["Invalid code.".to_string()]),
+    };
+    let mut variables = SymbolTable::new();
+    match analyzer::analyze_program(&mut variables, &parsed_program) {
+        Ok(analyzed_program) => Ok((variables, analyzed_program)),
+        Err(errors) => Err(errors.iter().map(|err| err.to_string()).collect()),
+    }
+}
+
+// Runs the given source on the interpreter, with the given inputs,
+// returning its output, or the first error.
+pub fn interpret(source: &str, inputs: &[&str]) -> Result<String, String> {
+    let (mut variables, analyzed_program) = analyze(source).map_err(|errors| errors.join("\n"))?;
+    let mut output = Vec::<u8>::new();
+    let inputs = inputs.iter().map(|input| input.to_string()).collect();
+    executor::execute_program(
+        &mut variables,
+        &analyzed_program,
+        &mut BatchIo::new(inputs, &mut output),
+    )?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+// The golden cases, named after their "name.out" files, with their sources.
+// The sources are taken from the data directory if they are there,
+// and from the golden directory otherwise.
+pub fn golden_cases() -> Vec<(String, PathBuf)> {
+    let mut cases = Vec::new();
+    for entry in std::fs::read_dir(GOLDEN_DIR).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("out") {
+            continue;
+        }
+        let case = path.file_stem().unwrap().to_string_lossy().to_string();
+        let data_path = Path::new(DATA_DIR).join(case.clone() + ".calc");
+        let source_path = if data_path.exists() {
+            data_path
+        } else {
+            path.with_extension("calc")
+        };
+        cases.push((case, source_path));
+    }
+    assert!(!cases.is_empty());
+    cases.sort();
+    cases
+}
+
+// The file of the given golden case with the given extension.
+pub fn golden_file(case: &str, extension: &str) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(case.to_string() + "." + extension)
+}
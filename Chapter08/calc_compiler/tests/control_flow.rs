@@ -0,0 +1,73 @@
+mod common;
+
+use common::interpret;
+
+// Writes 1 if the comparison of the two inputs holds, and 0 otherwise, for every operator.
+const COMPARISONS: &str = "@a\n@b\n>a\n>b\n\
+    if a < b { <1 } else { <0 }\n\
+    if a <= b { <1 } else { <0 }\n\
+    if a > b { <1 } else { <0 }\n\
+    if a >= b { <1 } else { <0 }\n\
+    if a == b { <1 } else { <0 }\n\
+    if a != b { <1 } else { <0 }\n";
+
+// Classifies the numbers from 0 to the input, with blocks nested in a loop.
+const NESTED: &str = "@n: int\n@i: int\n>n\nwhile i <= n {\n\
+    if i % 2 == 0 {\n\
+        if i == 0 {\n<\"zero\"\n} else if i % 4 == 0 {\n<i, \" even, multiple of 4\"\n\
+        } else {\n<i, \" even\"\n}\n\
+    } else {\n<i, \" odd\"\n}\n\
+    i := i + 1\n}\n<\"done at \", i\n";
+
+fn run(source: &str, inputs: &[&str]) -> String {
+    interpret(source, inputs).unwrap()
+}
+
+#[test]
+fn every_comparison_is_evaluated() {
+    assert_eq!(run(COMPARISONS, &["-12", "5"]), "1\n1\n0\n0\n0\n1\n");
+    assert_eq!(run(COMPARISONS, &["5", "5"]), "0\n1\n0\n1\n1\n0\n");
+    assert_eq!(run(COMPARISONS, &["2.5", "-1"]), "0\n0\n1\n1\n0\n1\n");
+    // The integers are compared with the floats by value.
+    let source = "@i: int\ni := 2\nif i == 2.0 { <\"equal\" }\nif i < 2.5 { <\"less\" }\n";
+    assert_eq!(run(source, &[]), "equal\nless\n");
+}
+
+#[test]
+fn conditions_without_operators_test_booleans() {
+    let source = "@b: bool\n>b\nif b { <\"yes\" } else { <\"no\" }\nwhile b { b := false }\n<b\n";
+    assert_eq!(run(source, &["true"]), "yes\nfalse\n");
+    assert_eq!(run(source, &["false"]), "no\nfalse\n");
+}
+
+#[test]
+fn blocks_are_nested() {
+    assert_eq!(
+        run(NESTED, &["5"]),
+        "zero\n1 odd\n2 even\n3 odd\n4 even, multiple of 4\n5 odd\ndone at 6\n"
+    );
+}
+
+#[test]
+fn while_loops_end_when_their_condition_fails() {
+    let countdown = "@n: int\n>n\nwhile n > 0 {\n<n\nn := n - 1\n}\n<\"end \", n\n";
+    assert_eq!(run(countdown, &["3"]), "3\n2\n1\nend 0\n");
+    // A false condition skips the body.
+    assert_eq!(run(countdown, &["-2"]), "end -2\n");
+    // The inner loop restarts at every iteration of the outer loop.
+    let product = "@i: int\n@j: int\n@count: int\nwhile i < 3 {\nj := 0\n\
+        while j < 4 {\ncount := count + 1\nj := j + 1\n}\ni := i + 1\n}\n<count\n";
+    assert_eq!(run(product, &[]), "12\n");
+}
+
+#[test]
+fn conditions_are_checked_by_the_analyzer() {
+    let errors = interpret("@s: string\nif s < 1 { <s }\n", &[]).unwrap_err();
+    assert!(
+        errors.contains("Cannot apply '<' to values of types string and int."),
+        "{}",
+        errors
+    );
+    let errors = interpret("while 1 + 2 { }\n", &[]).unwrap_err();
+    assert!(errors.contains("expected bool, found int"), "{}", errors);
+}
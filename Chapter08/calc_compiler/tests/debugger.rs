@@ -0,0 +1,106 @@
+mod common;
+
+use std::process::Output;
+
+const COUNTDOWN: &str = "@n: int\n@total: int\ntotal := 0\n>n\nwhile n > 0 {\n    \
+    total := total + n\n    n := n - 1\n}\n<\"total \", total\n";
+
+// Debugs the given calc source, with the given script of commands and inputs.
+fn debug(name: &str, source: &str, script: &str) -> Output {
+    common::run_on_source(name, &["--debug"], source, script)
+}
+
+#[test]
+fn statements_are_stepped_with_their_lines() {
+    let output = debug("step", COUNTDOWN, "s\ns\ns\ns\n1\ns\ns\ns\ns\n");
+    let errors = String::from_utf8_lossy(&output.stderr);
+    let stops: Vec<&str> = errors
+        .split("(debug) ")
+        .filter_map(|stop| stop.trim_start_matches("? ").split(" | ").next())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    // The while statement stops again before evaluating its condition.
+    assert_eq!(stops, ["1", "2", "3", "4", "5", "6", "7", "5", "9"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "total 1\n");
+}
+
+#[test]
+fn breakpoints_show_the_watched_variables() {
+    let script = "watch total\nbreak 7\nc\n3\nc\np n\ndelete 7\nc\n";
+    let output = debug("break", COUNTDOWN, script);
+    assert!(output.status.success());
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(errors.contains("Breakpoint at line 7."));
+    assert!(errors.contains("   7 | n := n - 1\ntotal: int = 3\n"));
+    assert!(errors.contains("   7 | n := n - 1\ntotal: int = 5\n"));
+    assert!(errors.contains("n: int = 2\n"));
+    assert!(!errors.contains("total: int = 6\n"));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "total 6\n");
+}
+
+#[test]
+fn function_bodies_are_stepped() {
+    let source = "fn twice(x: int) -> int {\n    return x * 2\n}\n@y: int\ny := twice(4)\n<y\n";
+    let output = debug("function", source, "s\ns\nvars\n");
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(errors.starts_with("   4 | @y: int\n"));
+    assert!(errors.contains("   2 | return x * 2\n(debug) x: int = 4\ny: int = 0\n"));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "8\n");
+}
+
+#[test]
+fn quitting_stops_the_program() {
+    let output = debug("quit", COUNTDOWN, "s\nprint m\nq\n");
+    assert!(!output.status.success());
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(errors.contains("No variable named 'm'."));
+    assert!(errors.contains("Program stopped by the debugger."));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn variables_are_looked_up_in_the_current_scope() {
+    let source = "@x: int\nx := 10\nfn twice(x: int) -> int {\n    @y: int\n    y := x * 2\n    \
+        return y\n}\n@r: int\nr := twice(4)\nwhile r > 0 {\n    @half: int\n    \
+        half := r % 3\n    r := 0\n}\n";
+    let script = "break 6\nc\np x\np y\np r\ndelete 6\nbreak 13\nc\np x\np half\nc\n";
+    let output = debug("scope", source, script);
+    assert!(output.status.success());
+    let errors = String::from_utf8_lossy(&output.stderr);
+    let function_stop = errors.find("   6 | return y\n").unwrap();
+    let loop_stop = errors.find("  13 | r := 0\n").unwrap();
+    assert_eq!(
+        &errors[function_stop..loop_stop],
+        "   6 | return y\n(debug) x: int = 4\n(debug) y: int = 8\n(debug) r: int = 0\n\
+         (debug) Removed the breakpoint at line 6.\n(debug) Breakpoint at line 13.\n(debug) "
+    );
+    assert!(errors[loop_stop..].contains("(debug) x: int = 10\n(debug) half: int = 2\n"));
+}
+
+#[test]
+fn sibling_blocks_have_their_own_variables() {
+    let source = "@n: int\nn := 1\nif n > 0 {\n    @t: int\n    t := 5\n    n := t\n}\n\
+        if n > 0 {\n    @t: bool\n    t := true\n    n := 0\n}\n\
+        fn f(k: int) -> int {\n    while k > 0 {\n        @t: int\n        t := k\n        k := 0\n    }\n\
+        if k == 0 {\n        @t: string\n        t := \"done\"\n        return k\n    }\n    return k\n}\n<f(3)\n";
+    let script = "break 6\nbreak 11\nbreak 17\nbreak 22\nc\np t\nc\np t\nc\np t\np k\nc\np t\nc\n";
+    let output = debug("siblings", source, script);
+    assert!(output.status.success());
+    let errors = String::from_utf8_lossy(&output.stderr);
+    let values: Vec<&str> = errors
+        .split("(debug) ")
+        .filter(|text| text.starts_with("t: ") || text.starts_with("k: "))
+        .map(str::trim)
+        .collect();
+    assert_eq!(
+        values,
+        [
+            "t: int = 5",
+            "t: bool = true",
+            "t: int = 3",
+            "k: int = 3",
+            "t: string = done"
+        ]
+    );
+}
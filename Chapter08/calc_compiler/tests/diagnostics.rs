@@ -0,0 +1,74 @@
+mod common;
+
+use calc_compiler::analyzer;
+use calc_compiler::diagnostics::{self, Diagnostic};
+use calc_compiler::parser;
+use calc_compiler::symbol_table::SymbolTable;
+use common::{calc_compiler, run_on_source, run_with_stdin};
+
+// Parses and analyzes the given source, and renders every diagnostic, if any.
+fn render_errors(source: &str) -> String {
+    let parsed_program = match parser::parse_whole_program(source) {
+        Ok(parsed_program) => parsed_program,
+        Err(err) => return diagnostics::render(source, "test.calc", &err),
+    };
+    match analyzer::analyze_program(&mut SymbolTable::new(), &parsed_program) {
+        Ok(_) => String::new(),
+        Err(errors) => errors
+            .iter()
+            .map(|err| diagnostics::render(source, "test.calc", err))
+            .collect(),
+    }
+}
+
+#[test]
+fn the_caret_is_under_the_span() {
+    assert_eq!(
+        render_errors("@x: int\nx := y + 1\n"),
+        "error: Identifier 'y' used before having been declared.\n  \
+         --> test.calc:2:6\n  |\n2 | x := y + 1\n  |      ^\n"
+    );
+}
+
+#[test]
+fn every_analyzer_error_is_reported() {
+    let errors = render_errors("@x\n@x\ny := 1\n<f(2)\n");
+    assert_eq!(errors.matches("error: ").count(), 3, "{}", errors);
+    assert!(errors.contains("Identifier 'x' declared several times."));
+    assert!(errors.contains("--> test.calc:2:2"));
+    assert!(errors.contains("--> test.calc:3:1"));
+    assert!(errors.contains("--> test.calc:4:2"));
+}
+
+#[test]
+fn invalid_statements_in_blocks_are_reported_where_they_are() {
+    let source = "@x\nx := 1\nwhile x < 3 {\n  if x > 0 {\n    y := * 2\n  }\n  x := x + 1\n}\n";
+    let err = parser::parse_whole_program(source).unwrap_err();
+    assert_eq!(
+        err,
+        Diagnostic::InvalidStatement("y := * 2\n  }\n  x := x + 1\n}")
+    );
+    assert_eq!(diagnostics::locate(source, err.span()), Some((5, 5)));
+
+    let output = run_on_source("diagnostics_nested", &["--batch"], source, "");
+    let errors = String::from_utf8(output.stderr).unwrap();
+    assert!(errors.contains("error: Invalid statement."), "{}", errors);
+    assert!(errors.contains("diagnostics_nested.calc:5:5"), "{}", errors);
+}
+
+#[test]
+fn unclosed_blocks_are_reported_at_the_end() {
+    let source = "@x\nwhile x < 3 {\n  x := x + 1\n";
+    let err = parser::parse_whole_program(source).unwrap_err();
+    assert_eq!(err.to_string(), "Missing '}' at the end of the code.");
+    assert_eq!(diagnostics::locate(source, err.span()), Some((3, 13)));
+}
+
+#[test]
+fn the_interactive_interpreter_renders_parse_errors() {
+    let output = run_with_stdin(&mut calc_compiler(), "@x\nif x < 1 { <1 } else { <) }\n");
+    let errors = String::from_utf8(output.stderr).unwrap();
+    assert!(errors.contains("error: Invalid statement."), "{}", errors);
+    assert!(errors.contains(":1:24\n"), "{}", errors);
+    assert!(!errors.contains("Error("), "{}", errors);
+}
@@ -0,0 +1,182 @@
+use calc_compiler::program_io::BatchIo;
+use calc_compiler::{Engine, Type, Value};
+
+#[test]
+fn formulas_are_compiled_once_and_evaluated_many_times() {
+    let mut engine = Engine::new();
+    engine.define_variable("price", Value::Float(0.)).unwrap();
+    engine.define_variable("quantity", Value::Int(0)).unwrap();
+    let formula = engine.compile("price * quantity * (1 + 0.25)").unwrap();
+    for (price, quantity, expected) in [(2., 4, 10.), (8., 1, 10.), (0.5, 10, 6.25)] {
+        engine.set_variable("price", Value::Float(price)).unwrap();
+        engine
+            .set_variable("quantity", Value::Int(quantity))
+            .unwrap();
+        assert_eq!(
+            engine.evaluate(&formula).unwrap(),
+            Some(Value::Float(expected))
+        );
+    }
+}
+
+#[test]
+fn statements_assign_variables_read_by_the_host() {
+    let mut engine = Engine::new();
+    engine
+        .define_variable("samples", Value::Array(vec![Value::Int(0); 3]))
+        .unwrap();
+    let formula = engine
+        .compile("@total: int\n@i: int\ni := 0\nwhile i < 3 {\n    total := total + samples[i]\n    i := i + 1\n}")
+        .unwrap();
+    engine
+        .set_variable(
+            "samples",
+            Value::Array(vec![Value::Int(4), Value::Int(5), Value::Int(6)]),
+        )
+        .unwrap();
+    assert_eq!(engine.evaluate(&formula).unwrap(), None);
+    assert_eq!(engine.get_variable("total"), Some(Value::Int(15)));
+    assert!(engine
+        .set_variable("samples", Value::Array(vec![Value::Int(1)]))
+        .is_err());
+}
+
+#[test]
+fn host_variables_keep_their_types() {
+    let mut engine = Engine::new();
+    engine.define_variable("rate", Value::Float(0.5)).unwrap();
+    engine.define_variable("count", Value::Int(1)).unwrap();
+    engine.set_variable("rate", Value::Int(2)).unwrap();
+    assert_eq!(engine.get_variable("rate"), Some(Value::Float(2.)));
+    assert_eq!(
+        engine.set_variable("count", Value::Str("two".to_string())),
+        Err("Error: Cannot assign the value two to variable 'count'.".to_string())
+    );
+    assert!(engine.define_variable("count", Value::Int(3)).is_err());
+    assert!(engine.set_variable("missing", Value::Int(3)).is_err());
+    let err = engine.compile("count := rate").unwrap_err();
+    assert!(err.contains("Mismatched types: expected int, found float."));
+    assert!(err.contains("<formula>:1:10"), "{}", err);
+}
+
+#[test]
+fn native_functions_are_resolved_by_the_analyzer() {
+    let mut engine = Engine::new();
+    engine
+        .register_function(
+            "clamp",
+            &[Type::Float, Type::Float, Type::Float],
+            Type::Float,
+            |arguments| {
+                let (value, low, high) = (&arguments[0], &arguments[1], &arguments[2]);
+                Ok(Value::Float(
+                    value.as_float().max(low.as_float()).min(high.as_float()),
+                ))
+            },
+        )
+        .unwrap();
+    let formula = engine
+        .compile("clamp(7, 0, 5) + clamp(-1.5, 0, 5)")
+        .unwrap();
+    assert_eq!(engine.evaluate(&formula).unwrap(), Some(Value::Float(5.)));
+    assert!(engine
+        .compile("clamp(1, 2)")
+        .unwrap_err()
+        .contains("Function 'clamp' takes 3 arguments but 2 were supplied."));
+    assert!(engine
+        .compile("clamp(\"text\", 0, 1)")
+        .unwrap_err()
+        .contains("Mismatched types: expected float, found string."));
+    assert!(engine
+        .compile("fn clamp(x) {\n    return x\n}")
+        .unwrap_err()
+        .contains("Function 'clamp' is a built-in function."));
+    assert!(engine
+        .register_function("sqrt", &[Type::Float], Type::Float, |_| Ok(Value::Float(
+            0.
+        )))
+        .is_err());
+    assert!(engine
+        .register_function("clamp", &[], Type::Int, |_| Ok(Value::Int(0)))
+        .is_err());
+}
+
+#[test]
+fn native_functions_report_errors() {
+    let mut engine = Engine::new();
+    engine
+        .register_function(
+            "check",
+            &[Type::Int],
+            Type::Int,
+            |arguments| match arguments[0] {
+                Value::Int(value) if value >= 0 => Ok(Value::Int(value)),
+                _ => Err("Error: Negative value.".to_string()),
+            },
+        )
+        .unwrap();
+    engine
+        .register_function("label", &[], Type::Int, |_| {
+            Ok(Value::Str("wrong".to_string()))
+        })
+        .unwrap();
+    let formula = engine.compile("check(0 - 2)").unwrap();
+    assert_eq!(
+        engine.evaluate(&formula),
+        Err("Error: Negative value.".to_string())
+    );
+    let formula = engine.compile("label()").unwrap();
+    assert_eq!(
+        engine.evaluate(&formula),
+        Err("Error: Function 'label' returned a value of type string instead of int.".to_string())
+    );
+}
+
+#[test]
+fn invalid_sources_leave_no_declarations() {
+    let mut engine = Engine::new();
+    assert!(engine.compile("@x: int\nx := y").is_err());
+    assert!(engine.compile("@x: int\n@y := 1").is_err());
+    assert_eq!(engine.get_variable("x"), None);
+    let formula = engine.compile("@x: int\nx := 3\nx * 2").unwrap();
+    assert_eq!(engine.evaluate(&formula).unwrap(), Some(Value::Int(6)));
+}
+
+#[test]
+fn output_statements_use_the_given_devices() {
+    let mut engine = Engine::new();
+    let formula = engine
+        .compile("@name: string\n>name\n<\"Hello, \", name")
+        .unwrap();
+    let mut output = Vec::<u8>::new();
+    let mut io = BatchIo::new(vec!["world".to_string()], &mut output);
+    assert_eq!(engine.evaluate_with_io(&formula, &mut io).unwrap(), None);
+    assert_eq!(String::from_utf8(output).unwrap(), "Hello, world\n");
+}
+
+#[test]
+fn formulas_are_evaluated_only_by_their_engine() {
+    let mut engine = Engine::new();
+    let formula = engine.compile("@x: int\nx := 4\nx + 1").unwrap();
+    let mut other = engine.clone();
+    assert_eq!(
+        other.evaluate(&formula),
+        Err("Error: The formula was compiled by another engine.".to_string())
+    );
+    assert_eq!(
+        Engine::new().evaluate(&formula),
+        Err("Error: The formula was compiled by another engine.".to_string())
+    );
+    assert_eq!(engine.evaluate(&formula), Ok(Some(Value::Int(5))));
+}
+
+#[test]
+fn parse_errors_are_rendered() {
+    let mut engine = Engine::new();
+    let err = engine
+        .compile("@x: int\nwhile x < 3 {\n    x := * 2\n}\nx")
+        .unwrap_err();
+    assert!(err.starts_with("error: Invalid statement."), "{}", err);
+    assert!(err.contains("--> <formula>:3:5"), "{}", err);
+    assert!(!err.contains("Error("), "{}", err);
+}
@@ -0,0 +1,290 @@
+mod common;
+
+use calc_compiler::{formatter, parser};
+use common::{calc_compiler, golden_cases, golden_file, TempDir};
+
+const RANDOM_PROGRAM_COUNT: u64 = 40;
+
+// The precedence levels of expressions, from the loosest to the tightest.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const PRIMARY: u8 = 5;
+
+// Formats the given source code, which must be valid, returning the formatted code.
+fn format_source(source: &str) -> String {
+    match parser::parse_program(source) {
+        Ok((rest, parsed_program)) if rest.trim().is_empty() => {
+            formatter::format_program(&parsed_program)
+        }
+        _ => panic!("Invalid code:\n{}", source),
+    }
+}
+
+// A xorshift generator, so that every run checks the same programs.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, limit: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % limit
+    }
+    fn choose<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+// Generates the formatted code of random programs,
+// using parentheses only where the precedence requires them.
+struct Generator {
+    random: Random,
+}
+
+impl Generator {
+    fn operand(&mut self, depth: u32, required_level: u8) -> String {
+        let (text, level) = self.expr(depth);
+        if level < required_level {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+    fn expr(&mut self, depth: u32) -> (String, u8) {
+        let kind = if depth == 0 { 0 } else { self.random.below(9) };
+        match kind {
+            0 | 1 => (self.primary(depth), PRIMARY),
+            2 => (format!("-{}", self.operand(depth - 1, NEGATION)), NEGATION),
+            3 => (
+                format!(
+                    "{} ^ {}",
+                    self.operand(depth - 1, PRIMARY),
+                    self.operand(depth - 1, NEGATION)
+                ),
+                POWER,
+            ),
+            4 | 5 => {
+                let mut text = self.operand(depth - 1, PRODUCT);
+                for _ in 0..=self.random.below(2) {
+                    let operator = self.random.choose(&["*", "/", "%"]);
+                    text += &format!(" {} {}", operator, self.operand(depth - 1, NEGATION));
+                }
+                (text, PRODUCT)
+            }
+            _ => {
+                let mut text = self.operand(depth - 1, SUM);
+                for _ in 0..=self.random.below(2) {
+                    let operator = self.random.choose(&["+", "-"]);
+                    text += &format!(" {} {}", operator, self.operand(depth - 1, PRODUCT));
+                }
+                (text, SUM)
+            }
+        }
+    }
+    fn primary(&mut self, depth: u32) -> String {
+        match self.random.below(if depth == 0 { 4 } else { 6 }) {
+            0 => self.random.below(1000).to_string(),
+            1 => self
+                .random
+                .choose(&["0.5", "2.0", "3.25", "1e30"])
+                .to_string(),
+            2 => self
+                .random
+                .choose(&["v0", "v_1", "total", "true"])
+                .to_string(),
+            3 => "\"text\"".to_string(),
+            4 => format!("arr[{}]", self.expr(depth - 1).0),
+            _ => {
+                let arguments: Vec<String> = (0..self.random.below(3))
+                    .map(|_| self.expr(depth - 1).0)
+                    .collect();
+                format!("f({})", arguments.join(", "))
+            }
+        }
+    }
+    fn condition(&mut self) -> String {
+        if self.random.below(4) == 0 {
+            return self.expr(2).0;
+        }
+        let operator = self.random.choose(&["==", "!=", "<", "<=", ">", ">="]);
+        format!("{} {} {}", self.expr(2).0, operator, self.expr(2).0)
+    }
+    fn block(&mut self, depth: u32, indentation: usize) -> String {
+        let count = self.random.below(3);
+        if count == 0 {
+            return "{}".to_string();
+        }
+        let mut text = "{\n".to_string();
+        for _ in 0..count {
+            text += &self.statement(depth, indentation + 1);
+        }
+        text + &"    ".repeat(indentation) + "}"
+    }
+    fn statement(&mut self, depth: u32, indentation: usize) -> String {
+        let kind = if depth == 0 { 0 } else { self.random.below(9) };
+        let text = match kind {
+            0 | 1 => format!("v0 := {}", self.expr(3).0),
+            2 => format!("arr[{}] := {}", self.expr(1).0, self.expr(2).0),
+            3 => {
+                let exprs: Vec<String> =
+                    (0..=self.random.below(2)).map(|_| self.expr(3).0).collect();
+                format!("<{}", exprs.join(", "))
+            }
+            4 => self
+                .random
+                .choose(&[">v0", ">arr[2]", "@v_1", "@total: int"])
+                .to_string(),
+            5 => format!("return {}", self.expr(2).0),
+            6 => format!(
+                "while {} {}",
+                self.condition(),
+                self.block(depth - 1, indentation)
+            ),
+            _ => {
+                let mut text = format!(
+                    "if {} {}",
+                    self.condition(),
+                    self.block(depth - 1, indentation)
+                );
+                match self.random.below(3) {
+                    0 => {}
+                    // An else block made only of an if statement would be printed as "else if".
+                    1 => {
+                        text += &format!(
+                            " else {{\n{}{}}}",
+                            self.statement(0, indentation + 1),
+                            "    ".repeat(indentation)
+                        )
+                    }
+                    _ => {
+                        text += &format!(
+                            " else if {} {}",
+                            self.condition(),
+                            self.block(depth - 1, indentation)
+                        )
+                    }
+                }
+                text
+            }
+        };
+        "    ".repeat(indentation) + &text + "\n"
+    }
+    fn program(&mut self) -> String {
+        let mut text = "@v0\n@arr[4]: int\nfn f(a: int, b) -> float {\n".to_string();
+        text += &self.statement(1, 1);
+        text += "}\n";
+        for _ in 0..=self.random.below(6) {
+            text += &self.statement(2, 0);
+        }
+        text
+    }
+}
+
+// Replaces the spaces, and the boundaries of parentheses and lines,
+// with random whitespace, which must not change the parsed program.
+fn add_whitespace(random: &mut Random, source: &str) -> String {
+    let mut text = String::new();
+    let mut in_string = false;
+    for ch in source.chars() {
+        let spacing = random.choose(&["", " ", "  ", "\t", "\n", " \n  "]);
+        match ch {
+            '"' => {
+                in_string = !in_string;
+                text.push(ch);
+            }
+            ' ' if !in_string => text += if spacing.is_empty() { " " } else { spacing },
+            '(' | '\n' if !in_string => {
+                text.push(ch);
+                text += spacing;
+            }
+            ')' if !in_string => {
+                text += spacing;
+                text.push(ch);
+            }
+            _ => text.push(ch),
+        }
+    }
+    text
+}
+
+#[test]
+fn random_programs_round_trip() {
+    for seed in 1..=RANDOM_PROGRAM_COUNT {
+        let mut generator = Generator {
+            random: Random(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        };
+        let formatted = generator.program();
+        assert_eq!(format_source(&formatted), formatted, "seed {}", seed);
+        let spaced = add_whitespace(&mut generator.random, &formatted);
+        assert_eq!(format_source(&spaced), formatted, "seed {}", seed);
+    }
+}
+
+#[test]
+fn only_required_parentheses_are_kept() {
+    for (source, expected) in [
+        ("x:=((1+2))*(3)", "x := (1 + 2) * 3"),
+        ("x := (a - b) - (c - d)", "x := a - b - (c - d)"),
+        (
+            "x := (a * b) * (c * d) + (e / f)",
+            "x := a * b * (c * d) + e / f",
+        ),
+        ("x := -(2 ^ 2) + (-2) ^ 2", "x := -2 ^ 2 + (-2) ^ 2"),
+        (
+            "x := a ^ (b ^ c) + (a ^ b) ^ c",
+            "x := a ^ b ^ c + (a ^ b) ^ c",
+        ),
+        ("x := a * (-b) - -(c) + (+3)", "x := a * -b - -c + 3.0"),
+        ("<(x), f((a), (b + c)), v[(i)]", "<x, f(a, b + c), v[i]"),
+    ] {
+        assert_eq!(format_source(source), expected.to_string() + "\n");
+    }
+}
+
+// The formatted golden programs are formatted already, and they behave the same.
+// They are formatted by the command, which rewrites the file.
+#[test]
+fn formatted_golden_programs_keep_their_output() {
+    let dir = TempDir::new("fmt_golden");
+    for (case, source_path) in golden_cases() {
+        let source = std::fs::read_to_string(&source_path).unwrap();
+        let formatted_path = dir.write_source(&case, &source);
+        let output = calc_compiler()
+            .arg("--fmt")
+            .arg(&formatted_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", case);
+        let formatted = std::fs::read_to_string(&formatted_path).unwrap();
+        assert_eq!(formatted, format_source(&source), "{}", case);
+        assert_eq!(format_source(&formatted), formatted, "{}", case);
+        let mut batch = calc_compiler();
+        batch.arg("--batch").arg(&formatted_path);
+        let inputs_path = golden_file(&case, "in");
+        if inputs_path.exists() {
+            batch.arg("--inputs").arg(&inputs_path);
+        }
+        let output = batch.output().unwrap();
+        assert!(output.status.success(), "{}", case);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            std::fs::read_to_string(golden_file(&case, "out")).unwrap(),
+            "{}",
+            case
+        );
+    }
+}
+
+#[test]
+fn invalid_files_are_left_unchanged() {
+    let dir = TempDir::new("fmt_invalid");
+    let path = dir.write_source("invalid", "x  :=  1\ny := * 2\n");
+    let output = calc_compiler().arg("--fmt").arg(&path).output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "x  :=  1\ny := * 2\n"
+    );
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{interpret, run_on_source};
+
+#[test]
+fn recursive_functions_are_called() {
+    let source = "fn factorial(n: int) -> int {\nif n <= 1 {\nreturn 1\n}\n\
+        return n * factorial(n - 1)\n}\n<factorial(20)\n";
+    assert_eq!(interpret(source, &[]).unwrap(), "2432902008176640000\n");
+}
+
+// The interpreter is run on the main thread, whose stack is larger than the one of the tests.
+#[test]
+fn endless_recursion_is_stopped() {
+    let source = "fn f(n: int) -> int {\nreturn f(n + 1)\n}\n<f(0)\n";
+    let output = run_on_source("functions_endless", &["--run"], source, "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: Too many nested calls, calling function 'f'."));
+    // The calls nested in blocks and expressions are counted as well.
+    let source = "fn g(n: int) -> int {\nwhile n >= 0 {\nif n < 1000000 {\n\
+        return 1 + (2 * (g(n + 1) - 1))\n}\n}\nreturn n\n}\n<g(0)\n";
+    let output = run_on_source("functions_nested", &["--run"], source, "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Too many nested calls"));
+}
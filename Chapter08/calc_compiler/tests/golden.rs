@@ -0,0 +1,64 @@
+mod common;
+
+use common::{calc_compiler, golden_cases, golden_file, run_with_stdin};
+use std::path::Path;
+use std::process::Output;
+
+fn check_output(case: &Path, backend: &str, output: Output, expected: &str) {
+    assert!(
+        output.status.success(),
+        "{} with {}: {}",
+        case.display(),
+        backend,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "{} with {}",
+        case.display(),
+        backend
+    );
+}
+
+// Every golden case is run with the lines of "name.in" as its inputs,
+// and its output must be the content of "name.out".
+#[test]
+fn golden_files() {
+    for (case, source_path) in golden_cases() {
+        let inputs_path = golden_file(&case, "in");
+        let expected = std::fs::read_to_string(golden_file(&case, "out")).unwrap();
+        let input = std::fs::read_to_string(&inputs_path).unwrap_or_default();
+
+        let mut batch = calc_compiler();
+        batch.arg("--batch").arg(&source_path);
+        if inputs_path.exists() {
+            batch.arg("--inputs").arg(&inputs_path);
+        }
+        check_output(&source_path, "--batch", batch.output().unwrap(), &expected);
+
+        let vm_output = run_with_stdin(calc_compiler().arg("--vm").arg(&source_path), &input);
+        check_output(&source_path, "--vm", vm_output, &expected);
+    }
+}
+
+#[test]
+fn inputs_from_arguments() {
+    let output = calc_compiler()
+        .args(["--batch", "data/sum.calc", "2", "40"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn missing_inputs_are_errors() {
+    let output = calc_compiler()
+        .args(["--batch", "data/sum.calc", "2"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: No more inputs."));
+}
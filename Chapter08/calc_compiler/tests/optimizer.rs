@@ -0,0 +1,94 @@
+mod common;
+
+use calc_compiler::program_io::BatchIo;
+use calc_compiler::{executor, optimizer};
+
+fn optimized_tree(source: &str) -> String {
+    let (mut variables, analyzed_program) = common::analyze(source).unwrap();
+    let optimized_program = optimizer::optimize_program(&mut variables, &analyzed_program);
+    optimizer::dump_program(&variables, &optimized_program)
+}
+
+#[test]
+fn constants_are_folded() {
+    let source = "@x: int\n>x\n<(1 + 2) * x, 2 ^ 10 - 24, -(3 * 2.5), abs(-4)\n<7 / 2 * 1.5\n";
+    assert_eq!(
+        optimized_tree(source),
+        "@x: int\n>x\n<3 * x, 1000, -7.5, 4\n<5.25\n"
+    );
+}
+
+#[test]
+fn failing_operations_are_not_folded() {
+    let source = "<1 / 0, 2 ^ -1\n";
+    assert_eq!(optimized_tree(source), "<1 / 0, 2 ^ -1\n");
+}
+
+#[test]
+fn neutral_operands_are_removed() {
+    let source =
+        "@x: int\n@f\n>x\n>f\n<x * 1 + 0, 1 * x / 1, 0 + x - 0, f * 1, f + 0, f - 0, x * 1.\n";
+    assert_eq!(
+        optimized_tree(source),
+        "@x: int\n@f: float\n>x\n>f\n<x, x / 1, x, f, f + 0, f, x * 1.0\n"
+    );
+}
+
+#[test]
+fn dead_stores_and_unused_declarations_are_removed() {
+    let source = "@unused\n@x: int\n@y: int\nx := 1\nx := 2\ny := x\n\
+        fn f(n: int) -> int {\n@t: int\nt := n\nreturn n\n}\n<x, f(x)\n";
+    assert_eq!(
+        optimized_tree(source),
+        "@x: int\nx := 2\nfn f\n<x, f(x)\nfn f(n: int) -> int {\n    return n\n}\n"
+    );
+}
+
+#[test]
+fn stores_with_calls_are_kept() {
+    let source = "fn f() -> int {\n<\"called\"\n}\n@x: int\nx := f()\n";
+    assert_eq!(
+        optimized_tree(source),
+        "fn f\n@x: int\nx := f()\nfn f() -> int {\n    <\"called\"\n}\n"
+    );
+}
+
+#[test]
+fn failing_stores_are_kept() {
+    let source = "@x: int\n@a[3]: int\nx := 5 % 0\nx := a[7]\nx := -x\n<1\n";
+    assert_eq!(
+        optimized_tree(source),
+        "@x: int\n@a[3]: int\nx := 5 % 0\nx := a[7]\nx := -x\n<1\n"
+    );
+    let (mut variables, analyzed_program) = common::analyze(source).unwrap();
+    let optimized_program = optimizer::optimize_program(&mut variables, &analyzed_program);
+    let mut output = Vec::<u8>::new();
+    let result = executor::execute_program(
+        &mut variables,
+        &optimized_program,
+        &mut BatchIo::new(vec![], &mut output),
+    );
+    assert!(result.is_err());
+    assert!(output.is_empty());
+}
+
+#[test]
+fn element_stores_are_kept() {
+    let source = "@a[4]: int\n@i: int\ni := 1 + 1\na[i * 1] := 2 * 3\na[i] := 7\n@unused[2]\n";
+    assert_eq!(
+        optimized_tree(source),
+        "@a[4]: int\n@i: int\ni := 2\na[i] := 6\na[i] := 7\n"
+    );
+}
+
+#[test]
+fn reads_before_assignments_are_reported() {
+    let source = "@a\n@b\n@c\n>a\nif a > 0 {\nb := 1\nc := 1\n} else {\nc := 2\n}\n\
+        <a + b + c\nfn f(n) {\n@t\nreturn n + t\n}\n<f(a)\n";
+    let (variables, analyzed_program) = common::analyze(source).unwrap();
+    let errors = optimizer::check_assignments(&variables, &analyzed_program).join("\n");
+    assert!(errors.contains("Variable 'b' may be read before being assigned."));
+    assert!(!errors.contains("Variable 'c'"));
+    assert!(!errors.contains("Variable 'n'"));
+    assert!(errors.contains("Variable 't' may be read before being assigned in function 'f'."));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use calc_compiler::repl::{self, LineReader, ReadResult};
+use common::{calc_compiler, run_with_stdin, TempDir};
+use std::collections::VecDeque;
+
+// Replays what the user typed, including the lines interrupted with Ctrl-C.
+struct ScriptedReader(VecDeque<ReadResult>);
+
+impl LineReader for ScriptedReader {
+    fn read_line(&mut self, _prompt: &str) -> ReadResult {
+        self.0.pop_front().unwrap_or(ReadResult::End)
+    }
+}
+
+// Runs the interactive interpreter with the given script as its input,
+// and returns its output and its error output.
+fn run_script(script: &str) -> (String, String) {
+    let output = run_with_stdin(&mut calc_compiler(), script);
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn statements_keep_their_variables() {
+    let (output, _) = run_script("@x: int\nx := 6\n<x * 7\n");
+    assert_eq!(output, "42\n");
+}
+
+// The inputs of the programs are the lines following their entries.
+#[test]
+fn inputs_are_read_from_the_script() {
+    let (output, _) = run_script("@x: int\n>x\n5\n<x * 2\n>x\n");
+    assert_eq!(output, "10\n");
+}
+
+#[test]
+fn unfinished_entries_continue_on_the_next_lines() {
+    let script = "fn twice(n: int) -> int {\n  return n *\n    2\n}\n<twice(\n4)\n";
+    let (output, errors) = run_script(script);
+    assert_eq!(output, "8\n");
+    assert!(errors.contains("... "));
+}
+
+#[test]
+fn invalid_entries_leave_no_declarations() {
+    let (output, errors) = run_script("@x\n@y y := z\n@y: int\n:vars\n");
+    assert!(errors.contains("Identifier 'z' used before having been declared."));
+    assert_eq!(output, "x: float = 0\ny: int = 0\n");
+}
+
+#[test]
+fn meta_commands_show_the_form_of_expressions() {
+    let (output, errors) = run_script(
+        "@s: string\n:type s + \"!\"\n:type 1 + 2\n:type 1 / 2.\n:ast -2\n:type s - 1\n",
+    );
+    assert_eq!(
+        output,
+        "string\nint\nfloat\n(\n    (\n        Negation(\n            IntegerLiteral(\n                2,\n            ),\n        ),\n        [],\n    ),\n    [],\n    \"-2\",\n)\n"
+    );
+    assert!(errors.contains("Cannot apply '-' to values of types string and int."));
+}
+
+// The divisions of integers used to truncate, when typed values were introduced.
+#[test]
+fn integer_divisions_give_floats() {
+    let (output, errors) = run_script("<1/3\n<6 / 3\n:type 7 / 7\n@i: int\ni := 7 / 2\n");
+    assert_eq!(output, "0.3333333333333333\n2\nfloat\n");
+    assert!(errors.contains("Mismatched types: expected int, found float."));
+}
+
+#[test]
+fn sessions_are_saved_and_loaded() {
+    let dir = TempDir::new("repl_session");
+    let path = dir.path().join("session.calc");
+    let path = path.to_str().unwrap();
+    let (output, _) = run_script(&format!(
+        "@x: int\nx := 5\n<\"x = \", x\n:save {}\n:reset\n:vars\n:load {}\n:quit\n<x\n",
+        path, path
+    ));
+    let saved = std::fs::read_to_string(path).unwrap();
+    assert_eq!(saved, "@x: int\nx := 5\n<\"x = \", x\n");
+    assert_eq!(output, "x = 5\nx = 5\n");
+}
+
+#[test]
+fn unknown_commands_are_reported() {
+    let (_, errors) = run_script(":frobnicate\n:load\n");
+    assert!(errors.contains("Unknown command ':frobnicate'."));
+    assert!(errors.contains("The command ':load' needs a file name."));
+}
+
+#[test]
+fn interrupting_a_line_discards_the_whole_entry() {
+    let line = |text: &str| ReadResult::Line(text.to_string());
+    let mut reader = ScriptedReader(VecDeque::from(vec![
+        line("fn f() -> int {"),
+        line("return 1"),
+        ReadResult::Interrupted,
+        line("<2"),
+        ReadResult::Interrupted,
+        line("<3 +"),
+        line("4"),
+    ]));
+    assert_eq!(repl::read_entry(&mut reader), ReadResult::Interrupted);
+    assert_eq!(repl::read_entry(&mut reader), line("<2"));
+    assert_eq!(repl::read_entry(&mut reader), ReadResult::Interrupted);
+    assert_eq!(repl::read_entry(&mut reader), line("<3 +\n4"));
+    assert_eq!(repl::read_entry(&mut reader), ReadResult::End);
+}
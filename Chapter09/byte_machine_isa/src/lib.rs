@@ -0,0 +1,360 @@
+extern crate nom;
+use nom::combinator::map;
+use nom::error::ErrorKind;
+use nom::number::complete::le_u16;
+use nom::number::complete::le_u8;
+use nom::Err;
+use nom::IResult;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Terminate(u8),
+    Set(u16),
+    Load(u16),
+    Store(u16),
+    IndirectLoad(u16),
+    IndirectStore(u16),
+    Input(u8),
+    Output(u8),
+    Add(u16),
+    Subtract(u16),
+    Multiply(u16),
+    Divide(u16),
+    Remainder(u16),
+    Jump(u16),
+    JumpIfZero(u16),
+    JumpIfNonZero(u16),
+    JumpIfPositive(u16),
+    JumpIfNegative(u16),
+    JumpIfNonPositive(u16),
+    JumpIfNonNegative(u16),
+    LoadByte(u16),
+    StoreByte(u16),
+    IndirectLoadByte(u16),
+    IndirectStoreByte(u16),
+    Byte(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    Byte,
+    Word,
+}
+
+impl OperandKind {
+    pub fn size(self) -> usize {
+        match self {
+            OperandKind::Byte => 1,
+            OperandKind::Word => 2,
+        }
+    }
+}
+
+pub struct OpcodeInfo {
+    // The name of the variant of the instruction.
+    pub name: &'static str,
+    // The text printed by the disassembler and read by the assembler.
+    pub mnemonic: &'static str,
+    pub operand: OperandKind,
+    build: fn(u16) -> Instruction,
+}
+
+// Every opcode is the index of its entry.
+pub const OPCODES: [OpcodeInfo; 24] = [
+    OpcodeInfo {
+        name: "Terminate",
+        mnemonic: "terminate",
+        operand: OperandKind::Byte,
+        build: |operand| Instruction::Terminate(operand as u8),
+    },
+    OpcodeInfo {
+        name: "Set",
+        mnemonic: "set",
+        operand: OperandKind::Word,
+        build: Instruction::Set,
+    },
+    OpcodeInfo {
+        name: "Load",
+        mnemonic: "load",
+        operand: OperandKind::Word,
+        build: Instruction::Load,
+    },
+    OpcodeInfo {
+        name: "Store",
+        mnemonic: "store",
+        operand: OperandKind::Word,
+        build: Instruction::Store,
+    },
+    OpcodeInfo {
+        name: "IndirectLoad",
+        mnemonic: "indirect load",
+        operand: OperandKind::Word,
+        build: Instruction::IndirectLoad,
+    },
+    OpcodeInfo {
+        name: "IndirectStore",
+        mnemonic: "indirect store",
+        operand: OperandKind::Word,
+        build: Instruction::IndirectStore,
+    },
+    OpcodeInfo {
+        name: "Input",
+        mnemonic: "input",
+        operand: OperandKind::Byte,
+        build: |operand| Instruction::Input(operand as u8),
+    },
+    OpcodeInfo {
+        name: "Output",
+        mnemonic: "output",
+        operand: OperandKind::Byte,
+        build: |operand| Instruction::Output(operand as u8),
+    },
+    OpcodeInfo {
+        name: "Add",
+        mnemonic: "add",
+        operand: OperandKind::Word,
+        build: Instruction::Add,
+    },
+    OpcodeInfo {
+        name: "Subtract",
+        mnemonic: "subtract",
+        operand: OperandKind::Word,
+        build: Instruction::Subtract,
+    },
+    OpcodeInfo {
+        name: "Multiply",
+        mnemonic: "multiply",
+        operand: OperandKind::Word,
+        build: Instruction::Multiply,
+    },
+    OpcodeInfo {
+        name: "Divide",
+        mnemonic: "divide",
+        operand: OperandKind::Word,
+        build: Instruction::Divide,
+    },
+    OpcodeInfo {
+        name: "Remainder",
+        mnemonic: "remainder",
+        operand: OperandKind::Word,
+        build: Instruction::Remainder,
+    },
+    OpcodeInfo {
+        name: "Jump",
+        mnemonic: "jump",
+        operand: OperandKind::Word,
+        build: Instruction::Jump,
+    },
+    OpcodeInfo {
+        name: "JumpIfZero",
+        mnemonic: "jump if zero",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfZero,
+    },
+    OpcodeInfo {
+        name: "JumpIfNonZero",
+        mnemonic: "jump if non-zero",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfNonZero,
+    },
+    OpcodeInfo {
+        name: "JumpIfPositive",
+        mnemonic: "jump if positive",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfPositive,
+    },
+    OpcodeInfo {
+        name: "JumpIfNegative",
+        mnemonic: "jump if negative",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfNegative,
+    },
+    OpcodeInfo {
+        name: "JumpIfNonPositive",
+        mnemonic: "jump if non-positive",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfNonPositive,
+    },
+    OpcodeInfo {
+        name: "JumpIfNonNegative",
+        mnemonic: "jump if non-negative",
+        operand: OperandKind::Word,
+        build: Instruction::JumpIfNonNegative,
+    },
+    OpcodeInfo {
+        name: "LoadByte",
+        mnemonic: "load byte",
+        operand: OperandKind::Word,
+        build: Instruction::LoadByte,
+    },
+    OpcodeInfo {
+        name: "StoreByte",
+        mnemonic: "store byte",
+        operand: OperandKind::Word,
+        build: Instruction::StoreByte,
+    },
+    OpcodeInfo {
+        name: "IndirectLoadByte",
+        mnemonic: "indirect load byte",
+        operand: OperandKind::Word,
+        build: Instruction::IndirectLoadByte,
+    },
+    OpcodeInfo {
+        name: "IndirectStoreByte",
+        mnemonic: "indirect store byte",
+        operand: OperandKind::Word,
+        build: Instruction::IndirectStoreByte,
+    },
+];
+
+// The mnemonic of the bytes that are not instructions.
+pub const DATA_BYTE_MNEMONIC: &str = "data byte";
+
+impl Instruction {
+    // Builds an instruction from its opcode and its operand,
+    // if the opcode exists and the operand fits in it.
+    pub fn from_parts(opcode: u8, operand: u16) -> Option<Instruction> {
+        let info = OPCODES.get(opcode as usize)?;
+        if info.operand == OperandKind::Byte && operand > u16::from(u8::MAX) {
+            return None;
+        }
+        Some((info.build)(operand))
+    }
+
+    pub fn operand(self) -> u16 {
+        use Instruction::*;
+        match self {
+            Terminate(byte) | Input(byte) | Output(byte) | Byte(byte) => u16::from(byte),
+            Set(word)
+            | Load(word)
+            | Store(word)
+            | IndirectLoad(word)
+            | IndirectStore(word)
+            | Add(word)
+            | Subtract(word)
+            | Multiply(word)
+            | Divide(word)
+            | Remainder(word)
+            | Jump(word)
+            | JumpIfZero(word)
+            | JumpIfNonZero(word)
+            | JumpIfPositive(word)
+            | JumpIfNegative(word)
+            | JumpIfNonPositive(word)
+            | JumpIfNonNegative(word)
+            | LoadByte(word)
+            | StoreByte(word)
+            | IndirectLoadByte(word)
+            | IndirectStoreByte(word) => word,
+        }
+    }
+
+    // The opcode is the index of the entry of the table building the same instruction
+    // from its operand, so the table is the only place where the opcodes are numbered.
+    // A data byte has no opcode.
+    pub fn opcode(self) -> Option<u8> {
+        let operand = self.operand();
+        OPCODES
+            .iter()
+            .position(|info| (info.build)(operand) == self)
+            .map(|opcode| opcode as u8)
+    }
+
+    pub fn info(self) -> Option<&'static OpcodeInfo> {
+        self.opcode().map(|opcode| &OPCODES[opcode as usize])
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(self) -> usize {
+        match self.info() {
+            Some(info) => 1 + info.operand.size(),
+            None => 1,
+        }
+    }
+
+    // The address the instruction may jump to, if it is a jump.
+    pub fn jump_target(self) -> Option<u16> {
+        use Instruction::*;
+        match self {
+            Jump(target)
+            | JumpIfZero(target)
+            | JumpIfNonZero(target)
+            | JumpIfPositive(target)
+            | JumpIfNegative(target)
+            | JumpIfNonPositive(target)
+            | JumpIfNonNegative(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    // Whether the following instruction may be executed after this one.
+    pub fn falls_through(self) -> bool {
+        !matches!(self, Instruction::Terminate(_) | Instruction::Jump(_))
+    }
+
+    // Appends the bytes of the instruction, with the operand in little-endian order.
+    pub fn encode(self, bytes: &mut Vec<u8>) {
+        let operand = self.operand();
+        match self.opcode() {
+            Some(opcode) => {
+                bytes.push(opcode);
+                match OPCODES[opcode as usize].operand {
+                    OperandKind::Byte => bytes.push(operand as u8),
+                    OperandKind::Word => bytes.extend_from_slice(&operand.to_le_bytes()),
+                }
+            }
+            None => bytes.push(operand as u8),
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.info() {
+            Some(info) => write!(f, "{} {}", info.mnemonic, self.operand()),
+            None => write!(f, "{} {}", DATA_BYTE_MNEMONIC, self.operand()),
+        }
+    }
+}
+
+// Parses an instruction, which is never a data byte.
+pub fn parse_instruction(input: &[u8]) -> IResult<&[u8], Instruction> {
+    let (rest, opcode) = le_u8(input)?;
+    let info = match OPCODES.get(opcode as usize) {
+        Some(info) => info,
+        None => return Err(Err::Error((input, ErrorKind::Tag))),
+    };
+    let (rest, operand) = match info.operand {
+        OperandKind::Byte => map(le_u8, u16::from)(rest)?,
+        OperandKind::Word => le_u16(rest)?,
+    };
+    Ok((rest, (info.build)(operand)))
+}
+
+// Decodes the instructions reachable from the entry point, following the jumps,
+// without executing them.
+// The decoder returns the instruction at an address with its size,
+// or why it cannot be decoded, which is kept, as it matters only if the address is reached.
+// The addresses wrap around, as the IP of the machines does.
+pub fn find_code<E>(
+    entry_point: u16,
+    mut decode: impl FnMut(u16) -> Result<(Instruction, u16), E>,
+) -> BTreeMap<u16, Result<Instruction, E>> {
+    let mut code = BTreeMap::new();
+    let mut pending = vec![entry_point];
+    while let Some(address) = pending.pop() {
+        if code.contains_key(&address) {
+            continue;
+        }
+        let instruction = decode(address).map(|(instruction, size)| {
+            pending.extend(instruction.jump_target());
+            if instruction.falls_through() {
+                pending.push(address.wrapping_add(size));
+            }
+            instruction
+        });
+        code.insert(address, instruction);
+    }
+    code
+}
@@ -0,0 +1,108 @@
+use byte_machine_isa::{parse_instruction, Instruction, OperandKind, OPCODES};
+
+const WORD_OPERANDS: [u16; 6] = [0, 1, 255, 256, 0x1234, u16::MAX];
+const BYTE_OPERANDS: [u16; 4] = [0, 1, 0x7F, 255];
+
+#[test]
+fn every_instruction_is_decoded_as_encoded() {
+    for (opcode, info) in OPCODES.iter().enumerate() {
+        let operands: &[u16] = match info.operand {
+            OperandKind::Byte => &BYTE_OPERANDS,
+            OperandKind::Word => &WORD_OPERANDS,
+        };
+        for &operand in operands {
+            let instruction = Instruction::from_parts(opcode as u8, operand).unwrap();
+            assert_eq!(instruction.opcode(), Some(opcode as u8));
+            assert_eq!(instruction.operand(), operand);
+            let mut bytes = Vec::new();
+            instruction.encode(&mut bytes);
+            assert_eq!(bytes.len(), instruction.len());
+            assert_eq!(bytes[0], opcode as u8);
+            bytes.push(0xAA);
+            let (rest, decoded) = parse_instruction(&bytes).unwrap();
+            assert_eq!(decoded, instruction);
+            assert_eq!(rest, [0xAA]);
+            assert_eq!(
+                instruction.to_string(),
+                format!("{} {}", info.mnemonic, operand)
+            );
+            assert!(format!("{:?}", instruction).starts_with(info.name));
+        }
+    }
+}
+
+#[test]
+fn every_byte_sequence_is_encoded_as_decoded() {
+    for opcode in 0..=u8::MAX {
+        let bytes = [opcode, 0x34, 0x12, 0x99];
+        match parse_instruction(&bytes) {
+            Ok((rest, instruction)) => {
+                let mut encoded = Vec::new();
+                instruction.encode(&mut encoded);
+                assert_eq!(encoded, bytes[..bytes.len() - rest.len()]);
+            }
+            Err(_) => assert!(opcode as usize >= OPCODES.len()),
+        }
+    }
+}
+
+#[test]
+fn truncated_instructions_are_rejected() {
+    for (opcode, info) in OPCODES.iter().enumerate() {
+        let bytes = [opcode as u8, 7];
+        let truncated = &bytes[..info.operand.size()];
+        assert!(parse_instruction(truncated).is_err(), "{}", info.mnemonic);
+    }
+    assert!(parse_instruction(&[]).is_err());
+}
+
+#[test]
+fn operands_must_fit_in_the_instruction() {
+    assert_eq!(Instruction::from_parts(0, 256), None);
+    assert_eq!(Instruction::from_parts(6, 300), None);
+    assert_eq!(Instruction::from_parts(24, 0), None);
+    assert_eq!(
+        Instruction::from_parts(7, 255),
+        Some(Instruction::Output(255))
+    );
+}
+
+#[test]
+fn data_bytes_are_not_instructions() {
+    let byte = Instruction::Byte(200);
+    assert_eq!(byte.opcode(), None);
+    assert_eq!(byte.len(), 1);
+    assert_eq!(byte.to_string(), "data byte 200");
+    let mut bytes = Vec::new();
+    byte.encode(&mut bytes);
+    assert_eq!(bytes, [200]);
+}
+
+#[test]
+fn mnemonics_are_unique() {
+    for (index, info) in OPCODES.iter().enumerate() {
+        assert!(OPCODES[..index]
+            .iter()
+            .all(|other| other.mnemonic != info.mnemonic && other.name != info.name));
+    }
+}
+
+#[test]
+fn only_jumps_have_targets() {
+    for (opcode, info) in OPCODES.iter().enumerate() {
+        let instruction = Instruction::from_parts(opcode as u8, 42).unwrap();
+        assert_eq!(
+            instruction.jump_target().is_some(),
+            info.mnemonic.starts_with("jump"),
+            "{}",
+            instruction
+        );
+        assert_eq!(
+            instruction.falls_through(),
+            info.mnemonic != "terminate" && info.mnemonic != "jump",
+            "{}",
+            instruction
+        );
+    }
+    assert_eq!(Instruction::JumpIfZero(7).jump_target(), Some(7));
+}
@@ -0,0 +1,133 @@
+// Prints the prime numbers less than the number typed by the user,
+// using the sieve of Eratosthenes.
+process size end
+
+    // Let the user input the digits of the limit number.
+    set digits
+    input 5
+    // Initialize digit pointer.
+    set digits
+    store pos
+    // If the digit is less than 0, parsing is ended.
+before_parsing_number:
+    indirect load byte pos
+    subtract ascii_zero
+    jump if negative after_parsing_number
+    // If the digit is greater than 9, parsing is ended.
+    indirect load byte pos
+    subtract ascii_zero
+    subtract number_base
+    jump if non-negative after_parsing_number
+    // Multiply by 10 the current limit.
+    load limit
+    multiply number_base
+    store limit
+    // Add next digit to current limit.
+    indirect load byte pos
+    subtract ascii_zero
+    add limit
+    store limit
+    // Increment digit pointer
+    load pos
+    add one
+    store pos
+    // If pos points to itself, the digit buffer is ended.
+    set pos
+    subtract pos
+    jump if non-zero before_parsing_number
+after_parsing_number:
+    load two
+    store i
+before_computing_primes:
+    load i
+    subtract limit
+    jump if non-negative after_computing_primes
+    set primes
+    add i
+    store pos
+    indirect load byte pos
+    jump if non-zero after_setting_multiples
+    load i
+    add i
+    store j
+before_setting_multiples:
+    subtract limit
+    jump if non-negative after_setting_multiples
+    set primes
+    add j
+    store pos
+    load one
+    indirect store byte pos
+    load j
+    add i
+    store j
+    jump before_setting_multiples
+after_setting_multiples:
+    load i
+    add one
+    store i
+    jump before_computing_primes
+after_computing_primes:
+    load two
+    store i
+before_printing_primes:
+    load i
+    subtract limit
+    jump if non-negative after_printing_all_primes
+    set primes
+    add i
+    store pos
+    indirect load byte pos
+    jump if non-zero after_printing_a_prime
+    // Format a prime number
+    load i
+    store j
+    set pos
+    store pos
+before_generating_digits:
+    load pos
+    subtract one
+    store pos
+    load j
+    remainder number_base
+    add ascii_zero
+    indirect store byte pos
+    load j
+    divide number_base
+    store j
+    jump if non-zero before_generating_digits
+    // Clear the initial spaces.
+before_clearing_spaces:
+    set digits
+    subtract pos
+    jump if zero after_clearing_spaces
+    load pos
+    subtract one
+    store pos
+    set 32 // blank
+    indirect store byte pos
+    jump before_clearing_spaces
+after_clearing_spaces:
+    // Emit the prime number.
+    set digits
+    output 5
+after_printing_a_prime:
+    load i
+    add one
+    store i
+    jump before_printing_primes
+after_printing_all_primes:
+    terminate 0
+
+// data
+limit: word 0
+i: word 0
+j: word 0
+digits: array 5
+pos: word 0
+number_base: word 10
+ascii_zero: word 48
+one: word 1
+two: word 2
+primes: array 400
+end:
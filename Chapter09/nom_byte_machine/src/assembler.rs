@@ -0,0 +1,229 @@
+use crate::instructions::{Instruction, OPCODES};
+use byte_machine_isa::{OperandKind, DATA_BYTE_MNEMONIC};
+use std::collections::HashMap;
+
+// The code starts after the word containing the process size.
+const CODE_ADDRESS: u32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+enum Operand<'a> {
+    Number(u16),
+    Label(&'a str),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Statement<'a> {
+    Instruction(u8, OperandKind, Operand<'a>),
+    Word(Operand<'a>),
+    Byte(Operand<'a>),
+    Array(u16),
+    ProcessSize(Operand<'a>),
+}
+
+impl<'a> Statement<'a> {
+    fn len(self) -> u32 {
+        match self {
+            Statement::Instruction(_, operand_kind, _) => 1 + operand_kind.size() as u32,
+            Statement::Word(_) => 2,
+            Statement::Byte(_) => 1,
+            Statement::Array(size) => u32::from(size),
+            Statement::ProcessSize(_) => 0,
+        }
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_number(text: &str) -> Result<u16, String> {
+    text.parse::<u16>()
+        .map_err(|_| format!("The number {} does not fit in a word.", text))
+}
+
+fn parse_operand(text: &str) -> Result<Operand<'_>, String> {
+    if text.chars().all(|ch| ch.is_ascii_digit()) {
+        parse_number(text).map(Operand::Number)
+    } else if is_identifier(text) {
+        Ok(Operand::Label(text))
+    } else {
+        Err(format!("Invalid operand '{}'.", text))
+    }
+}
+
+// Every statement has exactly one operand, which is the last word of the line.
+fn parse_statement<'a>(mnemonic: &str, operand: &'a str) -> Result<Statement<'a>, String> {
+    if let Some(opcode) = OPCODES.iter().position(|info| info.mnemonic == mnemonic) {
+        return Ok(Statement::Instruction(
+            opcode as u8,
+            OPCODES[opcode].operand,
+            parse_operand(operand)?,
+        ));
+    }
+    match mnemonic {
+        "word" => Ok(Statement::Word(parse_operand(operand)?)),
+        "byte" | DATA_BYTE_MNEMONIC => Ok(Statement::Byte(parse_operand(operand)?)),
+        "array" => Ok(Statement::Array(parse_number(operand)?)),
+        "process size" => Ok(Statement::ProcessSize(parse_operand(operand)?)),
+        _ => Err(format!("Unknown mnemonic '{}'.", mnemonic)),
+    }
+}
+
+// A line is made of any number of prefixes, followed by an optional statement,
+// and by an optional comment starting with "//".
+// A prefix is a label, like "loop:", or an address, like "145:",
+// which must be the address of the statement.
+// So, the output of the disassembler can be assembled again.
+#[derive(Debug, Default)]
+struct Line<'a> {
+    labels: Vec<&'a str>,
+    addresses: Vec<u16>,
+    statement: Option<Statement<'a>>,
+}
+
+fn parse_line(line: &str) -> Result<Line<'_>, String> {
+    let mut rest = match line.find("//") {
+        Some(comment_start) => &line[..comment_start],
+        None => line,
+    }
+    .trim();
+    let mut parsed_line = Line::default();
+    while let Some(colon) = rest.find(':') {
+        let prefix = rest[..colon].trim_end();
+        if prefix.chars().all(|ch| ch.is_ascii_digit()) && !prefix.is_empty() {
+            parsed_line.addresses.push(parse_number(prefix)?);
+        } else if is_identifier(prefix) {
+            parsed_line.labels.push(prefix);
+        } else {
+            return Err(format!("Invalid label '{}'.", prefix));
+        }
+        rest = rest[colon + 1..].trim_start();
+    }
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    parsed_line.statement = match words.split_last() {
+        None => None,
+        Some((_, [])) => return Err(format!("Missing operand after '{}'.", rest)),
+        Some((operand, mnemonic)) => Some(parse_statement(&mnemonic.join(" "), operand)?),
+    };
+    Ok(parsed_line)
+}
+
+fn resolve(labels: &HashMap<&str, u16>, operand: Operand<'_>) -> Result<u16, String> {
+    match operand {
+        Operand::Number(number) => Ok(number),
+        Operand::Label(label) => labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| format!("Undefined label '{}'.", label)),
+    }
+}
+
+fn resolve_byte(labels: &HashMap<&str, u16>, operand: Operand<'_>) -> Result<u8, String> {
+    let value = resolve(labels, operand)?;
+    if value > 255 {
+        return Err(format!("The value {} does not fit in a byte.", value));
+    }
+    Ok(value as u8)
+}
+
+// Assembles a program written with the mnemonics printed by the disassembler,
+// returning the binary image accepted by the emulator.
+// The first pass computes the addresses of the labels,
+// and the second pass emits the bytes, with the labels resolved.
+// The arrays at the end of the program are not emitted,
+// as the process is filled with zeros beyond the image.
+// If the process size is not specified, it is the size of the program,
+// including those arrays.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut statements = Vec::<(Statement, usize)>::new();
+    let mut labels = HashMap::<&str, u16>::new();
+    let mut address = CODE_ADDRESS;
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let in_line = |err: String| format!("Line {}: {}", line_number, err);
+        let parsed_line = parse_line(line).map_err(in_line)?;
+        for expected_address in parsed_line.addresses {
+            if u32::from(expected_address) != address {
+                return Err(in_line(format!(
+                    "The address is {}, not {}.",
+                    address, expected_address
+                )));
+            }
+        }
+        for label in parsed_line.labels {
+            if labels.insert(label, address as u16).is_some() {
+                return Err(in_line(format!("Duplicate label '{}'.", label)));
+            }
+        }
+        if let Some(statement) = parsed_line.statement {
+            address += statement.len();
+            if address > 0x10000 {
+                return Err(in_line("The program does not fit in memory.".to_string()));
+            }
+            statements.push((statement, line_number));
+        }
+    }
+    let program_size = address;
+
+    let mut image = vec![0u8; CODE_ADDRESS as usize];
+    let mut image_end = image.len();
+    let mut process_size = None;
+    for (statement, line_number) in statements {
+        let in_line = |err: String| format!("Line {}: {}", line_number, err);
+        match statement {
+            Statement::Instruction(opcode, operand_kind, operand) => {
+                let value = match operand_kind {
+                    OperandKind::Byte => {
+                        u16::from(resolve_byte(&labels, operand).map_err(in_line)?)
+                    }
+                    OperandKind::Word => resolve(&labels, operand).map_err(in_line)?,
+                };
+                // The opcode comes from the table, and the operand fits in it.
+                Instruction::from_parts(opcode, value)
+                    .unwrap()
+                    .encode(&mut image);
+            }
+            Statement::Word(operand) => {
+                let value = resolve(&labels, operand).map_err(in_line)?;
+                image.extend_from_slice(&value.to_le_bytes());
+            }
+            Statement::Byte(operand) => {
+                image.push(resolve_byte(&labels, operand).map_err(in_line)?);
+            }
+            Statement::Array(size) => {
+                image.resize(image.len() + size as usize, 0);
+                continue;
+            }
+            Statement::ProcessSize(operand) => {
+                if process_size.is_some() {
+                    return Err(in_line("Duplicate process size.".to_string()));
+                }
+                let size = resolve(&labels, operand).map_err(in_line)?;
+                if u32::from(size) < program_size {
+                    return Err(in_line(format!(
+                        "The process size {} is smaller than the program size {}.",
+                        size, program_size
+                    )));
+                }
+                process_size = Some(size);
+                continue;
+            }
+        }
+        image_end = image.len();
+    }
+    image.truncate(image_end);
+
+    let process_size = match process_size {
+        Some(size) => size,
+        None if program_size < 0x10000 => program_size as u16,
+        None => return Err("The process size does not fit in a word.".to_string()),
+    };
+    image[..CODE_ADDRESS as usize].copy_from_slice(&process_size.to_le_bytes());
+    Ok(image)
+}
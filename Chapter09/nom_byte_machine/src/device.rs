@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Result, Write};
+use std::path::Path;
+
+// The device used by the input and output instructions.
+pub trait IoDevice {
+    // Reads a line, including its terminator.
+    // At the end of the input, the line is empty.
+    fn read_line(&mut self) -> Result<Vec<u8>>;
+    fn write(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+fn read_line_from(input: &mut dyn BufRead) -> Result<Vec<u8>> {
+    let mut line = Vec::new();
+    input.read_until(b'\n', &mut line)?;
+    Ok(line)
+}
+
+// The standard input and the standard output.
+// The standard input is not locked between the lines,
+// so that it can be shared with the monitor.
+#[derive(Debug, Default)]
+pub struct ConsoleDevice;
+
+impl IoDevice for ConsoleDevice {
+    fn read_line(&mut self) -> Result<Vec<u8>> {
+        read_line_from(&mut std::io::stdin().lock())
+    }
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(bytes)?;
+        stdout.flush()
+    }
+}
+
+// A scripted input, and an output kept in memory, to run programs headlessly.
+#[derive(Debug, Default)]
+pub struct BufferDevice {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl BufferDevice {
+    pub fn new(input: &str) -> BufferDevice {
+        BufferDevice {
+            input: Cursor::new(input.as_bytes().to_vec()),
+            output: Vec::new(),
+        }
+    }
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+    pub fn output_text(&self) -> String {
+        String::from_utf8_lossy(&self.output).to_string()
+    }
+}
+
+impl IoDevice for BufferDevice {
+    fn read_line(&mut self) -> Result<Vec<u8>> {
+        read_line_from(&mut self.input)
+    }
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+// An input file and an output file.
+// If a path is missing, the console is used in its place.
+pub struct FileDevice {
+    input: Option<BufReader<File>>,
+    output: Option<File>,
+}
+
+impl FileDevice {
+    pub fn open(input_path: Option<&Path>, output_path: Option<&Path>) -> Result<FileDevice> {
+        Ok(FileDevice {
+            input: match input_path {
+                Some(path) => Some(BufReader::new(File::open(path)?)),
+                None => None,
+            },
+            output: match output_path {
+                Some(path) => Some(File::create(path)?),
+                None => None,
+            },
+        })
+    }
+}
+
+impl IoDevice for FileDevice {
+    fn read_line(&mut self) -> Result<Vec<u8>> {
+        match &mut self.input {
+            Some(input) => read_line_from(input),
+            None => ConsoleDevice.read_line(),
+        }
+    }
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        match &mut self.output {
+            Some(output) => output.write_all(bytes),
+            None => ConsoleDevice.write(bytes),
+        }
+    }
+}
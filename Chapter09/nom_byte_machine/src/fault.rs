@@ -0,0 +1,78 @@
+use std::fmt;
+
+// The reasons why a program cannot be loaded or cannot go on running.
+// The runtime faults carry the address of the faulting instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    // The image is too short to contain the process size.
+    MissingProcessSize,
+    // The process size, and the size of the image, which does not fit in the process.
+    ProcessTooSmall(u16, usize),
+    // The IP, and the byte or the word that is not an opcode.
+    InvalidOpcode(u16, u16),
+    // The IP, and the operand that does not fit in the byte operand of the instruction.
+    OperandTooLarge(u16, u16),
+    // The IP, and the first address that is outside the process.
+    OutOfBounds(u16, usize),
+    // The IP of the division or of the remainder.
+    DivisionByZero(u16),
+    // The IP of the instruction that was not executed, and the step limit.
+    StepLimitExceeded(u16, u64),
+    // The IP of the input or output instruction, and the kind of the error of the device.
+    DeviceFailure(u16, std::io::ErrorKind),
+}
+
+impl Fault {
+    pub fn ip(self) -> Option<u16> {
+        match self {
+            Fault::MissingProcessSize | Fault::ProcessTooSmall(_, _) => None,
+            Fault::InvalidOpcode(ip, _)
+            | Fault::OperandTooLarge(ip, _)
+            | Fault::OutOfBounds(ip, _)
+            | Fault::DivisionByZero(ip)
+            | Fault::StepLimitExceeded(ip, _)
+            | Fault::DeviceFailure(ip, _) => Some(ip),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::MissingProcessSize => write!(f, "The program has no process size."),
+            Fault::ProcessTooSmall(process_size, program_size) => write!(
+                f,
+                "The process size {} is smaller than the program size {}.",
+                process_size, program_size
+            ),
+            Fault::InvalidOpcode(ip, opcode) => {
+                write!(f, "Invalid opcode {} at address {}.", opcode, ip)
+            }
+            Fault::OperandTooLarge(ip, operand) => write!(
+                f,
+                "The operand {} of the instruction at address {} does not fit in a byte.",
+                operand, ip
+            ),
+            Fault::OutOfBounds(ip, address) => write!(
+                f,
+                "The instruction at address {} accesses address {}, outside the process.",
+                ip, address
+            ),
+            Fault::DivisionByZero(ip) => {
+                write!(f, "Division by zero at address {}.", ip)
+            }
+            Fault::StepLimitExceeded(ip, step_limit) => write!(
+                f,
+                "The program has not terminated in {} steps, at address {}.",
+                step_limit, ip
+            ),
+            Fault::DeviceFailure(ip, kind) => write!(
+                f,
+                "The device of the instruction at address {} failed: {}.",
+                ip, kind
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
@@ -0,0 +1,11 @@
+pub mod assembler;
+pub mod device;
+pub mod emulator;
+pub mod fault;
+pub mod instructions;
+pub mod machine;
+pub mod monitor;
+pub mod parsing_interpreter;
+pub mod translator;
+pub mod word_converter;
+pub mod word_machine;
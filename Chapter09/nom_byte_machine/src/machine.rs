@@ -0,0 +1,261 @@
+use crate::device::IoDevice;
+use crate::fault::Fault;
+use crate::instructions::Instruction;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RegisterSet {
+    pub ip: u16,
+    pub acc: u16,
+}
+
+impl Default for RegisterSet {
+    fn default() -> RegisterSet {
+        RegisterSet::new()
+    }
+}
+
+impl RegisterSet {
+    // The execution starts after the process size.
+    pub fn new() -> RegisterSet {
+        RegisterSet { ip: 2, acc: 0 }
+    }
+}
+
+// A machine running the instructions of the byte machine on its memory,
+// which is made of cells, like bytes or words.
+// The machines only differ in how they decode the instructions and access the cells,
+// so they share the execution of the instructions, with the same faults.
+pub trait Machine {
+    fn registers(&mut self) -> &mut RegisterSet;
+
+    // The number of cells of the memory.
+    fn memory_size(&self) -> usize;
+
+    // The number of cells of a word, and of an instruction.
+    fn word_size(&self) -> usize;
+    fn instruction_size(&self, instruction: Instruction) -> u16;
+
+    // Decodes the instruction at the given address,
+    // returning a fault if it is invalid or outside the memory.
+    fn fetch(&mut self, ip: u16) -> Result<Instruction, Fault>;
+
+    // The accesses to the cells, whose addresses have already been checked.
+    // A byte is the content of a cell, truncated if the cell is larger.
+    fn load_word(&self, address: usize) -> u16;
+    fn store_word(&mut self, address: usize, word: u16);
+    fn load_byte(&self, address: usize) -> u8;
+    fn store_byte(&mut self, address: usize, byte: u8);
+
+    // Executes the given instruction, which is at the IP,
+    // returning the return code if it terminates the program.
+    fn execute(
+        &mut self,
+        instruction: Instruction,
+        device: &mut dyn IoDevice,
+    ) -> Result<Option<u8>, Fault> {
+        use Instruction::*;
+        let RegisterSet { ip, acc } = *self.registers();
+        let mut next_ip = ip.wrapping_add(self.instruction_size(instruction));
+        let mut next_acc = acc;
+        match instruction {
+            Terminate(operand) => {
+                self.registers().ip = next_ip;
+                return Ok(Some(operand));
+            }
+            Set(operand) => next_acc = operand,
+            Load(address) => next_acc = get_word(self, ip, address)?,
+            Store(address) => set_word(self, ip, address, acc)?,
+            IndirectLoad(address) => {
+                let pointer = get_word(self, ip, address)?;
+                next_acc = get_word(self, ip, pointer)?;
+            }
+            IndirectStore(address) => {
+                let pointer = get_word(self, ip, address)?;
+                set_word(self, ip, pointer, acc)?;
+            }
+            Input(length) => input_line(self, device, ip, acc, length)?,
+            Output(length) => output_bytes(self, device, ip, acc, length)?,
+            Add(address) => next_acc = acc.wrapping_add(get_word(self, ip, address)?),
+            Subtract(address) => next_acc = acc.wrapping_sub(get_word(self, ip, address)?),
+            Multiply(address) => next_acc = acc.wrapping_mul(get_word(self, ip, address)?),
+            Divide(address) => {
+                next_acc = acc.wrapping_div(check_divisor(ip, get_word(self, ip, address)?)?)
+            }
+            Remainder(address) => {
+                next_acc = acc.wrapping_rem(check_divisor(ip, get_word(self, ip, address)?)?)
+            }
+            Jump(address) => next_ip = address,
+            JumpIfZero(address) => {
+                if acc == 0 {
+                    next_ip = address;
+                }
+            }
+            JumpIfNonZero(address) => {
+                if acc != 0 {
+                    next_ip = address;
+                }
+            }
+            JumpIfPositive(address) => {
+                if (acc as i16) > 0 {
+                    next_ip = address;
+                }
+            }
+            JumpIfNegative(address) => {
+                if (acc as i16) < 0 {
+                    next_ip = address;
+                }
+            }
+            JumpIfNonPositive(address) => {
+                if acc as i16 <= 0 {
+                    next_ip = address;
+                }
+            }
+            JumpIfNonNegative(address) => {
+                if acc as i16 >= 0 {
+                    next_ip = address;
+                }
+            }
+            LoadByte(address) => next_acc = u16::from(get_byte(self, ip, address)?),
+            StoreByte(address) => set_byte(self, ip, address, acc as u8)?,
+            IndirectLoadByte(address) => {
+                let pointer = get_word(self, ip, address)?;
+                next_acc = u16::from(get_byte(self, ip, pointer)?);
+            }
+            IndirectStoreByte(address) => {
+                let pointer = get_word(self, ip, address)?;
+                set_byte(self, ip, pointer, acc as u8)?;
+            }
+            // A data byte is skipped.
+            Byte(_) => {}
+        }
+        let registers = self.registers();
+        registers.ip = next_ip;
+        registers.acc = next_acc;
+        Ok(None)
+    }
+
+    // Executes the instruction at the IP,
+    // returning the return code if it terminates the program.
+    fn step(&mut self, device: &mut dyn IoDevice) -> Result<Option<u8>, Fault> {
+        let ip = self.registers().ip;
+        let instruction = self.fetch(ip)?;
+        self.execute(instruction, device)
+    }
+
+    // Decodes the instructions reachable from the entry point, following the jumps,
+    // without executing them.
+    // The addresses that cannot be decoded keep their fault, raised only if they are reached.
+    fn find_code(&mut self, entry_point: u16) -> BTreeMap<u16, Result<Instruction, Fault>> {
+        byte_machine_isa::find_code(entry_point, |ip| {
+            let instruction = self.fetch(ip)?;
+            Ok((instruction, self.instruction_size(instruction)))
+        })
+    }
+
+    // Executes at most the given number of instructions, using the given device.
+    fn run(&mut self, device: &mut dyn IoDevice, step_limit: u64) -> Result<u8, Fault> {
+        let mut steps = 0;
+        loop {
+            if steps == step_limit {
+                return Err(Fault::StepLimitExceeded(self.registers().ip, step_limit));
+            }
+            steps += 1;
+            if let Some(return_code) = self.step(device)? {
+                return Ok(return_code);
+            }
+        }
+    }
+}
+
+// The memory accesses outside the process are faults of the instruction at the given IP.
+fn check_range<M: Machine + ?Sized>(
+    machine: &M,
+    ip: u16,
+    address: usize,
+    length: usize,
+) -> Result<(), Fault> {
+    if address + length > machine.memory_size() {
+        return Err(Fault::OutOfBounds(ip, address.max(machine.memory_size())));
+    }
+    Ok(())
+}
+
+fn get_word<M: Machine + ?Sized>(machine: &M, ip: u16, address: u16) -> Result<u16, Fault> {
+    check_range(machine, ip, address as usize, machine.word_size())?;
+    Ok(machine.load_word(address as usize))
+}
+
+fn set_word<M: Machine + ?Sized>(
+    machine: &mut M,
+    ip: u16,
+    address: u16,
+    word: u16,
+) -> Result<(), Fault> {
+    check_range(machine, ip, address as usize, machine.word_size())?;
+    machine.store_word(address as usize, word);
+    Ok(())
+}
+
+fn get_byte<M: Machine + ?Sized>(machine: &M, ip: u16, address: u16) -> Result<u8, Fault> {
+    check_range(machine, ip, address as usize, 1)?;
+    Ok(machine.load_byte(address as usize))
+}
+
+fn set_byte<M: Machine + ?Sized>(
+    machine: &mut M,
+    ip: u16,
+    address: u16,
+    byte: u8,
+) -> Result<(), Fault> {
+    check_range(machine, ip, address as usize, 1)?;
+    machine.store_byte(address as usize, byte);
+    Ok(())
+}
+
+fn check_divisor(ip: u16, divisor: u16) -> Result<u16, Fault> {
+    if divisor == 0 {
+        return Err(Fault::DivisionByZero(ip));
+    }
+    Ok(divisor)
+}
+
+// Reads a line into the cells, one byte per cell, truncating it or padding it with zeros.
+fn input_line<M: Machine + ?Sized>(
+    machine: &mut M,
+    device: &mut dyn IoDevice,
+    ip: u16,
+    address: u16,
+    length: u8,
+) -> Result<(), Fault> {
+    let (address, length) = (address as usize, length as usize);
+    check_range(machine, ip, address, length)?;
+    let text = device
+        .read_line()
+        .map_err(|err| Fault::DeviceFailure(ip, err.kind()))?;
+    for index in 0..length {
+        machine.store_byte(address + index, text.get(index).copied().unwrap_or(0));
+    }
+    Ok(())
+}
+
+// Writes the bytes of the cells, showing the zeros as blanks.
+fn output_bytes<M: Machine + ?Sized>(
+    machine: &mut M,
+    device: &mut dyn IoDevice,
+    ip: u16,
+    address: u16,
+    length: u8,
+) -> Result<(), Fault> {
+    let (address, length) = (address as usize, length as usize);
+    check_range(machine, ip, address, length)?;
+    let text: Vec<u8> = (address..address + length)
+        .map(|address| match machine.load_byte(address) {
+            0 => b' ',
+            byte => byte,
+        })
+        .collect();
+    device
+        .write(&text)
+        .map_err(|err| Fault::DeviceFailure(ip, err.kind()))
+}
@@ -0,0 +1,389 @@
+use crate::device::IoDevice;
+use crate::emulator::{self, ByteMachine, RegisterSet};
+use crate::fault::Fault;
+use crate::instructions::{fetch_instruction, Instruction};
+use crate::machine::Machine;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+// The number of bytes dumped when no length is given, and the bytes per row.
+const DUMP_LENGTH: usize = 64;
+const DUMP_ROW_LENGTH: usize = 16;
+
+const HELP: &str = "Commands:
+  step [N] | s [N] | <empty line>  Execute the next instruction, or the next N
+  continue | c                     Run until a breakpoint, a watchpoint or the end
+  break ADDR | b ADDR              Stop before executing the instruction at ADDR
+  delete ADDR | d ADDR             Remove the breakpoint at ADDR
+  watch ADDR | w ADDR              Stop when the byte at ADDR changes
+  unwatch ADDR | u ADDR            Remove the watchpoint at ADDR
+  registers | r                    Show the IP and the accumulator
+  dump ADDR [LEN] | x ADDR [LEN]   Show LEN bytes of memory, starting from ADDR
+  help | h                         Show this help
+  quit | q                         Stop the program
+Addresses are decimal, or hexadecimal with the prefix 0x.
+After a fault, the program can be inspected, and resuming it ends it.";
+
+// The source of the commands of the monitor.
+// The end of the commands lets the program run to its end.
+pub trait CommandReader {
+    fn read_command(&mut self) -> Option<String>;
+}
+
+// The standard input is not locked between the commands,
+// as the input instructions of the program read it too.
+impl CommandReader for std::io::Stdin {
+    fn read_command(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line),
+        }
+    }
+}
+
+impl<'a> CommandReader for std::str::Lines<'a> {
+    fn read_command(&mut self) -> Option<String> {
+        self.next().map(str::to_string)
+    }
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse::<u16>(),
+    };
+    parsed.map_err(|_| format!("Invalid address '{}'.", text))
+}
+
+fn check_address(process: &[u8], text: &str) -> Result<u16, String> {
+    let address = parse_address(text)?;
+    if address as usize >= process.len() {
+        return Err(format!("Address {} is outside the process.", address));
+    }
+    Ok(address)
+}
+
+// Shows the memory in rows of hexadecimal bytes, followed by their printable characters.
+pub fn dump_memory(process: &[u8], start: u16, length: usize) -> String {
+    let start = start as usize;
+    let end = (start + length).min(process.len());
+    let mut result = String::new();
+    for row_start in (start..end).step_by(DUMP_ROW_LENGTH) {
+        let row = &process[row_start..(row_start + DUMP_ROW_LENGTH).min(end)];
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = row
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        result += &format!(
+            "{:5}: {:<width$} |{}|\n",
+            row_start,
+            hex.join(" "),
+            text,
+            width = DUMP_ROW_LENGTH * 3 - 1
+        );
+    }
+    result
+}
+
+fn describe_registers(registers: &RegisterSet) -> String {
+    format!(
+        "ip: {}, acc: {} ({})",
+        registers.ip, registers.acc, registers.acc as i16
+    )
+}
+
+// Runs a program under the control of the commands,
+// writing the state of the program to the given output.
+// The input and output instructions use the given device.
+pub struct Monitor<'a> {
+    commands: &'a mut dyn CommandReader,
+    output: &'a mut dyn Write,
+    device: &'a mut dyn IoDevice,
+    breakpoints: Vec<u16>,
+    watchpoints: Vec<u16>,
+    // The number of instructions to execute before stopping, if stepping.
+    steps_before_stop: Option<u64>,
+}
+
+impl<'a> Monitor<'a> {
+    pub fn new(
+        commands: &'a mut dyn CommandReader,
+        output: &'a mut dyn Write,
+        device: &'a mut dyn IoDevice,
+    ) -> Monitor<'a> {
+        Monitor {
+            commands,
+            output,
+            device,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            steps_before_stop: Some(0),
+        }
+    }
+
+    // The monitor has nowhere to report its own write errors.
+    fn say(&mut self, text: &str) {
+        let _ = writeln!(self.output, "{}", text);
+    }
+
+    // Returns the return code of the program, or None if it has been quit.
+    pub fn run(&mut self, program: &[u8]) -> Result<Option<u8>, Fault> {
+        let mut process = emulator::load_program(program)?;
+        let mut registers = RegisterSet::new();
+        loop {
+            let stop = match self.steps_before_stop {
+                Some(0) => true,
+                _ => self.breakpoints.contains(&registers.ip),
+            };
+            if stop {
+                self.show_position(&process, &registers);
+                if !self.read_commands(&process, &registers) {
+                    self.say("Program stopped by the monitor.");
+                    return Ok(None);
+                }
+            }
+            let watched: Vec<u8> = self
+                .watchpoints
+                .iter()
+                .map(|&address| process[address as usize])
+                .collect();
+            let ip = registers.ip;
+            let result = emulator::step(&mut process, &mut registers, self.device);
+            if let Some(steps) = &mut self.steps_before_stop {
+                *steps -= 1;
+            }
+            match result {
+                Ok(Some(return_code)) => {
+                    self.say(&format!(
+                        "Program terminated with return code {}.",
+                        return_code
+                    ));
+                    return Ok(Some(return_code));
+                }
+                Ok(None) => {}
+                Err(fault) => {
+                    // The faulting instruction has not changed the state, which can be inspected.
+                    self.say(&format!("Fault: {}", fault));
+                    self.show_position(&process, &registers);
+                    self.read_commands(&process, &registers);
+                    return Err(fault);
+                }
+            }
+            for (index, &old_value) in watched.iter().enumerate() {
+                let address = self.watchpoints[index];
+                let new_value = process[address as usize];
+                if new_value != old_value {
+                    self.say(&format!(
+                        "Watchpoint at address {}: {} -> {}, written by the instruction at address {}.",
+                        address, old_value, new_value, ip
+                    ));
+                    self.steps_before_stop = Some(0);
+                }
+            }
+        }
+    }
+
+    fn show_position(&mut self, process: &[u8], registers: &RegisterSet) {
+        let instruction = match fetch_instruction(process, registers.ip) {
+            Ok(instruction) => instruction.to_string(),
+            Err(fault) => fault.to_string(),
+        };
+        self.say(&format!(
+            "{:5}: {} | acc: {}",
+            registers.ip, instruction, registers.acc
+        ));
+    }
+
+    // Executes the commands until one resumes the program.
+    // Returns false if the program must be stopped.
+    fn read_commands(&mut self, process: &[u8], registers: &RegisterSet) -> bool {
+        loop {
+            let _ = write!(self.output, "(monitor) ");
+            let _ = self.output.flush();
+            let line = match self.commands.read_command() {
+                Some(line) => line,
+                None => {
+                    self.say("");
+                    self.breakpoints.clear();
+                    self.watchpoints.clear();
+                    self.steps_before_stop = None;
+                    return true;
+                }
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let result = match words.as_slice() {
+                [] | ["step"] | ["s"] => {
+                    self.steps_before_stop = Some(1);
+                    return true;
+                }
+                ["step", count] | ["s", count] => match count.parse::<u64>() {
+                    Ok(count) if count > 0 => {
+                        self.steps_before_stop = Some(count);
+                        return true;
+                    }
+                    _ => Err(format!("Invalid step count '{}'.", count)),
+                },
+                ["continue"] | ["c"] => {
+                    self.steps_before_stop = None;
+                    return true;
+                }
+                ["break", address] | ["b", address] => {
+                    check_address(process, address).map(|address| {
+                        if !self.breakpoints.contains(&address) {
+                            self.breakpoints.push(address);
+                        }
+                        format!("Breakpoint at address {}.", address)
+                    })
+                }
+                ["delete", address] | ["d", address] => {
+                    parse_address(address).and_then(|address| {
+                        match self.breakpoints.iter().position(|&a| a == address) {
+                            Some(index) => {
+                                self.breakpoints.remove(index);
+                                Ok(format!("Breakpoint at address {} deleted.", address))
+                            }
+                            None => Err(format!("No breakpoint at address {}.", address)),
+                        }
+                    })
+                }
+                ["watch", address] | ["w", address] => {
+                    check_address(process, address).map(|address| {
+                        if !self.watchpoints.contains(&address) {
+                            self.watchpoints.push(address);
+                        }
+                        format!(
+                            "Watching address {}, containing {}.",
+                            address, process[address as usize]
+                        )
+                    })
+                }
+                ["unwatch", address] | ["u", address] => {
+                    parse_address(address).and_then(|address| {
+                        match self.watchpoints.iter().position(|&a| a == address) {
+                            Some(index) => {
+                                self.watchpoints.remove(index);
+                                Ok(format!("Address {} no longer watched.", address))
+                            }
+                            None => Err(format!("Address {} is not watched.", address)),
+                        }
+                    })
+                }
+                ["registers"] | ["r"] => Ok(describe_registers(registers)),
+                ["dump", address] | ["x", address] => check_address(process, address)
+                    .map(|address| dump_memory(process, address, DUMP_LENGTH)),
+                ["dump", address, length] | ["x", address, length] => {
+                    check_address(process, address).and_then(|address| {
+                        length
+                            .parse::<usize>()
+                            .map(|length| dump_memory(process, address, length))
+                            .map_err(|_| format!("Invalid length '{}'.", length))
+                    })
+                }
+                ["help"] | ["h"] => Ok(HELP.to_string()),
+                ["quit"] | ["q"] => return false,
+                _ => Err("Unknown command. Type 'help' for the list of commands.".to_string()),
+            };
+            match result {
+                Ok(text) => self.say(text.trim_end()),
+                Err(err) => self.say(&err),
+            }
+        }
+    }
+}
+
+// How many times every instruction has been executed.
+#[derive(Debug, Default)]
+pub struct Trace {
+    counts: BTreeMap<u16, (u64, Instruction)>,
+}
+
+impl Trace {
+    pub fn count(&self, address: u16) -> u64 {
+        self.counts.get(&address).map_or(0, |entry| entry.0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().map(|entry| entry.0).sum()
+    }
+
+    // Writes a CSV table, with a row for every executed address.
+    // A self-modified address shows the last instruction executed there.
+    pub fn export(&self, output: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(output, "address,count,instruction")?;
+        for (address, (count, instruction)) in &self.counts {
+            writeln!(output, "{},{},{}", address, count, instruction)?;
+        }
+        Ok(())
+    }
+}
+
+// A byte machine counting the executions of its instructions.
+// The machine fetches only the instruction at the IP, just before executing it,
+// so the fetched instructions are the executed ones.
+struct TracingMachine<'a> {
+    machine: ByteMachine<'a>,
+    trace: &'a mut Trace,
+}
+
+impl<'a> Machine for TracingMachine<'a> {
+    fn registers(&mut self) -> &mut RegisterSet {
+        self.machine.registers()
+    }
+    fn memory_size(&self) -> usize {
+        self.machine.memory_size()
+    }
+    fn word_size(&self) -> usize {
+        self.machine.word_size()
+    }
+    fn instruction_size(&self, instruction: Instruction) -> u16 {
+        self.machine.instruction_size(instruction)
+    }
+    fn fetch(&mut self, ip: u16) -> Result<Instruction, Fault> {
+        let instruction = self.machine.fetch(ip)?;
+        let entry = self.trace.counts.entry(ip).or_insert((0, instruction));
+        *entry = (entry.0 + 1, instruction);
+        Ok(instruction)
+    }
+    fn load_word(&self, address: usize) -> u16 {
+        self.machine.load_word(address)
+    }
+    fn store_word(&mut self, address: usize, word: u16) {
+        self.machine.store_word(address, word)
+    }
+    fn load_byte(&self, address: usize) -> u8 {
+        self.machine.load_byte(address)
+    }
+    fn store_byte(&mut self, address: usize, byte: u8) {
+        self.machine.store_byte(address, byte)
+    }
+}
+
+// Executes at most the given number of instructions, using the given device,
+// and counting the executions of every instruction.
+pub fn trace_program(
+    program: &[u8],
+    device: &mut dyn IoDevice,
+    step_limit: u64,
+) -> (Result<u8, Fault>, Trace) {
+    let mut trace = Trace::default();
+    let result = emulator::load_program(program).and_then(|mut process| {
+        let mut registers = RegisterSet::new();
+        TracingMachine {
+            machine: ByteMachine {
+                process: &mut process,
+                registers: &mut registers,
+            },
+            trace: &mut trace,
+        }
+        .run(device, step_limit)
+    });
+    (result, trace)
+}
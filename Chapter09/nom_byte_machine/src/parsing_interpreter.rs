@@ -1,216 +1,132 @@
-use crate::instructions::{get_process_size, parse_instruction, Instruction};
+use crate::device::{ConsoleDevice, IoDevice};
+use crate::fault::Fault;
+use crate::instructions::{decode_instruction, get_process_size, Instruction};
+use crate::machine::{Machine, RegisterSet};
 
-pub fn parse_program(program: &[u8]) -> Result<Vec<Instruction>, ()> {
-    let process_size_parsed = match get_process_size(program) {
-        Ok(ok) => ok as usize,
-        Err(_) => return Err(()),
-    };
+// Every cell of a parsed program contains the instruction decoded
+// from the bytes starting at its address, or just the byte at its address.
+// So, every byte of the process can be read from the cells,
+// and the instructions can be decoded again when their bytes are written.
+pub fn parse_program(program: &[u8]) -> Result<Vec<Instruction>, Fault> {
+    let process_size_parsed = get_process_size(program)? as usize;
     let mut parsed_program = vec![Instruction::Byte(0); process_size_parsed];
-    let mut ip = 2;
-    loop {
-        match parse_instruction(&program[ip..]) {
-            Ok(instruction) => {
-                parsed_program[ip] = instruction.1;
-                ip += instruction.1.len();
-                if let Instruction::Terminate(_) = instruction.1 {
-                    break;
-                }
-            }
-            Err(_) => return Err(()),
-        };
+    for (cell, &byte) in parsed_program.iter_mut().zip(program) {
+        *cell = Instruction::Byte(byte);
     }
-    for ip in ip..program.len() {
-        parsed_program[ip] = Instruction::Byte(program[ip]);
+    // The code is decoded up to the first Terminate instruction.
+    // An invalid instruction stops the decoding too,
+    // as it is a fault only if it is executed.
+    let mut ip = 2;
+    while let Ok(instruction) = decode_parsed_instruction(&parsed_program, ip as u16) {
+        parsed_program[ip] = instruction;
+        ip += instruction.len();
+        if let Instruction::Terminate(_) = instruction {
+            break;
+        }
     }
     Ok(parsed_program)
 }
 
-struct ParsedRegisterSet {
-    ip: usize,
-    acc: u16,
-}
-
-pub fn execute_parsed_program(parsed_program: &mut Vec<Instruction>) -> u8 {
-    let mut registers = ParsedRegisterSet { ip: 2, acc: 0 };
-    loop {
-        if let Some(return_code) = execute_parsed_instruction(parsed_program, &mut registers) {
-            return return_code;
-        };
+// The byte at the address of the cell, which is the opcode of its instruction, if any.
+fn cell_byte(cell: Instruction) -> u8 {
+    match cell {
+        Instruction::Byte(byte) => byte,
+        // Only the data bytes have no opcode.
+        instruction => instruction.opcode().unwrap(),
     }
 }
 
-fn input_parsed_line(buffer: &mut [Instruction]) {
-    let mut text = String::new();
-    std::io::stdin()
-        .read_line(&mut text)
-        .expect("Cannot read line.");
-    for i in 0..text.len().min(buffer.len()) {
-        buffer[i] = Instruction::Byte(text.as_bytes()[i]);
-    }
-    for i in text.len()..buffer.len() {
-        buffer[i] = Instruction::Byte(0);
-    }
+fn decode_parsed_instruction(process: &[Instruction], ip: u16) -> Result<Instruction, Fault> {
+    let start = (ip as usize).min(process.len());
+    let end = (start + 3).min(process.len());
+    let code: Vec<u8> = process[start..end]
+        .iter()
+        .map(|&cell| cell_byte(cell))
+        .collect();
+    decode_instruction(&code, ip, process.len())
 }
 
-fn get_parsed_le_word(process: &[Instruction], address: u16) -> u16 {
-    if let Instruction::Byte(byte0) = process[address as usize] {
-        if let Instruction::Byte(byte1) = process[address as usize + 1] {
-            return u16::from(byte0) + (u16::from(byte1) << 8);
-        }
+// Writes the byte at the given address,
+// and decodes again the instructions containing that address.
+// The instructions that are no longer valid are kept as bytes,
+// to be decoded again if they are executed.
+fn store_parsed_byte(process: &mut [Instruction], address: usize, byte: u8) {
+    let changed_instructions: Vec<usize> = (address.saturating_sub(2)..=address)
+        .filter(|&start| match process[start] {
+            Instruction::Byte(_) => false,
+            instruction => start + instruction.len() > address,
+        })
+        .collect();
+    process[address] = Instruction::Byte(byte);
+    for start in changed_instructions {
+        process[start] = decode_parsed_instruction(process, start as u16)
+            .unwrap_or_else(|_| Instruction::Byte(cell_byte(process[start])));
     }
-    0
 }
 
-fn set_parsed_le_word(process: &mut [Instruction], address: u16, word: u16) {
-    process[address as usize] = Instruction::Byte(word as u8);
-    process[address as usize + 1] = Instruction::Byte((word >> 8) as u8);
+// The byte machine running on the cells of a parsed program.
+struct ParsedMachine<'a> {
+    process: &'a mut [Instruction],
+    registers: RegisterSet,
 }
 
-fn get_parsed_byte(process: &[Instruction], address: u16) -> u8 {
-    if let Instruction::Byte(byte) = process[address as usize] {
-        byte
-    } else {
-        0
+impl<'a> Machine for ParsedMachine<'a> {
+    fn registers(&mut self) -> &mut RegisterSet {
+        &mut self.registers
+    }
+    fn memory_size(&self) -> usize {
+        self.process.len()
+    }
+    fn word_size(&self) -> usize {
+        2
+    }
+    fn instruction_size(&self, instruction: Instruction) -> u16 {
+        instruction.len() as u16
+    }
+    // The bytes that have not been decoded, or that have been overwritten,
+    // are decoded when they are executed.
+    fn fetch(&mut self, ip: u16) -> Result<Instruction, Fault> {
+        match self.process.get(ip as usize) {
+            Some(Instruction::Byte(_)) => {
+                let instruction = decode_parsed_instruction(self.process, ip)?;
+                self.process[ip as usize] = instruction;
+                Ok(instruction)
+            }
+            Some(&instruction) => Ok(instruction),
+            None => Err(Fault::OutOfBounds(ip, self.process.len().max(ip as usize))),
+        }
+    }
+    // The words may overlap the cells of the instructions.
+    fn load_word(&self, address: usize) -> u16 {
+        u16::from_le_bytes([self.load_byte(address), self.load_byte(address + 1)])
+    }
+    fn store_word(&mut self, address: usize, word: u16) {
+        let [low, high] = word.to_le_bytes();
+        self.store_byte(address, low);
+        self.store_byte(address + 1, high);
+    }
+    fn load_byte(&self, address: usize) -> u8 {
+        cell_byte(self.process[address])
+    }
+    fn store_byte(&mut self, address: usize, byte: u8) {
+        store_parsed_byte(self.process, address, byte);
     }
 }
 
-fn set_parsed_byte(process: &mut [Instruction], address: u16, byte: u8) {
-    process[address as usize] = Instruction::Byte(byte);
+// The input and output instructions use the console.
+pub fn execute_parsed_program(parsed_program: &mut [Instruction]) -> Result<u8, Fault> {
+    execute_parsed_program_with_device(parsed_program, &mut ConsoleDevice, u64::MAX)
 }
 
-fn execute_parsed_instruction(
-    process: &mut [Instruction],
-    r: &mut ParsedRegisterSet,
-) -> Option<u8> {
-    use Instruction::*;
-    let instruction = process[r.ip as usize];
-    //println!("Ip: {} Acc: {} Instr: {:?}", r.ip, r.acc, instruction);
-    match instruction {
-        Terminate(operand) => {
-            r.ip += 2;
-            return Some(operand);
-        }
-        Set(operand) => {
-            r.acc = operand;
-            r.ip += 3;
-        }
-        Load(address) => {
-            r.acc = get_parsed_le_word(process, address);
-            r.ip += 3;
-        }
-        Store(address) => {
-            set_parsed_le_word(process, address, r.acc);
-            r.ip += 3;
-        }
-        IndirectLoad(address) => {
-            r.acc = get_parsed_le_word(process, get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        IndirectStore(address) => {
-            set_parsed_le_word(process, get_parsed_le_word(process, address), r.acc);
-            r.ip += 3;
-        }
-        Input(length) => {
-            let address = r.acc as usize;
-            input_parsed_line(&mut process[address..address + length as usize]);
-            r.ip += 2;
-        }
-        Output(length) => {
-            let address = r.acc as usize;
-            for &instruction in &process[address..address + length as usize] {
-                if let Byte(byte) = instruction {
-                    print!("{}", if byte == 0 { ' ' } else { byte as char });
-                }
-            }
-            r.ip += 2;
-        }
-        Add(address) => {
-            r.acc = r.acc.wrapping_add(get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        Subtract(address) => {
-            r.acc = r.acc.wrapping_sub(get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        Multiply(address) => {
-            r.acc = r.acc.wrapping_mul(get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        Divide(address) => {
-            r.acc = r.acc.wrapping_div(get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        Remainder(address) => {
-            r.acc = r.acc.wrapping_rem(get_parsed_le_word(process, address));
-            r.ip += 3;
-        }
-        Jump(address) => {
-            r.ip = address as usize;
-        }
-        JumpIfZero(address) => {
-            if r.acc == 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        JumpIfNonZero(address) => {
-            if r.acc != 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        JumpIfPositive(address) => {
-            if (r.acc as i16) > 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        JumpIfNegative(address) => {
-            if (r.acc as i16) < 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        JumpIfNonPositive(address) => {
-            if r.acc as i16 <= 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        JumpIfNonNegative(address) => {
-            if r.acc as i16 >= 0 {
-                r.ip = address as usize;
-            } else {
-                r.ip += 3;
-            }
-        }
-        LoadByte(address) => {
-            r.acc = u16::from(get_parsed_byte(process, address));
-            r.ip += 3;
-        }
-        StoreByte(address) => {
-            set_parsed_byte(process, address, r.acc as u8);
-            r.ip += 3;
-        }
-        IndirectLoadByte(address) => {
-            r.acc = u16::from(get_parsed_byte(
-                process,
-                get_parsed_le_word(process, address),
-            ));
-            r.ip += 3;
-        }
-        IndirectStoreByte(address) => {
-            set_parsed_byte(process, get_parsed_le_word(process, address), r.acc as u8);
-            r.ip += 3;
-        }
-        Byte(_) => {
-            r.ip += 1;
-        }
+// Executes at most the given number of instructions, using the given device.
+pub fn execute_parsed_program_with_device(
+    parsed_program: &mut [Instruction],
+    device: &mut dyn IoDevice,
+    step_limit: u64,
+) -> Result<u8, Fault> {
+    ParsedMachine {
+        process: parsed_program,
+        registers: RegisterSet::new(),
     }
-    None
+    .run(device, step_limit)
 }
@@ -1,229 +1,231 @@
-use crate::instructions::{parse_instruction, Instruction};
+use crate::emulator::{load_program, ByteMachine, RegisterSet};
+use crate::instructions::Instruction;
+use crate::machine::Machine;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{Error, ErrorKind, Result, Write};
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
 
+// The code starts after the word containing the process size.
+const ENTRY_POINT: u16 = 2;
+
+// The functions used by the translated instructions.
+// They check the accesses to memory and the divisors as the emulator does,
+// and the input and output functions behave like the console device.
+// They are not static, as a program may not use all of them.
+const RUNTIME: &str = r#"void fault(const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "Fault: %s\n", message);
+    exit(1);
+}
+
+void check_range(unsigned int ip, unsigned int address, unsigned int length) {
+    if (address + length > PROCESS_SIZE) {
+        fflush(stdout);
+        fprintf(stderr,
+            "Fault: The instruction at address %u accesses address %u, outside the process.\n",
+            ip, address > PROCESS_SIZE ? address : PROCESS_SIZE);
+        exit(1);
+    }
+}
+
+unsigned short check_divisor(unsigned int ip, unsigned short divisor) {
+    if (divisor == 0) {
+        fflush(stdout);
+        fprintf(stderr, "Fault: Division by zero at address %u.\n", ip);
+        exit(1);
+    }
+    return divisor;
+}
+
+unsigned short bytes_to_u16_le(unsigned int ip, unsigned int address) {
+    check_range(ip, address, 2);
+    return (unsigned short)(memory[address] | memory[address + 1] << 8);
+}
+
+void u16_to_bytes_le(unsigned int ip, unsigned int address, unsigned short operand) {
+    check_range(ip, address, 2);
+    memory[address] = operand & 0xFF;
+    memory[address + 1] = operand >> 8;
+}
+
+unsigned short load_byte(unsigned int ip, unsigned int address) {
+    check_range(ip, address, 1);
+    return memory[address];
+}
+
+void store_byte(unsigned int ip, unsigned int address, unsigned short operand) {
+    check_range(ip, address, 1);
+    memory[address] = operand & 0xFF;
+}
+
+/* Reads a line, including its terminator, truncating it or padding it with zeros. */
+void input_line(unsigned int ip, unsigned int address, unsigned int length) {
+    unsigned int i = 0;
+    int c;
+    check_range(ip, address, length);
+    fflush(stdout);
+    while ((c = getchar()) != EOF) {
+        if (i < length) {
+            memory[address + i++] = (unsigned char)c;
+        }
+        if (c == '\n') {
+            break;
+        }
+    }
+    while (i < length) {
+        memory[address + i++] = 0;
+    }
+}
+
+/* Writes the bytes, showing the zeros as blanks. */
+void output_bytes(unsigned int ip, unsigned int address, unsigned int length) {
+    unsigned int i;
+    check_range(ip, address, length);
+    for (i = 0; i < length; i++) {
+        putchar(memory[address + i] ? memory[address + i] : ' ');
+    }
+    fflush(stdout);
+}
+
+/* The sign of a signed word. */
+int sign(unsigned short word) {
+    return word == 0 ? 0 : word & 0x8000 ? -1 : 1;
+}
+"#;
+
+// Translates the code reachable from the entry point into a C program,
+// containing the memory of the process, initialized with the image.
+// Every instruction becomes a statement, labeled if it is reached by a goto.
+// The translated code is fixed, so the program must not modify its own code.
 pub fn translate_program_to_c(program: &[u8], target_path: &str) -> Result<()> {
-    let mut file = File::create(target_path)?;
+    let mut process =
+        load_program(program).map_err(|fault| Error::new(ErrorKind::InvalidData, fault))?;
+    let code = ByteMachine {
+        process: &mut process,
+        registers: &mut RegisterSet::new(),
+    }
+    .find_code(ENTRY_POINT);
 
-    match std::fs::write(target_path, "") {
-        Ok(_) => eprintln!("Compiled to {}.", target_path),
-        Err(err) => eprintln!("Failed to write to file {}: ({})", target_path, err),
+    // The instructions are translated in the order of their addresses,
+    // so a goto is needed wherever the next instruction is not the following one.
+    let addresses: Vec<u16> = code.keys().copied().collect();
+    let mut fall_through_gotos = BTreeMap::new();
+    for (index, &ip) in addresses.iter().enumerate() {
+        if let Ok(instruction) = code[&ip] {
+            let next = ip + instruction.len() as u16;
+            if instruction.falls_through() && addresses.get(index + 1) != Some(&next) {
+                fall_through_gotos.insert(ip, next);
+            }
+        }
+    }
+    let mut labels: BTreeSet<u16> = fall_through_gotos.values().copied().collect();
+    labels.extend(code.values().flatten().filter_map(|i| i.jump_target()));
+    // The header may be executed only after a jump.
+    let starts_at_entry_point = addresses.first() == Some(&ENTRY_POINT);
+    if !starts_at_entry_point {
+        labels.insert(ENTRY_POINT);
     }
 
-    let mut ip = 2;
+    let mut file = BufWriter::new(File::create(target_path)?);
     writeln!(file, "#include <stdio.h>")?;
-    writeln!(file, "#include <string.h>")?;
-    writeln!(file, "unsigned char memory[];")?;
-    writeln!(
-        file,
-        "unsigned short bytes_to_u16_le(unsigned int address) {{"
-    )?;
-    writeln!(
-        file,
-        "    return (unsigned short)(memory[address] + (memory[address + 1] << 8));"
-    )?;
-    writeln!(file, "}}")?;
+    writeln!(file, "#include <stdlib.h>")?;
+    writeln!(file)?;
+    writeln!(file, "#define PROCESS_SIZE {}", process.len())?;
+    writeln!(file)?;
+    writeln!(file, "/* The bytes beyond the image are zeros. */")?;
+    writeln!(file, "static unsigned char memory[PROCESS_SIZE] = {{")?;
+    for row in program.chunks(16) {
+        let bytes: Vec<String> = row.iter().map(|byte| byte.to_string()).collect();
+        writeln!(file, "    {},", bytes.join(", "))?;
+    }
+    writeln!(file, "}};")?;
+    writeln!(file)?;
     writeln!(
         file,
-        "void u16_to_bytes_le(unsigned int address, unsigned short operand) {{"
+        "/* The accumulator is not local, as it may be unused. */"
     )?;
-    writeln!(file, "    memory[address] = operand & 0xFF;")?;
-    writeln!(file, "    memory[address + 1] = operand >> 8;")?;
-    writeln!(file, "}}")?;
-    writeln!(file, "int main() {{")?;
-    writeln!(file, "    unsigned short acc = 0;")?;
-    loop {
-        let instruction = match parse_instruction(&program[ip..]) {
-            Ok(instruction) => instruction.1,
-            Err(_) => return Err(Error::new(ErrorKind::Other, "Invalid instruction.")),
+    writeln!(file, "unsigned short acc = 0;")?;
+    writeln!(file)?;
+    write!(file, "{}", RUNTIME)?;
+    writeln!(file)?;
+    writeln!(file, "int main(void) {{")?;
+    if !starts_at_entry_point {
+        writeln!(file, "    goto addr_{};", ENTRY_POINT)?;
+    }
+    for (&ip, instruction) in &code {
+        let label = if labels.contains(&ip) {
+            format!("addr_{}: ", ip)
+        } else {
+            String::new()
         };
-        if translate_instruction_to_c(&mut file, instruction, &mut ip)? {
-            break;
+        let statement = match instruction {
+            Ok(instruction) => translate_instruction_to_c(ip, *instruction),
+            Err(fault) => format!("fault(\"{}\");", fault),
+        };
+        writeln!(file, "    {}{}", label, statement)?;
+        if let Some(next) = fall_through_gotos.get(&ip) {
+            writeln!(file, "    goto addr_{};", next)?;
         }
     }
     writeln!(file, "}}")?;
-    writeln!(file, "unsigned char memory[] = {{")?;
-    for byte in program {
-        writeln!(file, "    {}, ", byte)?;
-    }
-    writeln!(file, "}};")?;
-    Ok(())
+    file.flush()
 }
 
-fn translate_instruction_to_c(
-    file: &mut File,
-    instruction: Instruction,
-    ip: &mut usize,
-) -> Result<bool> {
+fn translate_instruction_to_c(ip: u16, instruction: Instruction) -> String {
     use Instruction::*;
     match instruction {
-        Terminate(operand) => {
-            writeln!(file, "    addr_{}: return {};", *ip, operand)?;
-            *ip += 2;
-            return Ok(true);
-        }
-        Set(operand) => {
-            writeln!(file, "    addr_{}: acc = {};", *ip, operand)?;
-            *ip += 3;
-        }
-        Load(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc = bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Store(address) => {
-            writeln!(file, "    addr_{}: u16_to_bytes_le({}, acc);", *ip, address)?;
-            *ip += 3;
-        }
-        IndirectLoad(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc = bytes_to_u16_le(bytes_to_u16_le({}));",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        IndirectStore(address) => {
-            writeln!(
-                file,
-                "    addr_{}: u16_to_bytes_le(bytes_to_u16_le({}), acc);",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        Input(length) => {
-            writeln!(file, "    addr_{}: {{", *ip)?;
-            writeln!(file, "        char buf[{}];", length + 1)?;
-            writeln!(file, "        int len;")?;
-            writeln!(file, "        scanf(\"%{}s\", buf);", length)?;
-            writeln!(file, "        len = strlen(buf);")?;
-            writeln!(file, "        memcpy(memory + acc, buf, len);")?;
-            writeln!(
-                file,
-                "        memset(memory + acc + len, ' ', {} - len);",
-                length
-            )?;
-            writeln!(file, "    }}")?;
-            *ip += 2;
-        }
-        Output(length) => {
-            writeln!(file, "    addr_{}: for (int i = 0; i < {}; i++) {{ putchar(memory[acc + i] ? memory[acc + i] : ' '); }}", *ip, length)?;
-            *ip += 2;
-        }
-        Add(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc += bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Subtract(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc -= bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Multiply(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc *= bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Divide(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc /= bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Remainder(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc %= bytes_to_u16_le({});",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Jump(address) => {
-            writeln!(file, "    addr_{}: goto addr_{};", *ip, address)?;
-            *ip += 3;
-        }
-        JumpIfZero(address) => {
-            writeln!(file, "    addr_{}: if (!acc) goto addr_{};", *ip, address)?;
-            *ip += 3;
-        }
-        JumpIfNonZero(address) => {
-            writeln!(file, "    addr_{}: if (acc) goto addr_{};", *ip, address)?;
-            *ip += 3;
-        }
-        JumpIfPositive(address) => {
-            writeln!(
-                file,
-                "    addr_{}: if ((short)acc > 0) goto addr_{};",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        JumpIfNegative(address) => {
-            writeln!(
-                file,
-                "    addr_{}: if ((short)acc < 0) goto addr_{};",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        JumpIfNonPositive(address) => {
-            writeln!(
-                file,
-                "    addr_{}: if ((short)acc <= 0) goto addr_{};",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        JumpIfNonNegative(address) => {
-            writeln!(
-                file,
-                "    addr_{}: if ((short)acc >= 0) goto addr_{};",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        LoadByte(address) => {
-            writeln!(file, "    addr_{}: acc = memory[{}];", *ip, address)?;
-            *ip += 3;
-        }
-        StoreByte(address) => {
-            writeln!(file, "    addr_{}: memory[{}] = acc & 0xFF;", *ip, address)?;
-            *ip += 3;
-        }
-        IndirectLoadByte(address) => {
-            writeln!(
-                file,
-                "    addr_{}: acc = memory[bytes_to_u16_le({})];",
-                ip, address
-            )?;
-            *ip += 3;
-        }
-        IndirectStoreByte(address) => {
-            writeln!(
-                file,
-                "    addr_{}: memory[bytes_to_u16_le({})] = acc & 0xFF;",
-                *ip, address
-            )?;
-            *ip += 3;
-        }
-        Byte(_) => {
-            *ip += 1;
-        }
+        Terminate(operand) => format!("return {};", operand),
+        Set(operand) => format!("acc = {};", operand),
+        Load(address) => format!("acc = bytes_to_u16_le({}, {});", ip, address),
+        Store(address) => format!("u16_to_bytes_le({}, {}, acc);", ip, address),
+        IndirectLoad(address) => format!(
+            "acc = bytes_to_u16_le({0}, bytes_to_u16_le({0}, {1}));",
+            ip, address
+        ),
+        IndirectStore(address) => format!(
+            "u16_to_bytes_le({0}, bytes_to_u16_le({0}, {1}), acc);",
+            ip, address
+        ),
+        Input(length) => format!("input_line({}, acc, {});", ip, length),
+        Output(length) => format!("output_bytes({}, acc, {});", ip, length),
+        // The arithmetic is computed on unsigned ints, and then truncated to a word.
+        Add(address) => format!(
+            "acc = (unsigned short)(acc + (unsigned int)bytes_to_u16_le({}, {}));",
+            ip, address
+        ),
+        Subtract(address) => format!(
+            "acc = (unsigned short)(acc - (unsigned int)bytes_to_u16_le({}, {}));",
+            ip, address
+        ),
+        Multiply(address) => format!(
+            "acc = (unsigned short)(acc * (unsigned int)bytes_to_u16_le({}, {}));",
+            ip, address
+        ),
+        Divide(address) => format!(
+            "acc = acc / check_divisor({0}, bytes_to_u16_le({0}, {1}));",
+            ip, address
+        ),
+        Remainder(address) => format!(
+            "acc = acc % check_divisor({0}, bytes_to_u16_le({0}, {1}));",
+            ip, address
+        ),
+        Jump(address) => format!("goto addr_{};", address),
+        JumpIfZero(address) => format!("if (acc == 0) goto addr_{};", address),
+        JumpIfNonZero(address) => format!("if (acc != 0) goto addr_{};", address),
+        JumpIfPositive(address) => format!("if (sign(acc) > 0) goto addr_{};", address),
+        JumpIfNegative(address) => format!("if (sign(acc) < 0) goto addr_{};", address),
+        JumpIfNonPositive(address) => format!("if (sign(acc) <= 0) goto addr_{};", address),
+        JumpIfNonNegative(address) => format!("if (sign(acc) >= 0) goto addr_{};", address),
+        LoadByte(address) => format!("acc = load_byte({}, {});", ip, address),
+        StoreByte(address) => format!("store_byte({}, {}, acc);", ip, address),
+        IndirectLoadByte(address) => format!(
+            "acc = load_byte({0}, bytes_to_u16_le({0}, {1}));",
+            ip, address
+        ),
+        IndirectStoreByte(address) => format!(
+            "store_byte({0}, bytes_to_u16_le({0}, {1}), acc);",
+            ip, address
+        ),
+        Byte(_) => unreachable!("The fetched instructions are never data bytes."),
     }
-    Ok(false)
 }
@@ -0,0 +1,227 @@
+use crate::assembler::assemble;
+use crate::instructions::Instruction;
+use crate::machine::Machine;
+use crate::word_machine::WordMachine;
+use std::collections::BTreeSet;
+
+// The code of the word machine starts after the word containing the process size.
+const ENTRY_POINT: u16 = 1;
+
+// An address outside any byte process, accessed instead of the words outside the memory.
+const OUTSIDE: &str = "65535";
+
+// The label of the byte word holding the word cell at the given address.
+fn cell(memory_size: usize, address: u16) -> String {
+    if (address as usize) < memory_size {
+        format!("m_{}", address)
+    } else {
+        OUTSIDE.to_string()
+    }
+}
+
+// The statements computing into "io_pointer" the byte address
+// of the word cell whose address is in the accumulator.
+// The addresses outside the memory jump to a fault,
+// as their byte addresses could wrap around into the process.
+// The memory size is less than 32768, as it takes two bytes per word.
+const CELL_POINTER: &str = "    store io_pointer
+    jump if negative outside
+    subtract memory_size
+    jump if non-negative outside
+    load io_pointer
+    add io_pointer
+    add base
+    store io_pointer
+";
+
+// The statements saving the accumulator into "io_address",
+// and jumping to a fault if the word cells of the given length starting there
+// are not all inside the memory, even if there are none.
+// The signed comparisons cannot overflow, as the first one removes the large addresses.
+fn check_area(length: u8) -> String {
+    format!(
+        "    store io_address
+    jump if negative outside
+    subtract memory_size
+    jump if positive outside
+    add length_{}
+    jump if positive outside
+",
+        length
+    )
+}
+
+// Copies into the word cells starting at the accumulator
+// the bytes read into the buffer, one byte per cell.
+fn convert_input(ip: u16, length: u8) -> String {
+    format!(
+        "{check_area}    set io_buffer
+    input {length}
+    set 0
+    store io_index
+input_{ip}:
+    load io_index
+    subtract length_{length}
+    jump if zero input_{ip}_end
+    load io_index
+    add io_address
+{cell_pointer}    set io_buffer
+    add io_index
+    store io_value
+    indirect load byte io_value
+    indirect store io_pointer
+    load io_index
+    add one
+    store io_index
+    jump input_{ip}
+input_{ip}_end:
+    load io_address
+",
+        ip = ip,
+        length = length,
+        check_area = check_area(length),
+        cell_pointer = CELL_POINTER
+    )
+}
+
+// Copies into the buffer the low bytes of the word cells starting at the accumulator,
+// and writes them.
+fn convert_output(ip: u16, length: u8) -> String {
+    format!(
+        "{check_area}    set 0
+    store io_index
+output_{ip}:
+    load io_index
+    subtract length_{length}
+    jump if zero output_{ip}_end
+    load io_index
+    add io_address
+{cell_pointer}    indirect load io_pointer
+    store io_value
+    set io_buffer
+    add io_index
+    store io_pointer
+    load io_value
+    indirect store byte io_pointer
+    load io_index
+    add one
+    store io_index
+    jump output_{ip}
+output_{ip}_end:
+    set io_buffer
+    output {length}
+    load io_address
+",
+        ip = ip,
+        length = length,
+        check_area = check_area(length),
+        cell_pointer = CELL_POINTER
+    )
+}
+
+// The byte statements executing the given word instruction.
+fn convert_instruction(memory_size: usize, ip: u16, instruction: Instruction) -> String {
+    use Instruction::*;
+    let cell = |address| cell(memory_size, address);
+    match instruction {
+        Terminate(operand) => format!("    terminate {}\n", operand),
+        Set(operand) => format!("    set {}\n", operand),
+        // The pointers are word addresses, converted to byte addresses.
+        IndirectLoad(address) => format!(
+            "    load {}\n{}    indirect load io_pointer\n",
+            cell(address),
+            CELL_POINTER
+        ),
+        IndirectStore(address) => format!(
+            "    store io_value\n    load {}\n{}    load io_value\n    indirect store io_pointer\n",
+            cell(address),
+            CELL_POINTER
+        ),
+        Input(length) => convert_input(ip, length),
+        Output(length) => convert_output(ip, length),
+        Jump(address)
+        | JumpIfZero(address)
+        | JumpIfNonZero(address)
+        | JumpIfPositive(address)
+        | JumpIfNegative(address)
+        | JumpIfNonPositive(address)
+        | JumpIfNonNegative(address) => {
+            format!(
+                "    {} code_{}\n",
+                instruction.info().unwrap().mnemonic,
+                address
+            )
+        }
+        // The other instructions access a word cell, which is a byte word.
+        instruction => format!(
+            "    {} {}\n",
+            instruction.info().unwrap().mnemonic,
+            cell(instruction.operand())
+        ),
+    }
+}
+
+// Converts a word machine program into the assembly source of a byte machine program.
+// The word cells become byte words, placed after the code,
+// and the reachable instructions become sequences of byte instructions,
+// labeled by the address of the word instruction.
+// The invalid instructions become invalid opcodes,
+// so they are faults only if they are executed, like the accesses outside the memory,
+// but the faults have the addresses of the byte program.
+// The converted code is fixed, so the program must not modify its own code.
+pub fn convert_word_program_to_source(program: &[u16]) -> Result<String, String> {
+    let mut machine = WordMachine::load(program).map_err(|fault| fault.to_string())?;
+    let memory_size = machine.memory.len();
+    let code = machine.find_code(ENTRY_POINT);
+
+    let mut source = String::new();
+    let mut lengths = BTreeSet::new();
+    let addresses: Vec<u16> = code.keys().copied().collect();
+    // The word 0 may be executed only after a jump.
+    if addresses.first() != Some(&ENTRY_POINT) {
+        source += &format!("    jump code_{}\n", ENTRY_POINT);
+    }
+    for (index, (&ip, instruction)) in code.iter().enumerate() {
+        source += &format!("code_{}:\n", ip);
+        match instruction {
+            Ok(instruction) => {
+                if let Instruction::Input(length) | Instruction::Output(length) = instruction {
+                    lengths.insert(*length);
+                }
+                source += &convert_instruction(memory_size, ip, *instruction);
+                let next = ip.wrapping_add(2);
+                if instruction.falls_through() && addresses.get(index + 1) != Some(&next) {
+                    source += &format!("    jump code_{}\n", next);
+                }
+            }
+            Err(fault) => source += &format!("    byte 255 // {}\n", fault),
+        }
+    }
+
+    // The fault of the accesses outside the memory,
+    // and the variables of the conversion, followed by the memory,
+    // so that its final zeros are not in the image.
+    source += &format!("outside: store {}\n", OUTSIDE);
+    source += &format!(
+        "base: word m_0\none: word 1\nmemory_size: word {}\n",
+        memory_size
+    );
+    for length in lengths {
+        source += &format!("length_{0}: word {0}\n", length);
+    }
+    source += "io_address: word 0\nio_index: word 0\nio_pointer: word 0\nio_value: word 0\n";
+    source += "io_buffer: array 255\n";
+    for (address, word) in machine.memory.iter().enumerate() {
+        if address < program.len() {
+            source += &format!("m_{}: word {}\n", address, word);
+        } else {
+            source += &format!("m_{}: array 2\n", address);
+        }
+    }
+    Ok(source)
+}
+
+// Converts a word machine program into the image of a byte machine program.
+pub fn convert_word_program(program: &[u16]) -> Result<Vec<u8>, String> {
+    assemble(&convert_word_program_to_source(program)?)
+}
@@ -0,0 +1,69 @@
+use crate::fault::Fault;
+use crate::instructions::Instruction;
+use crate::machine::{Machine, RegisterSet};
+
+// The word machine has the opcodes of the byte machine up to the conditional jumps,
+// as every cell can be loaded as a byte.
+pub const WORD_OPCODE_COUNT: u16 = 20;
+
+// The word machine, whose memory cells are words,
+// and whose instructions are made of an opcode word and an operand word.
+// The characters read and written by the input and output instructions
+// take a word each.
+pub struct WordMachine {
+    pub memory: Vec<u16>,
+    pub registers: RegisterSet,
+}
+
+impl WordMachine {
+    // Creates the memory of the process, containing the program followed by zeros.
+    // The first word is the process size, and the execution starts after it.
+    pub fn load(program: &[u16]) -> Result<WordMachine, Fault> {
+        let process_size = *program.first().ok_or(Fault::MissingProcessSize)?;
+        if (process_size as usize) < program.len() {
+            return Err(Fault::ProcessTooSmall(process_size, program.len()));
+        }
+        let mut memory = vec![0u16; process_size as usize];
+        memory[..program.len()].copy_from_slice(program);
+        Ok(WordMachine {
+            memory,
+            registers: RegisterSet { ip: 1, acc: 0 },
+        })
+    }
+}
+
+impl Machine for WordMachine {
+    fn registers(&mut self) -> &mut RegisterSet {
+        &mut self.registers
+    }
+    fn memory_size(&self) -> usize {
+        self.memory.len()
+    }
+    fn word_size(&self) -> usize {
+        1
+    }
+    fn instruction_size(&self, _instruction: Instruction) -> u16 {
+        2
+    }
+    fn fetch(&mut self, ip: u16) -> Result<Instruction, Fault> {
+        let out_of_bounds = Fault::OutOfBounds(ip, self.memory.len().max(ip as usize));
+        let opcode = *self.memory.get(ip as usize).ok_or(out_of_bounds)?;
+        if opcode >= WORD_OPCODE_COUNT {
+            return Err(Fault::InvalidOpcode(ip, opcode));
+        }
+        let operand = *self.memory.get(ip as usize + 1).ok_or(out_of_bounds)?;
+        Instruction::from_parts(opcode as u8, operand).ok_or(Fault::OperandTooLarge(ip, operand))
+    }
+    fn load_word(&self, address: usize) -> u16 {
+        self.memory[address]
+    }
+    fn store_word(&mut self, address: usize, word: u16) {
+        self.memory[address] = word;
+    }
+    fn load_byte(&self, address: usize) -> u8 {
+        self.memory[address] as u8
+    }
+    fn store_byte(&mut self, address: usize, byte: u8) {
+        self.memory[address] = u16::from(byte);
+    }
+}
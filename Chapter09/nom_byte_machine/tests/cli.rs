@@ -0,0 +1,147 @@
+mod common;
+
+use common::TempFile;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const SIEVE_SOURCE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/programs/sieve.asm");
+
+const SIEVE_OUTPUT_TO_30: &str = "    2    3    5    7   11   13   17   19   23   29";
+
+fn run_cli(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nom_byte_machine"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout_of(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr_of(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn runs_the_sieve_source_on_the_console() {
+    let output = run_cli(&["run", SIEVE_SOURCE_PATH], "30\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout_of(&output), SIEVE_OUTPUT_TO_30);
+}
+
+#[test]
+fn runs_an_assembled_image_with_files_on_both_interpreters() {
+    let image = TempFile::new("sieve.bin");
+    let output = run_cli(&["assemble", SIEVE_SOURCE_PATH, image.path()], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    let input = TempFile::with_contents("sieve_input.txt", "30\n");
+    for interpreter in &[None, Some("--parsed")] {
+        let output_file = TempFile::new("sieve_output.txt");
+        let mut args = vec![
+            "run",
+            "--input",
+            input.path(),
+            "--output",
+            output_file.path(),
+        ];
+        args.extend(interpreter);
+        args.push(image.path());
+        let output = run_cli(&args, "");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(stdout_of(&output), "");
+        assert_eq!(
+            std::fs::read_to_string(output_file.path()).unwrap(),
+            SIEVE_OUTPUT_TO_30
+        );
+    }
+}
+
+#[test]
+fn exits_with_the_return_code_of_the_program() {
+    let source = TempFile::with_contents("return_code.asm", "terminate 42\n");
+    assert_eq!(run_cli(&["run", source.path()], "").status.code(), Some(42));
+    assert_eq!(
+        run_cli(&["run", "--parsed", source.path()], "")
+            .status
+            .code(),
+        Some(42)
+    );
+    // A return code of 1 is not a failure of the runner.
+    let source = TempFile::with_contents("return_code_1.asm", "terminate 1\n");
+    let output = run_cli(&["run", source.path()], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr_of(&output), "");
+}
+
+#[test]
+fn reports_faults_and_step_limits() {
+    let source = TempFile::with_contents(
+        "division.asm",
+        "set 1\ndivide zero\nterminate 0\nzero: word 0\n",
+    );
+    let output = run_cli(&["run", source.path()], "");
+    assert_eq!(output.status.code(), Some(126));
+    assert!(stderr_of(&output).contains("Fault: Division by zero at address 5."));
+
+    let source = TempFile::with_contents("loop.asm", "loop: jump loop\nterminate 0\n");
+    let output = run_cli(&["run", "--step-limit", "100", source.path()], "");
+    assert_eq!(output.status.code(), Some(126));
+    assert!(stderr_of(&output).contains("has not terminated in 100 steps"));
+}
+
+#[test]
+fn disassembles_to_a_source_that_assembles_to_the_same_image() {
+    let image = TempFile::new("reassembled_original.bin");
+    run_cli(&["assemble", SIEVE_SOURCE_PATH, image.path()], "");
+
+    let output = run_cli(&["disassemble", image.path()], "");
+    assert_eq!(output.status.code(), Some(0));
+    let source = TempFile::with_contents("reassembled.asm", &stdout_of(&output));
+    let reassembled = TempFile::new("reassembled.bin");
+    let output = run_cli(&["assemble", source.path(), reassembled.path()], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        std::fs::read(image.path()).unwrap(),
+        std::fs::read(reassembled.path()).unwrap()
+    );
+}
+
+#[test]
+fn translates_to_c() {
+    let target = TempFile::new("sieve.c");
+    let output = run_cli(&["translate", SIEVE_SOURCE_PATH, target.path()], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(std::fs::read_to_string(target.path())
+        .unwrap()
+        .contains("int main(void)"));
+}
+
+#[test]
+fn prints_the_usage_or_the_error_for_invalid_arguments() {
+    for args in &[&[][..], &["compile", SIEVE_SOURCE_PATH][..]] {
+        let output = run_cli(args, "");
+        assert_eq!(output.status.code(), Some(125));
+        assert!(stderr_of(&output).starts_with("Usage: "));
+    }
+    let output = run_cli(&["run", "--fast", SIEVE_SOURCE_PATH], "");
+    assert_eq!(output.status.code(), Some(125));
+    assert!(stderr_of(&output).contains("Invalid option '--fast'"));
+    let output = run_cli(&["translate", SIEVE_SOURCE_PATH], "");
+    assert_eq!(output.status.code(), Some(125));
+    assert!(stderr_of(&output).contains("Expected 2 file paths, found 1"));
+    let output = run_cli(&["run", "missing.asm"], "");
+    assert_eq!(output.status.code(), Some(125));
+    assert!(stderr_of(&output).contains("Cannot read file 'missing.asm'"));
+}
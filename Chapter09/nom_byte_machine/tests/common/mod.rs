@@ -0,0 +1,45 @@
+// The helpers shared by the integration tests, which do not all use every one.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+// A file in the temporary directory, unique for every test,
+// removed when it is dropped, even if the test fails.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn new(name: &str) -> TempFile {
+        TempFile(std::env::temp_dir().join(format!(
+            "nom_byte_machine_{}_{}",
+            std::process::id(),
+            name
+        )))
+    }
+
+    pub fn with_contents(name: &str, contents: &str) -> TempFile {
+        let file = TempFile::new(name);
+        std::fs::write(&file.0, contents).unwrap();
+        file
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Whether the given tool can be run, to skip the tests needing it.
+pub fn is_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
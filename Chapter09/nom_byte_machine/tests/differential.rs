@@ -0,0 +1,132 @@
+use nom_byte_machine::device::BufferDevice;
+use nom_byte_machine::fault::Fault;
+use nom_byte_machine::instructions::OPCODES;
+use nom_byte_machine::{assembler, emulator, parsing_interpreter};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const STEP_LIMIT: u64 = 1000;
+const RANDOM_PROGRAM_COUNT: u64 = 5000;
+
+// Runs the program with both the emulator and the parsing interpreter,
+// which must report the same result and write the same output.
+fn run_both(program: &[u8], input: &str) -> (Result<u8, Fault>, Vec<u8>) {
+    let mut emulator_device = BufferDevice::new(input);
+    let emulated = emulator::execute_program_with_device(program, &mut emulator_device, STEP_LIMIT);
+    let mut interpreter_device = BufferDevice::new(input);
+    let interpreted = parsing_interpreter::parse_program(program).and_then(|mut parsed| {
+        parsing_interpreter::execute_parsed_program_with_device(
+            &mut parsed,
+            &mut interpreter_device,
+            STEP_LIMIT,
+        )
+    });
+    assert_eq!(emulated, interpreted, "{:?}", program);
+    assert_eq!(
+        emulator_device.output(),
+        interpreter_device.output(),
+        "{:?}",
+        program
+    );
+    (emulated, emulator_device.output().to_vec())
+}
+
+fn run_source(source: &str) -> (Result<u8, Fault>, Vec<u8>) {
+    run_both(&assembler::assemble(source).unwrap(), "")
+}
+
+#[test]
+fn stores_into_the_operands_of_instructions_change_them() {
+    // The word 7 is stored into the operand of the Terminate instruction at address 8.
+    assert_eq!(
+        run_source("process size 12\nset 7\nstore 9\nterminate 0"),
+        (Ok(7), vec![])
+    );
+}
+
+#[test]
+fn stores_into_the_opcodes_of_instructions_change_them() {
+    // The Set instruction at address 8 becomes a Terminate instruction.
+    assert_eq!(
+        run_source("set 0\nstore byte 8\nset 3\nterminate 1"),
+        (Ok(3), vec![])
+    );
+}
+
+#[test]
+fn loaded_words_may_overlap_instructions() {
+    // The word at address 2 contains the opcode and the first byte of the operand of "load 2".
+    assert_eq!(
+        run_source("load 2\nstore text\nset text\noutput 2\nterminate 0\ntext: word 0"),
+        (Ok(0), vec![2, 2])
+    );
+}
+
+#[test]
+fn stored_data_can_be_executed() {
+    // The word 1280 contains the bytes of "terminate 5".
+    assert_eq!(
+        run_source("set 1280\nstore code\njump code\ncode: word 0"),
+        (Ok(5), vec![])
+    );
+}
+
+#[test]
+fn input_can_overwrite_code() {
+    // The text "\0\x09" read by the input instruction replaces "terminate 0" by "terminate 9".
+    let program = assembler::assemble("set code\ninput 2\ncode: terminate 0").unwrap();
+    assert_eq!(run_both(&program, "\u{0}\u{9}\n"), (Ok(9), vec![]));
+}
+
+#[test]
+fn invalid_instructions_are_faults_only_if_executed() {
+    assert_eq!(
+        run_source("jump end\nbyte 99\nend: terminate 4"),
+        (Ok(4), vec![])
+    );
+    assert_eq!(
+        run_source("jump bad\nterminate 4\nbad: byte 99"),
+        (Err(Fault::InvalidOpcode(7, 99)), vec![])
+    );
+}
+
+// Generates a small program made mostly of instructions
+// accessing the process and jumping inside it,
+// so that they often overwrite the code and execute the data.
+fn random_program(rng: &mut StdRng) -> Vec<u8> {
+    let process_size: u16 = rng.gen_range(4, 64);
+    let image_size = rng.gen_range(2, process_size as usize + 1);
+    let mut program = process_size.to_le_bytes().to_vec();
+    while program.len() < image_size {
+        if rng.gen_bool(0.1) {
+            program.push(rng.gen());
+            continue;
+        }
+        let opcode = rng.gen_range(0, OPCODES.len() as u8);
+        program.push(opcode);
+        match OPCODES[opcode as usize].name {
+            "Input" | "Output" => program.push(rng.gen_range(0, 8)),
+            "Terminate" => program.push(rng.gen()),
+            "Set" => program.extend_from_slice(&rng.gen::<u16>().to_le_bytes()),
+            // Some accesses are just outside the process.
+            _ => {
+                let address: u16 = rng.gen_range(0, process_size + 4);
+                program.extend_from_slice(&address.to_le_bytes());
+            }
+        }
+    }
+    program.truncate(image_size);
+    program
+}
+
+#[test]
+fn random_programs_behave_as_in_the_emulator() {
+    for seed in 0..RANDOM_PROGRAM_COUNT {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let program = random_program(&mut rng);
+        let input: String = (0..rng.gen_range(0, 4))
+            .map(|line| format!("line {}\n", line))
+            .collect();
+        let _ = run_both(&program, &input);
+    }
+}
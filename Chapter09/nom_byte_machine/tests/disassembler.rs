@@ -0,0 +1,120 @@
+use nom_byte_machine::assembler;
+use nom_disassembler::disassembly_program;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const RANDOM_PROGRAM_COUNT: u64 = 2000;
+
+// Disassembles the program, checking that the listing assembles to the same image.
+fn disassemble(program: &[u8]) -> String {
+    let listing = disassembly_program(program).unwrap();
+    assert_eq!(
+        assembler::assemble(&listing).as_deref(),
+        Ok(program),
+        "{}",
+        listing
+    );
+    listing
+}
+
+#[test]
+fn the_sieve_gets_symbolic_labels() {
+    let sieve = assembler::assemble(include_str!("../programs/sieve.asm")).unwrap();
+    let listing = disassemble(&sieve);
+    assert!(listing.starts_with("process size 699\n    2: set D_011C\n"));
+    assert!(listing.contains("L_0091:\n  145: load D_0118\n"));
+    assert!(listing.contains("  142: jump L_0070\n"));
+    assert!(listing.contains("D_0123:\n  291: word 10\n"));
+    assert!(listing.contains("D_011C:\n  284: array 5\n"));
+}
+
+#[test]
+fn unreachable_bytes_are_data() {
+    let program = assembler::assemble(
+        "jump start\nbyte 99\nbyte 1\nstart: load value\nterminate 0\nvalue: word 1000",
+    )
+    .unwrap();
+    assert_eq!(
+        disassemble(&program),
+        "process size 14
+    2: jump L_0007
+    5: byte 99
+    6: byte 1
+L_0007:
+    7: load D_000C
+   10: terminate 0
+D_000C:
+   12: word 1000
+"
+    );
+}
+
+#[test]
+fn data_beyond_the_image_is_shown_as_arrays() {
+    let program = assembler::assemble(
+        "load value\nstore byte flag\nterminate 0\narray 3\nvalue: array 2\nflag: array 10",
+    )
+    .unwrap();
+    assert_eq!(
+        disassemble(&program),
+        "process size 25
+    2: load D_000D
+    5: store byte D_000F
+    8: terminate 0
+   10: array 3
+D_000D:
+   13: array 2
+D_000F:
+   15: array 10
+"
+    );
+}
+
+#[test]
+fn set_operands_inside_the_data_get_labels() {
+    // The operands beyond the process, or at some code, stay numeric.
+    let program =
+        assembler::assemble("set buffer\ninput 3\nset 1000\nset 2\nterminate 0\nbuffer: array 3")
+            .unwrap();
+    assert_eq!(
+        disassemble(&program),
+        "process size 18
+    2: set D_000F
+    5: input 3
+    7: set 1000
+   10: set 2
+   13: terminate 0
+D_000F:
+   15: array 3
+"
+    );
+}
+
+#[test]
+fn jumps_into_instructions_stay_numeric() {
+    // The operand of "set 1280" contains the bytes of "terminate 5".
+    let program = assembler::assemble("set 1280\njump 3").unwrap();
+    assert_eq!(
+        disassemble(&program),
+        "process size 8\n    2: set 1280\n    5: jump 3\n"
+    );
+}
+
+#[test]
+fn random_programs_are_assembled_again() {
+    for seed in 0..RANDOM_PROGRAM_COUNT {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let process_size: u16 = rng.gen_range(2, 80);
+        let image_size = rng.gen_range(2, process_size as usize + 1);
+        let mut program = process_size.to_le_bytes().to_vec();
+        // Small bytes are often opcodes, or addresses inside the process.
+        while program.len() < image_size {
+            program.push(if rng.gen_bool(0.8) {
+                rng.gen_range(0, process_size.min(256) as u8)
+            } else {
+                rng.gen()
+            });
+        }
+        disassemble(&program);
+    }
+}
@@ -0,0 +1,143 @@
+use nom_byte_machine::device::{BufferDevice, IoDevice};
+use nom_byte_machine::fault::Fault;
+use nom_byte_machine::{assembler, emulator, parsing_interpreter};
+use std::io::ErrorKind;
+
+const STEP_LIMIT: u64 = 1000;
+
+// Runs the given program with both the emulator and the parsing interpreter,
+// which must report the same result.
+fn run(source: &str) -> Result<u8, Fault> {
+    let program = assembler::assemble(source).unwrap();
+    let mut device = BufferDevice::new("");
+    let emulated = emulator::execute_program_with_device(&program, &mut device, STEP_LIMIT);
+    let interpreted = parsing_interpreter::parse_program(&program).and_then(|mut parsed| {
+        parsing_interpreter::execute_parsed_program_with_device(
+            &mut parsed,
+            &mut device,
+            STEP_LIMIT,
+        )
+    });
+    assert_eq!(emulated, interpreted, "{}", source);
+    emulated
+}
+
+#[test]
+fn valid_programs_terminate() {
+    assert_eq!(
+        run("load value\ndivide value\nterminate 3\nvalue: word 7"),
+        Ok(3)
+    );
+}
+
+#[test]
+fn invalid_opcodes_are_faults() {
+    assert_eq!(run("byte 99"), Err(Fault::InvalidOpcode(2, 99)));
+    assert_eq!(
+        run("set 1\nbyte 24\nterminate 0"),
+        Err(Fault::InvalidOpcode(5, 24))
+    );
+}
+
+#[test]
+fn accesses_outside_the_process_are_faults() {
+    assert_eq!(
+        run("process size 10\nload 60000\nterminate 0"),
+        Err(Fault::OutOfBounds(2, 60000))
+    );
+    assert_eq!(
+        run("process size 10\nset 1\nstore 9\nterminate 0"),
+        Err(Fault::OutOfBounds(5, 10))
+    );
+    assert_eq!(
+        run("process size 10\nindirect load byte pointer\nterminate 0\npointer: word 10"),
+        Err(Fault::OutOfBounds(2, 10))
+    );
+    assert_eq!(
+        run("process size 20\nset 8\ninput 200\nterminate 0"),
+        Err(Fault::OutOfBounds(5, 20))
+    );
+    assert_eq!(
+        run("set 30\noutput 1\nterminate 0"),
+        Err(Fault::OutOfBounds(5, 30))
+    );
+    assert_eq!(
+        run("jump 500\nterminate 0"),
+        Err(Fault::OutOfBounds(500, 500))
+    );
+    assert_eq!(run("set 1"), Err(Fault::OutOfBounds(5, 5)));
+}
+
+#[test]
+fn divisions_by_zero_are_faults() {
+    assert_eq!(
+        run("load one\ndivide zero\nterminate 0\none: word 1\nzero: word 0"),
+        Err(Fault::DivisionByZero(5))
+    );
+    assert_eq!(
+        run("load one\nremainder zero\nterminate 0\none: word 1\nzero: word 0"),
+        Err(Fault::DivisionByZero(5))
+    );
+}
+
+#[test]
+fn endless_programs_exceed_the_step_limit() {
+    assert_eq!(
+        run("set 1\nloop: jump loop\nterminate 0"),
+        Err(Fault::StepLimitExceeded(5, STEP_LIMIT))
+    );
+}
+
+#[test]
+fn invalid_images_are_rejected() {
+    assert_eq!(
+        emulator::execute_program(&[7]),
+        Err(Fault::MissingProcessSize)
+    );
+    assert_eq!(
+        emulator::execute_program(&[3, 0, 0, 0]),
+        Err(Fault::ProcessTooSmall(3, 4))
+    );
+    assert_eq!(
+        parsing_interpreter::parse_program(&[3, 0, 0, 0]).unwrap_err(),
+        (Fault::ProcessTooSmall(3, 4))
+    );
+}
+
+#[test]
+fn faults_describe_their_address() {
+    let fault = Fault::DivisionByZero(17);
+    assert_eq!(fault.ip(), Some(17));
+    assert_eq!(fault.to_string(), "Division by zero at address 17.");
+    assert_eq!(Fault::MissingProcessSize.ip(), None);
+}
+
+struct BrokenDevice;
+
+impl IoDevice for BrokenDevice {
+    fn read_line(&mut self) -> std::io::Result<Vec<u8>> {
+        Err(ErrorKind::UnexpectedEof.into())
+    }
+    fn write(&mut self, _bytes: &[u8]) -> std::io::Result<()> {
+        Err(ErrorKind::BrokenPipe.into())
+    }
+}
+
+#[test]
+fn device_errors_are_faults() {
+    let program = assembler::assemble("set 0\noutput 1\ninput 1\nterminate 0").unwrap();
+    assert_eq!(
+        emulator::execute_program_with_device(&program, &mut BrokenDevice, STEP_LIMIT),
+        Err(Fault::DeviceFailure(5, ErrorKind::BrokenPipe))
+    );
+    let program = assembler::assemble("set 0\ninput 1\nterminate 0").unwrap();
+    let mut parsed_program = parsing_interpreter::parse_program(&program).unwrap();
+    assert_eq!(
+        parsing_interpreter::execute_parsed_program_with_device(
+            &mut parsed_program,
+            &mut BrokenDevice,
+            STEP_LIMIT
+        ),
+        Err(Fault::DeviceFailure(5, ErrorKind::UnexpectedEof))
+    );
+}
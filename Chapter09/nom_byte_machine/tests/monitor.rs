@@ -0,0 +1,100 @@
+use nom_byte_machine::device::BufferDevice;
+use nom_byte_machine::fault::Fault;
+use nom_byte_machine::monitor::{self, Monitor};
+use nom_byte_machine::{assembler, emulator};
+
+const COUNTDOWN: &str = "    load count
+loop:
+    subtract one
+    store count
+    jump if positive loop
+    terminate 4
+count: word 3
+one: word 1
+";
+
+// Runs the given program under the monitor, with the given commands,
+// returning the result and the output of the monitor.
+fn monitor(source: &str, commands: &str) -> (Result<Option<u8>, Fault>, String) {
+    let program = assembler::assemble(source).unwrap();
+    let mut output = Vec::<u8>::new();
+    let mut device = BufferDevice::new("");
+    let result = Monitor::new(&mut commands.lines(), &mut output, &mut device).run(&program);
+    (result, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn instructions_are_stepped() {
+    let (result, output) = monitor(COUNTDOWN, "s\ns 2\nr\nq\n");
+    assert_eq!(result, Ok(None));
+    let expected = "    2: load 16 | acc: 0\n(monitor)     5: subtract 18 | acc: 3\n\
+        (monitor)    11: jump if positive 5 | acc: 2\n(monitor) ip: 11, acc: 2 (2)\n\
+        (monitor) Program stopped by the monitor.\n";
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn breakpoints_and_watchpoints_stop_the_program() {
+    let (result, output) = monitor(COUNTDOWN, "b 14\nw 0x10\nc\nc\nc\nc\n");
+    assert_eq!(result, Ok(Some(4)));
+    assert!(output.contains("Breakpoint at address 14.\n"));
+    assert!(output.contains("Watching address 16, containing 3.\n"));
+    for (old_value, new_value) in [(3, 2), (2, 1), (1, 0)] {
+        assert!(output.contains(&format!(
+            "Watchpoint at address 16: {} -> {}, written by the instruction at address 8.\n   \
+            11: jump if positive 5 | acc: {}\n",
+            old_value, new_value, new_value
+        )));
+    }
+    assert!(output.contains("   14: terminate 4 | acc: 0\n"));
+    assert!(output.ends_with("Program terminated with return code 4.\n"));
+}
+
+#[test]
+fn memory_is_dumped() {
+    let (result, output) = monitor(COUNTDOWN, "x 0 20\nx 0x10 3\nx 99\ndump 3 many\nd 3\nfoo\n");
+    assert_eq!(result, Ok(Some(4)));
+    assert!(output.contains(
+        "    0: 14 00 02 10 00 09 12 00 03 10 00 10 05 00 00 04 |................|\n   \
+        16: 03 00 01 00                                     |....|\n"
+    ));
+    assert!(output.contains("   16: 03 00 01 "));
+    assert!(output.contains("Address 99 is outside the process.\n"));
+    assert!(output.contains("Invalid length 'many'.\n"));
+    assert!(output.contains("No breakpoint at address 3.\n"));
+    assert!(output.contains("Unknown command. Type 'help' for the list of commands.\n"));
+}
+
+#[test]
+fn faults_stop_the_monitored_program_for_inspection() {
+    let source = "set 7\ndivide zero\nterminate 0\nzero: word 0";
+    let (result, output) = monitor(source, "c\nr\nx 10 2\nc\nr\n");
+    assert_eq!(result, Err(Fault::DivisionByZero(5)));
+    assert!(output.contains(
+        "Fault: Division by zero at address 5.\n    5: divide 10 | acc: 7\n\
+        (monitor) ip: 5, acc: 7 (7)\n(monitor)    10: 00 00 "
+    ));
+    // Resuming the program ends it, without reading the following commands.
+    assert!(output.ends_with("|..|\n(monitor) "));
+    assert_eq!(output.matches("ip: 5").count(), 1);
+}
+
+#[test]
+fn executions_are_counted_per_address() {
+    let program = assembler::assemble(COUNTDOWN).unwrap();
+    let (result, trace) = monitor::trace_program(&program, &mut BufferDevice::new(""), u64::MAX);
+    assert_eq!(result, emulator::execute_program(&program));
+    assert_eq!(trace.total(), 11);
+    assert_eq!(trace.count(5), 3);
+    assert_eq!(trace.count(16), 0);
+    let mut csv = Vec::<u8>::new();
+    trace.export(&mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "address,count,instruction\n2,1,load 16\n5,3,subtract 18\n8,3,store 16\n\
+        11,3,jump if positive 5\n14,1,terminate 4\n"
+    );
+    let (result, trace) = monitor::trace_program(&program, &mut BufferDevice::new(""), 5);
+    assert_eq!(result, Err(Fault::StepLimitExceeded(8, 5)));
+    assert_eq!(trace.total(), 5);
+}
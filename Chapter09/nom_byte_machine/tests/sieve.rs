@@ -0,0 +1,93 @@
+use nom_byte_machine::device::{BufferDevice, FileDevice};
+use nom_byte_machine::{assembler, emulator, monitor, parsing_interpreter};
+
+// The sieve keeps a byte for every number below the limit.
+const MAX_LIMIT: u16 = 400;
+
+fn sieve() -> Vec<u8> {
+    assembler::assemble(include_str!("../programs/sieve.asm")).unwrap()
+}
+
+// The primes below the limit, right-aligned in fields of 5 characters.
+fn expected_output(limit: u16) -> String {
+    (2..limit)
+        .filter(|&n| (2..n).all(|d| n % d != 0))
+        .map(|prime| format!("{:>5}", prime))
+        .collect()
+}
+
+#[test]
+fn the_emulator_prints_the_primes() {
+    for limit in [0, 2, 3, 30, 100, 257, MAX_LIMIT] {
+        let mut device = BufferDevice::new(&format!("{}\n", limit));
+        assert_eq!(
+            emulator::execute_program_with_device(&sieve(), &mut device, u64::MAX),
+            Ok(0)
+        );
+        assert_eq!(device.output_text(), expected_output(limit), "{}", limit);
+    }
+}
+
+#[test]
+fn the_parsing_interpreter_prints_the_primes() {
+    for limit in [2, 30, 199, MAX_LIMIT] {
+        let mut device = BufferDevice::new(&format!("{}\n", limit));
+        let mut parsed_program = parsing_interpreter::parse_program(&sieve()).unwrap();
+        assert_eq!(
+            parsing_interpreter::execute_parsed_program_with_device(
+                &mut parsed_program,
+                &mut device,
+                u64::MAX
+            ),
+            Ok(0)
+        );
+        assert_eq!(device.output_text(), expected_output(limit), "{}", limit);
+    }
+}
+
+#[test]
+fn the_traced_sieve_prints_the_primes() {
+    let mut device = BufferDevice::new("50\n");
+    let (result, trace) = monitor::trace_program(&sieve(), &mut device, u64::MAX);
+    assert_eq!(result, Ok(0));
+    assert_eq!(device.output(), expected_output(50).as_bytes());
+    // The input instruction is executed once.
+    assert_eq!(trace.count(5), 1);
+}
+
+// The input stops at the first character that is not a digit,
+// and at the end of the buffer of 5 characters.
+#[test]
+fn the_input_is_parsed_up_to_a_non_digit() {
+    for (input, limit) in [
+        ("20 primes\n", 20),
+        ("", 0),
+        ("12x4\n", 12),
+        ("0000013\n", 0),
+    ] {
+        let mut device = BufferDevice::new(input);
+        emulator::execute_program_with_device(&sieve(), &mut device, u64::MAX).unwrap();
+        assert_eq!(device.output_text(), expected_output(limit), "{:?}", input);
+    }
+}
+
+#[test]
+fn files_are_used_as_devices() {
+    let dir = std::env::temp_dir().join(format!("byte_machine_sieve_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input_path = dir.join("input.txt");
+    let output_path = dir.join("output.txt");
+    std::fs::write(&input_path, "60\n").unwrap();
+    let mut device = FileDevice::open(Some(&input_path), Some(&output_path)).unwrap();
+    assert_eq!(
+        emulator::execute_program_with_device(&sieve(), &mut device, u64::MAX),
+        Ok(0)
+    );
+    drop(device);
+    assert_eq!(
+        std::fs::read_to_string(&output_path).unwrap(),
+        expected_output(60)
+    );
+    assert!(FileDevice::open(Some(&dir.join("missing.txt")), None).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::TempFile;
+use nom_byte_machine::device::BufferDevice;
+use nom_byte_machine::{assembler, emulator, translator};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const STEP_LIMIT: u64 = 1_000_000;
+
+const JUMP_MNEMONICS: [&str; 6] = [
+    "jump if zero",
+    "jump if non-zero",
+    "jump if positive",
+    "jump if negative",
+    "jump if non-positive",
+    "jump if non-negative",
+];
+
+// Translates the program to C, and builds it with the system C compiler,
+// which must accept it as standard C without warnings.
+fn build(name: &str, program: &[u8]) -> TempFile {
+    let source = TempFile::new(&format!("{}.c", name));
+    let executable = TempFile::new(name);
+    translator::translate_program_to_c(program, source.path()).unwrap();
+    let output = Command::new("cc")
+        .args(["-std=c99", "-pedantic", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(executable.path())
+        .arg(source.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}: {}",
+        name,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    executable
+}
+
+// Runs the translated program and the emulated program with the same input.
+// They must write the same output, and exit with the same return code,
+// or with 1 after printing the same fault.
+// Without a C compiler, the comparison is skipped.
+fn compare(name: &str, program: &[u8], inputs: &[&str]) {
+    if !common::is_available("cc") {
+        eprintln!("Skipping the {} program: cc is not available.", name);
+        return;
+    }
+    let executable = build(name, program);
+    for input in inputs {
+        let mut child = Command::new(executable.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        let mut device = BufferDevice::new(input);
+        let emulated = emulator::execute_program_with_device(program, &mut device, STEP_LIMIT);
+        assert_eq!(output.stdout, device.output(), "{} {:?}", name, input);
+        match emulated {
+            Ok(return_code) => {
+                assert_eq!(output.status.code(), Some(i32::from(return_code)));
+            }
+            Err(fault) => {
+                assert_eq!(output.status.code(), Some(1), "{} {:?}", name, input);
+                assert_eq!(
+                    String::from_utf8_lossy(&output.stderr),
+                    format!("Fault: {}\n", fault)
+                );
+            }
+        }
+    }
+}
+
+fn compare_source(name: &str, source: &str, inputs: &[&str]) {
+    compare(name, &assembler::assemble(source).unwrap(), inputs);
+}
+
+#[test]
+fn the_sieve_matches_the_emulator() {
+    compare_source(
+        "sieve",
+        include_str!("../programs/sieve.asm"),
+        &["30\n", "1000\n", "2", ""],
+    );
+}
+
+#[test]
+fn every_jump_matches_the_emulator() {
+    // Every jump is tried on zero, on a positive value and on a negative value,
+    // writing Y if it is taken, and N otherwise.
+    let mut source = String::new();
+    for (index, mnemonic) in JUMP_MNEMONICS.iter().enumerate() {
+        for value in &[0, 1, 65535] {
+            source += &format!(
+                "set {value}\n{mnemonic} taken_{index}_{value}\nset no\njump done_{index}_{value}\n\
+                 taken_{index}_{value}: set yes\ndone_{index}_{value}: output 1\n",
+                value = value,
+                mnemonic = mnemonic,
+                index = index
+            );
+        }
+    }
+    source += "jump end\nyes: byte 89\nno: byte 78\nend: terminate 7\n";
+    compare_source("jumps", &source, &[""]);
+}
+
+#[test]
+fn data_instructions_match_the_emulator() {
+    compare_source(
+        "data",
+        "set buffer\ninput 4\noutput 4
+        load big\nmultiply big\nstore result\nset result\noutput 2
+        set 3\nsubtract big\nstore result\nset result\noutput 2
+        load big\ndivide seven\nstore result\nset result\noutput 2
+        load big\nremainder seven\nadd pointer\nstore result\nset result\noutput 2
+        load letter\nindirect store pointer\nindirect load pointer\nstore result\nset result\noutput 2
+        set 75\nindirect store byte pointer\nindirect load byte pointer\nstore byte result\nset result\noutput 2
+        load byte letter\nstore byte buffer\nset buffer\noutput 4
+        terminate 200
+        big: word 40000\nseven: word 7\nletter: word 16961\npointer: word buffer
+        buffer: array 4\nresult: word 0",
+        &["abcdefg\n", "xy\n", "", "\n"],
+    );
+}
+
+#[test]
+fn code_layout_matches_the_emulator() {
+    // The code after the data, a backward jump, and a conditional jump
+    // to an invalid instruction, which is a fault only if taken.
+    compare_source(
+        "layout",
+        "jump start\nbad: byte 99\nback: terminate 4
+        start: set buffer\ninput 1\nload byte buffer\nsubtract letter\njump if zero bad
+        jump back\nletter: word 98\nbuffer: array 2",
+        &["a\n", "b\n"],
+    );
+    // The zeros beyond the image are executed as "terminate 0".
+    compare_source("beyond", "process size 20\njump 15", &[""]);
+}
+
+#[test]
+fn faults_match_the_emulator() {
+    compare_source(
+        "division",
+        "set 1\ndivide zero\nterminate 0\nzero: word 0",
+        &[""],
+    );
+    compare_source(
+        "bounds",
+        "process size 12\nset 11\nstore 11\nterminate 0",
+        &[""],
+    );
+    compare_source(
+        "input",
+        "process size 20\nset 18\ninput 5\nterminate 0",
+        &[""],
+    );
+}
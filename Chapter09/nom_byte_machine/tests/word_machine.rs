@@ -0,0 +1,188 @@
+use nom_byte_machine::device::BufferDevice;
+use nom_byte_machine::emulator;
+use nom_byte_machine::fault::Fault;
+use nom_byte_machine::machine::Machine;
+use nom_byte_machine::word_converter::convert_word_program;
+use nom_byte_machine::word_machine::WordMachine;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const STEP_LIMIT: u64 = 1000;
+// Every word instruction becomes at most a few dozens of byte instructions.
+const BYTE_STEPS_PER_WORD_STEP: u64 = 200;
+const RANDOM_PROGRAM_COUNT: u64 = 2000;
+
+// The sieve of the word_machine_sieve project.
+const SIEVE: [u16; 200] = [
+    600, 1, 190, 6, 5, 1, 190, 3, 195, 4, 195, 9, 197, 17, 49, 4, 195, 9, 197, 9, 196, 19, 49, 2,
+    187, 10, 196, 3, 187, 4, 195, 9, 197, 8, 187, 3, 187, 2, 195, 8, 198, 3, 195, 1, 195, 9, 195,
+    15, 9, 2, 199, 3, 188, 2, 188, 9, 187, 19, 105, 1, 200, 8, 188, 3, 195, 4, 195, 15, 97, 2, 188,
+    8, 188, 3, 189, 9, 187, 19, 97, 1, 200, 8, 189, 3, 195, 2, 198, 5, 195, 2, 189, 8, 188, 3, 189,
+    13, 75, 2, 188, 8, 198, 3, 188, 13, 53, 2, 199, 3, 188, 2, 188, 9, 187, 19, 185, 1, 200, 8,
+    188, 3, 195, 4, 195, 15, 177, 2, 188, 3, 189, 1, 195, 3, 195, 2, 195, 9, 198, 3, 195, 2, 189,
+    12, 196, 8, 197, 5, 195, 2, 189, 11, 196, 3, 189, 15, 133, 1, 190, 9, 195, 14, 173, 2, 195, 9,
+    198, 3, 195, 1, 32, 5, 195, 13, 155, 1, 190, 7, 5, 2, 188, 8, 198, 3, 188, 13, 109, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 10, 48, 1, 2,
+];
+
+// The number conversion of the word_machine_convert project.
+const CONVERT: [u16; 43] = [
+    43, 1, 39, 3, 39, 2, 39, 9, 42, 3, 39, 2, 33, 12, 40, 8, 41, 5, 39, 2, 33, 11, 40, 3, 33, 15,
+    5, 1, 34, 7, 5, 0, 0, 6710, 0, 0, 0, 0, 0, 0, 10, 48, 1,
+];
+
+fn run_word_program(program: &[u16], input: &str, step_limit: u64) -> (Result<u8, Fault>, Vec<u8>) {
+    let mut device = BufferDevice::new(input);
+    let result =
+        WordMachine::load(program).and_then(|mut machine| machine.run(&mut device, step_limit));
+    (result, device.output().to_vec())
+}
+
+// Runs the program on the word machine, and its conversion on the byte machine.
+// They must write the same output, and return the same return code,
+// or both fault, at different addresses.
+fn compare(program: &[u16], input: &str, step_limit: u64) -> (Result<u8, Fault>, Vec<u8>) {
+    let (result, output) = run_word_program(program, input, step_limit);
+    let image = convert_word_program(program).unwrap();
+    let mut device = BufferDevice::new(input);
+    let converted_result = emulator::execute_program_with_device(
+        &image,
+        &mut device,
+        step_limit.saturating_mul(BYTE_STEPS_PER_WORD_STEP),
+    );
+    assert_eq!(device.output(), &output[..], "{:?}", program);
+    match result {
+        Ok(return_code) => assert_eq!(converted_result, Ok(return_code), "{:?}", program),
+        Err(_) => assert!(converted_result.is_err(), "{:?}", program),
+    }
+    (result, output)
+}
+
+#[test]
+fn the_sieve_runs_on_both_machines() {
+    let (result, output) = compare(&SIEVE, "30\n", u64::MAX);
+    assert_eq!(result, Ok(0));
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "    2    3    5    7   11   13   17   19   23   29"
+    );
+    let (result, output) = compare(&SIEVE, "400\n", u64::MAX);
+    assert_eq!(result, Ok(0));
+    assert!(output.ends_with(b"  389  397"));
+    // Without digits, the limit is zero.
+    assert_eq!(compare(&SIEVE, "", u64::MAX), (Ok(0), vec![]));
+}
+
+#[test]
+fn the_conversion_runs_on_both_machines() {
+    assert_eq!(compare(&CONVERT, "", u64::MAX), (Ok(0), b" 6710".to_vec()));
+}
+
+#[test]
+fn every_jump_runs_on_both_machines() {
+    // Every conditional jump is tried on zero, on a positive value and on a negative value,
+    // writing Y if it is taken, and N otherwise.
+    const LETTER: u16 = 300;
+    let mut program = vec![LETTER + 1];
+    for opcode in 14..20 {
+        for &value in &[0, 1, 65535] {
+            let taken = program.len() as u16 + 8;
+            program.extend_from_slice(&[1, value, opcode, taken, 1, 78, 13, taken + 2]);
+            program.extend_from_slice(&[1, 89, 3, LETTER, 1, LETTER, 7, 1]);
+        }
+    }
+    program.extend_from_slice(&[0, 7]);
+    let (result, output) = compare(&program, "", STEP_LIMIT);
+    assert_eq!(result, Ok(7));
+    assert_eq!(output, b"YNNNYYNYNNNYYNYYYN".to_vec());
+}
+
+#[test]
+fn data_instructions_run_on_both_machines() {
+    const BIG: u16 = 50;
+    const SEVEN: u16 = 51;
+    const LETTER: u16 = 52;
+    const POINTER: u16 = 53;
+    const BUFFER: u16 = 60;
+    const RESULT: u16 = 70;
+    #[rustfmt::skip]
+    let mut program = vec![
+        80,
+        1, BUFFER, 6, 6, 1, BUFFER, 7, 6,
+        2, BIG, 10, BIG, 3, RESULT,
+        1, 3, 9, BIG, 11, SEVEN, 12, SEVEN, 8, RESULT, 3, RESULT,
+        2, LETTER, 5, POINTER, 1, 0, 4, POINTER, 3, RESULT + 1,
+        1, RESULT, 7, 2,
+        1, BUFFER, 7, 6,
+        0, 200,
+    ];
+    program.resize(BIG as usize, 0);
+    program.extend_from_slice(&[40000, 7, 16961, BUFFER]);
+    for input in &["abcdefg\n", "xy\n", "", "\n"] {
+        let (result, output) = compare(&program, input, STEP_LIMIT);
+        assert_eq!(result, Ok(200));
+        assert_eq!(output.len(), 14);
+    }
+}
+
+#[test]
+fn faults_happen_on_both_machines() {
+    assert!(WordMachine::load(&[]).is_err());
+    assert!(convert_word_program(&[]).is_err());
+    assert!(WordMachine::load(&[2, 1, 1]).is_err());
+    assert!(convert_word_program(&[2, 1, 1]).is_err());
+    let run = |program: &[u16]| compare(program, "", STEP_LIMIT).0;
+    assert_eq!(run(&[5, 1, 1, 40]), Err(Fault::InvalidOpcode(3, 40)));
+    assert_eq!(run(&[5, 20, 1]), Err(Fault::InvalidOpcode(1, 20)));
+    assert_eq!(run(&[5, 0, 256]), Err(Fault::OperandTooLarge(1, 256)));
+    assert_eq!(run(&[4, 1, 1, 1]), Err(Fault::OutOfBounds(3, 4)));
+    assert_eq!(run(&[5, 2, 9, 0, 0]), Err(Fault::OutOfBounds(1, 9)));
+    assert_eq!(run(&[6, 11, 5, 0, 0, 0]), Err(Fault::DivisionByZero(1)));
+    assert_eq!(
+        run(&[6, 13, 1]),
+        Err(Fault::StepLimitExceeded(1, STEP_LIMIT))
+    );
+    assert_eq!(run(&[7, 1, 6, 7, 2, 0, 0]), Err(Fault::OutOfBounds(3, 7)));
+    // The byte address of this pointer would wrap around to the start of the memory.
+    assert_eq!(
+        run(&[6, 4, 5, 0, 0, 32768]),
+        Err(Fault::OutOfBounds(1, 32768))
+    );
+}
+
+#[test]
+fn random_programs_run_on_both_machines() {
+    // The programs have some code, ending with a terminate instruction,
+    // followed by some data.
+    // They never write their code, as it is not supported by the conversion.
+    // So, they do not use the indirect instructions and the input instruction.
+    for seed in 0..RANDOM_PROGRAM_COUNT {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let instruction_count = rng.gen_range(1, 20);
+        let data_start = 1 + 2 * (instruction_count + 1);
+        let process_size = data_start + rng.gen_range(1, 10);
+        let mut program = vec![process_size];
+        for _ in 0..instruction_count {
+            let opcode =
+                [0, 1, 2, 3, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19][rng.gen_range(0, 17)];
+            let operand = match opcode {
+                0 => rng.gen_range(0, 256),
+                1 => rng.gen(),
+                3 => rng.gen_range(data_start, process_size + 1),
+                7 => rng.gen_range(0, 4),
+                13..=19 => 1 + 2 * rng.gen_range(0, instruction_count + 1),
+                _ => rng.gen_range(0, process_size + 1),
+            };
+            program.extend_from_slice(&[opcode, operand]);
+        }
+        program.extend_from_slice(&[0, 1]);
+        while (program.len() as u16) < process_size {
+            program.push(rng.gen_range(0, 300));
+        }
+        let (result, output) = run_word_program(&program, "", STEP_LIMIT);
+        if let Err(Fault::StepLimitExceeded(_, _)) = result {
+            continue;
+        }
+        assert_eq!(compare(&program, "", STEP_LIMIT), (result, output));
+    }
+}
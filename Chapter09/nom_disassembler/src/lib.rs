@@ -0,0 +1,73 @@
+extern crate nom;
+use byte_machine_isa::{parse_instruction, Instruction, OperandKind};
+use nom::number::complete::le_u16;
+
+mod recursive_descent;
+pub use recursive_descent::disassembly_program;
+
+#[derive(Copy, Clone)]
+struct Word(u16);
+
+impl std::fmt::Debug for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}, {}", self.0, self.0 as u8, self.0 >> 8)
+    }
+}
+
+// Shows the instruction with the bytes of its word operand.
+struct DebugInstruction(Instruction);
+
+impl std::fmt::Debug for DebugInstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let operand = self.0.operand();
+        match self.0.info() {
+            Some(info) if info.operand == OperandKind::Word => {
+                write!(f, "{}({:?})", info.name, Word(operand))
+            }
+            Some(info) => write!(f, "{}({})", info.name, operand),
+            None => write!(f, "Byte({})", operand),
+        }
+    }
+}
+
+// Decodes the instructions up to the first terminate instruction,
+// and then the data bytes, with the given format for every line.
+fn disassemble(
+    program: &[u8],
+    format_line: fn(usize, Instruction) -> String,
+) -> Result<(u16, String), String> {
+    use Instruction::*;
+    let (mut rest, process_size) = le_u16::<(&[u8], nom::error::ErrorKind)>(program)
+        .map_err(|_| "The program has no process size.".to_string())?;
+    let mut listing = String::new();
+    let mut offset = 2;
+    loop {
+        let instruction = match parse_instruction(rest) {
+            Ok(instruction) => instruction,
+            Err(_) => return Err(format!("Invalid instruction at address {}.", offset)),
+        };
+        listing += &format_line(offset, instruction.1);
+        offset += instruction.1.len();
+        rest = instruction.0;
+        if let Terminate(_) = instruction.1 {
+            break;
+        }
+    }
+    for byte in rest {
+        let instr = Byte(*byte);
+        listing += &format_line(offset, instr);
+        offset += instr.len();
+    }
+    Ok((process_size, listing))
+}
+
+pub fn disassembly_program_for_debug(program: &[u8]) -> Result<String, String> {
+    let (process_size, listing) = disassemble(program, |offset, instruction| {
+        format!("{:5}: {:?}\n", offset, DebugInstruction(instruction))
+    })?;
+    Ok(format!(
+        "Program size: {}\nProcess size: {}\n",
+        program.len(),
+        process_size
+    ) + &listing)
+}
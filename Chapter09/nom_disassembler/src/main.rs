@@ -1,279 +1,4 @@
-extern crate nom;
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::combinator::map;
-use nom::number::complete::le_u16;
-use nom::number::complete::le_u8;
-use nom::sequence::preceded;
-use nom::IResult;
-
-#[derive(Copy, Clone)]
-struct Word(u16);
-
-fn le_word(input: &[u8]) -> IResult<&[u8], Word> {
-    le_u16(input).map(|(input, output)| (input, Word(output)))
-}
-
-impl std::fmt::Display for Word {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl std::fmt::Debug for Word {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}: {}, {}", self.0, self.0 as u8, self.0 >> 8)
-    }
-}
-
-impl std::fmt::Display for Instruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        use Instruction::*;
-        match self {
-            Terminate(byte) => write!(f, "terminate {}", byte),
-            Set(word) => write!(f, "set {}", word),
-            Load(word) => write!(f, "load {}", word),
-            Store(word) => write!(f, "store {}", word),
-            IndirectLoad(word) => write!(f, "indirect load {}", word),
-            IndirectStore(word) => write!(f, "indirect store {}", word),
-            Input(byte) => write!(f, "input {}", byte),
-            Output(byte) => write!(f, "output {}", byte),
-            Add(word) => write!(f, "add {}", word),
-            Subtract(word) => write!(f, "subtract {}", word),
-            Multiply(word) => write!(f, "multiply {}", word),
-            Divide(word) => write!(f, "divide {}", word),
-            Remainder(word) => write!(f, "remainder {}", word),
-            Jump(word) => write!(f, "jump {}", word),
-            JumpIfZero(word) => write!(f, "jump if zero {}", word),
-            JumpIfNonZero(word) => write!(f, "jump if non-zero {}", word),
-            JumpIfPositive(word) => write!(f, "jump if positive {}", word),
-            JumpIfNegative(word) => write!(f, "jump if negative {}", word),
-            JumpIfNonPositive(word) => write!(f, "jump if non-positive {}", word),
-            JumpIfNonNegative(word) => write!(f, "jump if non-negative {}", word),
-            LoadByte(word) => write!(f, "load byte {}", word),
-            StoreByte(word) => write!(f, "store byte {}", word),
-            IndirectLoadByte(word) => write!(f, "indirect load byte {}", word),
-            IndirectStoreByte(word) => write!(f, "indirect store byte {}", word),
-            Byte(byte) => write!(f, "data byte {}", byte),
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone)]
-enum Instruction {
-    Terminate(u8),
-    Set(Word),
-    Load(Word),
-    Store(Word),
-    IndirectLoad(Word),
-    IndirectStore(Word),
-    Input(u8),
-    Output(u8),
-    Add(Word),
-    Subtract(Word),
-    Multiply(Word),
-    Divide(Word),
-    Remainder(Word),
-    Jump(Word),
-    JumpIfZero(Word),
-    JumpIfNonZero(Word),
-    JumpIfPositive(Word),
-    JumpIfNegative(Word),
-    JumpIfNonPositive(Word),
-    JumpIfNonNegative(Word),
-    LoadByte(Word),
-    StoreByte(Word),
-    IndirectLoadByte(Word),
-    IndirectStoreByte(Word),
-    Byte(u8),
-}
-
-fn parse_terminate(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x00"), map(le_u8, Instruction::Terminate))(input)
-}
-
-fn parse_set(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x01"), map(le_word, Instruction::Set))(input)
-}
-
-fn parse_load(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x02"), map(le_word, Instruction::Load))(input)
-}
-
-fn parse_store(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x03"), map(le_word, Instruction::Store))(input)
-}
-
-fn parse_indirect_load(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x04"), map(le_word, Instruction::IndirectLoad))(input)
-}
-
-fn parse_indirect_store(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x05"), map(le_word, Instruction::IndirectStore))(input)
-}
-
-fn parse_input(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x06"), map(le_u8, Instruction::Input))(input)
-}
-
-fn parse_output(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x07"), map(le_u8, Instruction::Output))(input)
-}
-
-fn parse_add(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x08"), map(le_word, Instruction::Add))(input)
-}
-
-fn parse_subtract(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x09"), map(le_word, Instruction::Subtract))(input)
-}
-
-fn parse_multiply(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0A"), map(le_word, Instruction::Multiply))(input)
-}
-
-fn parse_divide(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0B"), map(le_word, Instruction::Divide))(input)
-}
-
-fn parse_remainder(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0C"), map(le_word, Instruction::Remainder))(input)
-}
-
-fn parse_jump(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0D"), map(le_word, Instruction::Jump))(input)
-}
-
-fn parse_jump_if_zero(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0E"), map(le_word, Instruction::JumpIfZero))(input)
-}
-
-fn parse_jump_if_nonzero(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x0F"), map(le_word, Instruction::JumpIfNonZero))(input)
-}
-
-fn parse_jump_if_positive(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x10"), map(le_word, Instruction::JumpIfPositive))(input)
-}
-
-fn parse_jump_if_negative(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x11"), map(le_word, Instruction::JumpIfNegative))(input)
-}
-
-fn parse_jump_if_nonpositive(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x12"), map(le_word, Instruction::JumpIfNonPositive))(input)
-}
-
-fn parse_jump_if_nonnegative(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x13"), map(le_word, Instruction::JumpIfNonNegative))(input)
-}
-
-fn parse_load_byte(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x14"), map(le_word, Instruction::LoadByte))(input)
-}
-
-fn parse_store_byte(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x15"), map(le_word, Instruction::StoreByte))(input)
-}
-
-fn parse_indirect_load_byte(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x16"), map(le_word, Instruction::IndirectLoadByte))(input)
-}
-
-fn parse_indirect_store_byte(input: &[u8]) -> IResult<&[u8], Instruction> {
-    preceded(tag("\x17"), map(le_word, Instruction::IndirectStoreByte))(input)
-}
-
-fn parse_instruction(input: &[u8]) -> IResult<&[u8], Instruction> {
-    alt((
-        alt((
-            parse_terminate,
-            parse_set,
-            parse_load,
-            parse_store,
-            parse_indirect_load,
-            parse_indirect_store,
-            parse_input,
-            parse_output,
-            parse_add,
-            parse_subtract,
-            parse_multiply,
-            parse_divide,
-            parse_remainder,
-            parse_jump,
-            parse_jump_if_zero,
-            parse_jump_if_nonzero,
-            parse_jump_if_positive,
-            parse_jump_if_negative,
-            parse_jump_if_nonpositive,
-            parse_jump_if_nonnegative,
-        )),
-        alt((
-            parse_load_byte,
-            parse_store_byte,
-            parse_indirect_load_byte,
-            parse_indirect_store_byte,
-        )),
-    ))(input)
-}
-
-impl Instruction {
-    fn len(self) -> usize {
-        use Instruction::*;
-        match self {
-            Byte(_) => 1,
-            Terminate(_) | Input(_) | Output(_) => 2,
-            _ => 3,
-        }
-    }
-}
-
-fn disassembly_program_for_debug(program: &[u8]) -> IResult<&[u8], ()> {
-    use Instruction::*;
-    println!("Program size: {}", program.len());
-    let parsed_process_size = le_u16(program)?;
-    println!("Process size: {}", parsed_process_size.1);
-    let mut rest = parsed_process_size.0;
-    let mut offset = 2;
-    loop {
-        let instruction = parse_instruction(rest)?;
-        println!("{:5}: {:?}", offset, instruction.1);
-        offset += instruction.1.len();
-        rest = instruction.0;
-        if let Terminate(_) = instruction.1 {
-            break;
-        }
-    }
-    for byte in rest {
-        let instr = Byte(*byte);
-        println!("{:5}: {:?}", offset, instr);
-        offset += instr.len();
-    }
-    Ok((b"", ()))
-}
-
-fn disassembly_program(program: &[u8]) -> IResult<&[u8], ()> {
-    use Instruction::*;
-    let parsed_process_size = le_u16(program)?;
-    println!("process size {}", parsed_process_size.1);
-    let mut rest = parsed_process_size.0;
-    let mut offset = 2;
-    loop {
-        let instruction = parse_instruction(rest)?;
-        println!("{:5}: {}", offset, instruction.1);
-        offset += instruction.1.len();
-        rest = instruction.0;
-        if let Terminate(_) = instruction.1 {
-            break;
-        }
-    }
-    for byte in rest {
-        let instr = Byte(*byte);
-        println!("{:5}: {}", offset, instr);
-        offset += instr.len();
-    }
-    Ok((b"", ()))
-}
+use nom_disassembler::{disassembly_program, disassembly_program_for_debug};
 
 fn main() {
     let prog = vec![
@@ -408,8 +133,14 @@ fn main() {
            // 43, 1: primes: array 400
     ];
     println!("FOR DEBUG");
-    let _ = disassembly_program_for_debug(&prog);
+    match disassembly_program_for_debug(&prog) {
+        Ok(listing) => print!("{}", listing),
+        Err(err) => eprintln!("{}", err),
+    }
     println!();
     println!("FOR ASSEMBLING");
-    let _ = disassembly_program(&prog);
+    match disassembly_program(&prog) {
+        Ok(listing) => print!("{}", listing),
+        Err(err) => eprintln!("{}", err),
+    }
 }
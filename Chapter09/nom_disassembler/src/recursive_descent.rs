@@ -0,0 +1,199 @@
+use byte_machine_isa::{parse_instruction, Instruction};
+use nom::number::complete::le_u16;
+use std::collections::{BTreeMap, BTreeSet};
+
+// The code starts after the word containing the process size.
+const ENTRY_POINT: usize = 2;
+
+// The shortest run of zeros shown as an array.
+const MIN_ARRAY_SIZE: usize = 4;
+
+// How the operand of an instruction is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Reference {
+    Value,
+    Code,
+    // The operand of a set instruction, which may be the address of some data.
+    Address,
+    Byte,
+    Word,
+}
+
+fn reference(instruction: Instruction) -> Reference {
+    use Instruction::*;
+    match instruction {
+        Terminate(_) | Input(_) | Output(_) | Byte(_) => Reference::Value,
+        Set(_) => Reference::Address,
+        Jump(_) | JumpIfZero(_) | JumpIfNonZero(_) | JumpIfPositive(_) | JumpIfNegative(_)
+        | JumpIfNonPositive(_) | JumpIfNonNegative(_) => Reference::Code,
+        LoadByte(_) | StoreByte(_) => Reference::Byte,
+        // The indirect instructions access a word containing an address.
+        Load(_) | Store(_) | IndirectLoad(_) | IndirectStore(_) | Add(_) | Subtract(_)
+        | Multiply(_) | Divide(_) | Remainder(_) | IndirectLoadByte(_) | IndirectStoreByte(_) => {
+            Reference::Word
+        }
+    }
+}
+
+// Decodes the instructions reachable from the entry point, following the jumps.
+// A path ends at an invalid instruction, or at an instruction not contained in the image,
+// as the bytes beyond the image are not known to the listing.
+fn find_code(image: &[u8]) -> BTreeMap<usize, Instruction> {
+    byte_machine_isa::find_code(ENTRY_POINT as u16, |address| {
+        let bytes = image.get(address as usize..).unwrap_or(&[]);
+        parse_instruction(bytes).map(|(_, instruction)| (instruction, instruction.len() as u16))
+    })
+    .into_iter()
+    .filter_map(|(address, instruction)| Some((address as usize, instruction.ok()?)))
+    .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Statement {
+    Instruction(Instruction),
+    Word(u16),
+    Byte(u8),
+    Array(usize),
+}
+
+// Splits the image into statements, starting from the entry point.
+// The data statements never cover the address of a label or of some code,
+// but an instruction may cover the code starting inside it,
+// which is reached only by jumps that are left numeric.
+// The data beyond the image is shown as arrays, ending at the labels and at the process size.
+fn lay_out(
+    image: &[u8],
+    process_size: usize,
+    code: &BTreeMap<usize, Instruction>,
+    data: &BTreeMap<usize, Reference>,
+) -> Vec<(usize, Statement)> {
+    let is_boundary = |address: usize| code.contains_key(&address) || data.contains_key(&address);
+    let mut statements = Vec::new();
+    let mut address = ENTRY_POINT;
+    while address < image.len() {
+        let statement = if let Some(&instruction) = code.get(&address) {
+            Statement::Instruction(instruction)
+        } else if data.get(&address) == Some(&Reference::Word)
+            && address + 1 < image.len()
+            && !is_boundary(address + 1)
+        {
+            Statement::Word(u16::from(image[address]) + (u16::from(image[address + 1]) << 8))
+        } else if image[address] == 0 {
+            // The zeros at the end of the image are not arrays,
+            // as the assembler does not emit the arrays at the end of the program.
+            match (address + 1..image.len()).find(|&a| image[a] != 0 || is_boundary(a)) {
+                Some(end) if end - address >= MIN_ARRAY_SIZE => Statement::Array(end - address),
+                _ => Statement::Byte(0),
+            }
+        } else {
+            Statement::Byte(image[address])
+        };
+        statements.push((address, statement));
+        address += match statement {
+            Statement::Instruction(instruction) => instruction.len(),
+            Statement::Word(_) => 2,
+            Statement::Byte(_) => 1,
+            Statement::Array(size) => size,
+        };
+    }
+    let mut ends: Vec<usize> = data
+        .keys()
+        .copied()
+        .filter(|&label| label >= address && label < process_size)
+        .collect();
+    if !ends.is_empty() {
+        ends.push(process_size);
+    }
+    for end in ends {
+        if end > address {
+            statements.push((address, Statement::Array(end - address)));
+            address = end;
+        }
+    }
+    statements
+}
+
+fn code_label(address: usize) -> String {
+    format!("L_{:04X}", address)
+}
+
+fn data_label(address: usize) -> String {
+    format!("D_{:04X}", address)
+}
+
+// Disassembles the code reachable from the entry point,
+// and shows the rest of the image as data.
+// The jump targets and the accessed data get symbolic labels,
+// where statements start.
+// The listing can be assembled again, producing the same image.
+pub fn disassembly_program(program: &[u8]) -> Result<String, String> {
+    let (_, process_size) = le_u16::<(&[u8], nom::error::ErrorKind)>(program)
+        .map_err(|_| "The program has no process size.".to_string())?;
+    let process_size = process_size as usize;
+    let code = find_code(program);
+    let mut data = BTreeMap::<usize, Reference>::new();
+    for &instruction in code.values() {
+        let reference = reference(instruction);
+        let address = instruction.operand() as usize;
+        // The set operands are taken as addresses only if they are in the process,
+        // and they are not code.
+        let is_data = match reference {
+            Reference::Value | Reference::Code => false,
+            Reference::Address => {
+                address >= ENTRY_POINT && address < process_size && !code.contains_key(&address)
+            }
+            Reference::Byte | Reference::Word => true,
+        };
+        if is_data {
+            let entry = data.entry(address).or_insert(reference);
+            *entry = (*entry).max(reference);
+        }
+    }
+    let statements = lay_out(program, process_size, &code, &data);
+
+    let starts: BTreeSet<usize> = statements.iter().map(|&(address, _)| address).collect();
+    let jump_targets: BTreeSet<usize> = code
+        .values()
+        .filter(|&&instruction| reference(instruction) == Reference::Code)
+        .map(|instruction| instruction.operand() as usize)
+        .filter(|target| code.contains_key(target) && starts.contains(target))
+        .collect();
+    let data_labels: BTreeSet<usize> = data
+        .keys()
+        .copied()
+        .filter(|address| starts.contains(address))
+        .collect();
+    let operand = |instruction: Instruction| {
+        let address = instruction.operand() as usize;
+        match reference(instruction) {
+            Reference::Code if jump_targets.contains(&address) => code_label(address),
+            Reference::Address | Reference::Byte | Reference::Word
+                if data_labels.contains(&address) =>
+            {
+                data_label(address)
+            }
+            _ => address.to_string(),
+        }
+    };
+
+    let mut listing = format!("process size {}\n", process_size);
+    for (address, statement) in statements {
+        if jump_targets.contains(&address) {
+            listing += &format!("{}:\n", code_label(address));
+        }
+        if data_labels.contains(&address) {
+            listing += &format!("{}:\n", data_label(address));
+        }
+        let text = match statement {
+            Statement::Instruction(instruction) => match instruction.info() {
+                Some(info) => format!("{} {}", info.mnemonic, operand(instruction)),
+                None => instruction.to_string(),
+            },
+            Statement::Word(word) => format!("word {}", word),
+            Statement::Byte(byte) => format!("byte {}", byte),
+            Statement::Array(size) => format!("array {}", size),
+        };
+        listing += &format!("{:5}: {}\n", address, text);
+    }
+    Ok(listing)
+}